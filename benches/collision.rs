@@ -0,0 +1,55 @@
+//! How the collision grid scales: broad phase (`update_grid`), narrow phase
+//! (`collision_checks`), spawning throughput and a whole tick, from 10 to 10000 colliders.
+//!
+//! cargo bench --features bench --bench collision
+
+use bevy::ecs::schedule::ScheduleLabel;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+
+use space_race::bench::{collision_app, headless_app, spawn_asteroids, BroadPhase, FullTick, NarrowPhase};
+
+const COLLIDERS: [usize; 4] = [10, 100, 1_000, 10_000];
+
+fn run_schedule(c: &mut Criterion, group_name: &str, label: impl ScheduleLabel + Clone) {
+    let mut group = c.benchmark_group(group_name);
+
+    for colliders in COLLIDERS {
+        let mut app = collision_app(colliders);
+
+        group.throughput(Throughput::Elements(colliders as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(colliders), &colliders, |b, _| {
+            b.iter(|| app.world.run_schedule(label.clone()))
+        });
+    }
+
+    group.finish();
+}
+
+fn broad_phase(c: &mut Criterion) {
+    run_schedule(c, "broad_phase", BroadPhase);
+}
+
+fn narrow_phase(c: &mut Criterion) {
+    run_schedule(c, "narrow_phase", NarrowPhase);
+}
+
+fn full_tick(c: &mut Criterion) {
+    run_schedule(c, "full_tick", FullTick);
+}
+
+fn spawning(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spawn_asteroids");
+
+    for count in COLLIDERS {
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, &count| {
+            // A fresh world every batch, so later batches don't spawn into a bigger one
+            b.iter_batched_ref(headless_app, |app| spawn_asteroids(app, count), BatchSize::LargeInput)
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, broad_phase, narrow_phase, full_tick, spawning);
+criterion_main!(benches);
@@ -0,0 +1,76 @@
+//! Spawn and despawn against pooling under heavy fire: every frame the rockets from the
+//! last one are let go of and a new volley is fired.
+//!
+//! cargo bench --features bench --bench pooling
+
+use bevy::prelude::*;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use space_race::{
+    game_assets::GameAssets,
+    pool::{recycle, EntityPools, PoolKind},
+    ship::{Rocket, RocketBundle},
+    weapon::ProjectileSpec
+};
+
+const VOLLEYS: [usize; 3] = [50, 200, 1000];
+
+#[derive(Resource)]
+struct Volley(usize);
+
+fn heavy_fire(
+    mut commands: Commands,
+    volley: Res<Volley>,
+    game_assets: Res<GameAssets>,
+    mut pools: ResMut<EntityPools>,
+    rocket_query: Query<Entity, With<Rocket>>,
+) {
+    for entity in rocket_query.iter() {
+        commands.entity(entity).add(recycle);
+    }
+
+    let projectile = ProjectileSpec::default();
+
+    for i in 0..volley.0 {
+        let bundle = RocketBundle::new(&game_assets, &projectile, Vec3::new(i as f32, 0.0, 0.0), Vec2::Y);
+
+        pools.spawn(&mut commands, PoolKind::Rocket, bundle);
+    }
+}
+
+fn app(pooled: bool, volley: usize) -> App {
+    let mut app = App::new();
+
+    app
+        .add_plugins((MinimalPlugins, AssetPlugin::default()))
+        .init_asset::<Image>()
+        .init_asset::<TextureAtlasLayout>()
+        .init_resource::<GameAssets>()
+        .insert_resource(EntityPools::new(pooled))
+        .insert_resource(Volley(volley))
+        .add_systems(Update, heavy_fire);
+
+    // Warm up so the pool is full
+    for _ in 0..10 {
+        app.update();
+    }
+
+    app
+}
+
+fn pooling(c: &mut Criterion) {
+    let mut group = c.benchmark_group("heavy_fire");
+
+    for volley in VOLLEYS {
+        for (name, pooled) in [("spawn_despawn", false), ("pooled", true)] {
+            let mut app = app(pooled, volley);
+
+            group.bench_with_input(BenchmarkId::new(name, volley), &volley, |b, _| b.iter(|| app.update()));
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, pooling);
+criterion_main!(benches);
@@ -0,0 +1,557 @@
+use std::{collections::HashMap, marker::PhantomData};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    loading::Preload,
+    pool::recycle,
+    MyGameSet
+};
+
+/// Sprite sheet animations. Clips are named and live in a RON file ending in `.anim.ron`,
+/// see `assets/animations/default.anim.ron`. Entities carry an `Animator` with the clip
+/// they are on, `PlayClip` switches it and `AnimationFinished` is sent when a `Once`
+/// clip runs out. What happens then is up to the policy components next to the animator:
+/// `DespawnOnFinish`, `RemoveComponentOnFinish<T>` and `ReturnToClip`. Looping clips
+/// never finish, so they never trigger them.
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<AnimationLibrary>()
+            .register_asset_loader(AnimationLibraryLoader)
+            .add_event::<PlayClip>()
+            .add_event::<AnimationFinished>()
+            .add_systems(Startup, load_animations)
+            .add_systems(PostUpdate, (
+                play_animations.after(crate::explosion_event_listener),
+                (despawn_on_finish, return_to_clip).after(play_animations),
+            ).in_set(MyGameSet));
+    }
+}
+
+pub const DEFAULT_ANIMATIONS: &str = "animations/default.anim.ron";
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum Frames {
+    /// First to last atlas index, both included
+    Range(usize, usize),
+    List(Vec<usize>),
+}
+
+impl Frames {
+    pub fn len(&self) -> usize {
+        match self {
+            Frames::Range(first, last) => (last + 1).saturating_sub(*first),
+            Frames::List(frames) => frames.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn atlas_index(&self, frame: usize) -> usize {
+        match self {
+            Frames::Range(first, _) => first + frame,
+            Frames::List(frames) => frames[frame],
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum PlaybackMode {
+    Loop,
+    Once,
+    PingPong,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct AnimationClip {
+    pub frames: Frames,
+    /// One duration for every frame, or one per frame
+    pub durations: Vec<f32>,
+    pub mode: PlaybackMode,
+    #[serde(default)]
+    pub next: Option<String>,
+}
+
+impl AnimationClip {
+    pub fn duration(&self, frame: usize) -> f32 {
+        if self.durations.len() == 1 {
+            self.durations[0]
+        } else {
+            self.durations[frame]
+        }
+    }
+}
+
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct AnimationLibrary {
+    pub clips: HashMap<String, AnimationClip>,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum AnimationLibraryError {
+    #[error("clip {0} has no frames")]
+    NoFrames(String),
+    #[error("clip {0} needs one positive duration, or one per frame")]
+    InvalidDurations(String),
+    #[error("clip {0} moves on to a clip that doesn't exist")]
+    UnknownNext(String),
+}
+
+impl AnimationLibrary {
+
+    pub fn validate(&self) -> Result<(), AnimationLibraryError> {
+        for (name, clip) in &self.clips {
+            if clip.frames.is_empty() {
+                return Err(AnimationLibraryError::NoFrames(name.clone()));
+            }
+
+            let counts_match = clip.durations.len() == 1 || clip.durations.len() == clip.frames.len();
+
+            if !counts_match || clip.durations.iter().any(|duration| *duration <= 0.0) {
+                return Err(AnimationLibraryError::InvalidDurations(name.clone()));
+            }
+
+            if clip.next.as_ref().is_some_and(|next| !self.clips.contains_key(next)) {
+                return Err(AnimationLibraryError::UnknownNext(name.clone()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct Animations {
+    pub library: Handle<AnimationLibrary>,
+}
+
+/// Switches an entity to another clip from the start
+#[derive(Event, Debug)]
+pub struct PlayClip {
+    pub entity: Entity,
+    pub clip: String,
+}
+
+#[derive(Event, Debug, PartialEq)]
+pub struct AnimationFinished {
+    pub entity: Entity,
+    pub clip: String,
+}
+
+#[derive(Component, Debug, Default)]
+pub struct DespawnOnFinish;
+
+/// Takes `T` off the entity once a clip finishes. Needs `remove_component_on_finish::<T>`
+/// added for every `T` in use.
+#[derive(Component, Debug)]
+pub struct RemoveComponentOnFinish<T: Component>(PhantomData<T>);
+
+impl<T: Component> Default for RemoveComponentOnFinish<T> {
+    fn default() -> Self {
+        RemoveComponentOnFinish(PhantomData)
+    }
+}
+
+/// Clip to go back to once another finishes. Unlike a clip's `next` it can change at runtime.
+#[derive(Component, Debug)]
+pub struct ReturnToClip(pub String);
+
+#[derive(Component, Debug)]
+pub struct Animator {
+    clip: String,
+    frame: usize,
+    elapsed: f32,
+    backwards: bool,
+    finished: bool,
+}
+
+impl Animator {
+    pub fn new(clip: &str) -> Animator {
+        Animator {
+            clip: clip.to_string(),
+            frame: 0,
+            elapsed: 0.0,
+            backwards: false,
+            finished: false,
+        }
+    }
+
+    pub fn clip(&self) -> &str {
+        &self.clip
+    }
+
+    pub fn play(&mut self, clip: &str) {
+        *self = Animator::new(clip);
+    }
+
+    pub fn atlas_index(&self, clip: &AnimationClip) -> usize {
+        clip.frames.atlas_index(self.frame.min(clip.frames.len() - 1))
+    }
+
+    /// Moves through the clip, true on the tick a `Once` clip runs out
+    pub fn advance(&mut self, clip: &AnimationClip, delta: f32) -> bool {
+
+        if self.finished { return false; }
+
+        let last = clip.frames.len() - 1;
+
+        self.elapsed += delta;
+
+        while self.elapsed >= clip.duration(self.frame) {
+            self.elapsed -= clip.duration(self.frame);
+
+            match clip.mode {
+                PlaybackMode::Loop => {
+                    self.frame = if self.frame == last { 0 } else { self.frame + 1 };
+                },
+                PlaybackMode::Once => {
+                    if self.frame == last {
+                        self.finished = true;
+                        return true;
+                    }
+
+                    self.frame += 1;
+                },
+                PlaybackMode::PingPong => {
+                    if last == 0 { continue; }
+
+                    if self.frame == last { self.backwards = true; }
+                    if self.frame == 0 { self.backwards = false; }
+
+                    self.frame = if self.backwards { self.frame - 1 } else { self.frame + 1 };
+                },
+            }
+        }
+
+        false
+    }
+}
+
+fn load_animations(mut commands: Commands, asset_server: Res<AssetServer>, mut preload: ResMut<Preload>) {
+    let library = asset_server.load(DEFAULT_ANIMATIONS);
+
+    preload.track(DEFAULT_ANIMATIONS, &library);
+
+    commands.insert_resource(Animations { library });
+}
+
+pub fn play_animations(
+    time: Res<Time>,
+    animations: Res<Animations>,
+    libraries: Res<Assets<AnimationLibrary>>,
+    mut play_events: EventReader<PlayClip>,
+    mut finished_events: EventWriter<AnimationFinished>,
+    mut animator_query: Query<(Entity, &mut Animator, &mut TextureAtlas)>,
+) {
+
+    let Some(library) = libraries.get(&animations.library) else { return; };
+
+    for PlayClip { entity, clip } in play_events.read() {
+        if let Ok((_, mut animator, _)) = animator_query.get_mut(*entity) {
+            animator.play(clip);
+        }
+    }
+
+    for (entity, mut animator, mut atlas) in animator_query.iter_mut() {
+        let Some(clip) = library.clips.get(animator.clip()) else { continue; };
+
+        if animator.advance(clip, time.delta_seconds()) {
+            finished_events.send(AnimationFinished {
+                entity,
+                clip: animator.clip().to_string(),
+            });
+
+            if let Some(next) = &clip.next {
+                animator.play(next);
+            }
+        }
+
+        let Some(clip) = library.clips.get(animator.clip()) else { continue; };
+
+        let index = animator.atlas_index(clip);
+
+        // Most animators sit on one frame, leave the atlas alone unless it moves
+        if atlas.index != index {
+            atlas.index = index;
+        }
+    }
+}
+
+pub fn despawn_on_finish(
+    mut commands: Commands,
+    mut finished_events: EventReader<AnimationFinished>,
+    despawn_query: Query<(), With<DespawnOnFinish>>,
+) {
+
+    for AnimationFinished { entity, .. } in finished_events.read() {
+        if despawn_query.contains(*entity) {
+            // Pooled asteroids go back to their pool
+            commands.entity(*entity).add(recycle);
+        }
+    }
+}
+
+pub fn remove_component_on_finish<T: Component>(
+    mut commands: Commands,
+    mut finished_events: EventReader<AnimationFinished>,
+    remove_query: Query<(), (With<RemoveComponentOnFinish<T>>, With<T>)>,
+) {
+
+    for AnimationFinished { entity, .. } in finished_events.read() {
+        if remove_query.contains(*entity) {
+            commands.entity(*entity).remove::<T>();
+        }
+    }
+}
+
+pub fn return_to_clip(
+    mut finished_events: EventReader<AnimationFinished>,
+    mut animator_query: Query<(&ReturnToClip, &mut Animator)>,
+) {
+
+    for AnimationFinished { entity, .. } in finished_events.read() {
+        if let Ok((return_to, mut animator)) = animator_query.get_mut(*entity) {
+            animator.play(&return_to.0);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AnimationLibraryLoader;
+
+#[derive(Error, Debug)]
+pub enum AnimationLibraryLoaderError {
+    #[error("could not read animations: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse animations: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("invalid animations: {0}")]
+    Invalid(#[from] AnimationLibraryError),
+}
+
+impl AssetLoader for AnimationLibraryLoader {
+    type Asset = AnimationLibrary;
+    type Settings = ();
+    type Error = AnimationLibraryLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<AnimationLibrary, AnimationLibraryLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            let library: AnimationLibrary = ron::de::from_bytes(&bytes)?;
+            library.validate()?;
+
+            Ok(library)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["anim.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn default_animations() -> AnimationLibrary {
+        let source = std::fs::read_to_string(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/assets/", "animations/default.anim.ron")
+        ).unwrap();
+
+        ron::from_str(&source).unwrap()
+    }
+
+    fn clip(frames: Frames, mode: PlaybackMode) -> AnimationClip {
+        AnimationClip {
+            frames,
+            durations: vec![0.1],
+            mode,
+            next: None,
+        }
+    }
+
+    /// Atlas indices shown over `ticks` steps of 0.1 seconds, nudged so steps land inside a frame
+    fn play(animator: &mut Animator, clip: &AnimationClip, ticks: usize) -> Vec<usize> {
+        (0..ticks).map(|tick| {
+            animator.advance(clip, if tick == 0 { 0.05 } else { 0.1 });
+            animator.atlas_index(clip)
+        }).collect()
+    }
+
+    #[test]
+    fn default_clips_are_valid() {
+        let library = default_animations();
+
+        assert_eq!(library.validate(), Ok(()));
+
+        // Every clip the game asks for by name
+        for name in [
+            "ship_full", "ship_damaged", "ship_very_damaged",
+            "ship_hit_full", "ship_hit_damaged", "ship_hit_very_damaged",
+            "asteroid_idle", "asteroid_explode", "rocket_flight"
+        ] {
+            assert!(library.clips.contains_key(name), "missing clip {name}");
+        }
+
+        // The ship blinks for two seconds after a hit
+        let hit = &library.clips["ship_hit_full"];
+        let length: f32 = (0..hit.frames.len()).map(|frame| hit.duration(frame)).sum();
+        assert!((length - 2.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn loops_wrap_around() {
+        let clip = clip(Frames::Range(4, 6), PlaybackMode::Loop);
+        let mut animator = Animator::new("loop");
+
+        assert_eq!(play(&mut animator, &clip, 7), vec![4, 5, 6, 4, 5, 6, 4]);
+    }
+
+    #[test]
+    fn ping_pong_turns_at_both_ends() {
+        let clip = clip(Frames::List(vec![7, 8, 9]), PlaybackMode::PingPong);
+        let mut animator = Animator::new("ping_pong");
+
+        assert_eq!(play(&mut animator, &clip, 7), vec![7, 8, 9, 8, 7, 8, 9]);
+    }
+
+    #[test]
+    fn once_finishes_a_single_time() {
+        let mut clip = clip(Frames::Range(0, 2), PlaybackMode::Once);
+        clip.durations = vec![0.1, 0.2, 0.1];
+
+        let mut animator = Animator::new("once");
+
+        assert!(!animator.advance(&clip, 0.35));
+        assert_eq!(animator.atlas_index(&clip), 2);
+
+        assert!(animator.advance(&clip, 0.1));
+
+        // Holds the last frame
+        assert!(!animator.advance(&clip, 1.0));
+        assert_eq!(animator.atlas_index(&clip), 2);
+
+        animator.play("once");
+        assert_eq!(animator.atlas_index(&clip), 0);
+    }
+
+    #[derive(Component)]
+    struct Shielded;
+
+    /// The animation systems and policies on their own, no window or renderer
+    fn animation_app() -> App {
+        let mut library = default_animations();
+
+        library.clips.insert("blink".to_string(), AnimationClip {
+            frames: Frames::List(vec![0, 1]),
+            durations: vec![0.5],
+            mode: PlaybackMode::Once,
+            next: None,
+        });
+
+        let mut libraries = Assets::<AnimationLibrary>::default();
+        let handle = libraries.add(library);
+
+        let mut app = App::new();
+
+        app
+            .init_resource::<Time>()
+            .insert_resource(libraries)
+            .insert_resource(Animations { library: handle })
+            .add_event::<PlayClip>()
+            .add_event::<AnimationFinished>()
+            .add_systems(Update, (
+                play_animations,
+                (despawn_on_finish, return_to_clip, remove_component_on_finish::<Shielded>).after(play_animations),
+            ));
+
+        app
+    }
+
+    fn run_for(app: &mut App, seconds: f32) {
+        for _ in 0..(seconds / 0.05).round() as usize {
+            app.world.resource_mut::<Time>().advance_by(Duration::from_secs_f32(0.05));
+            app.update();
+        }
+    }
+
+    #[test]
+    fn policies_run_when_a_clip_finishes() {
+        let mut app = animation_app();
+
+        let explosion = app.world.spawn((
+            Animator::new("asteroid_explode"),
+            TextureAtlas::default(),
+            DespawnOnFinish
+        )).id();
+
+        let rocket = app.world.spawn((
+            Animator::new("rocket_flight"),
+            TextureAtlas::default(),
+            DespawnOnFinish
+        )).id();
+
+        let ship = app.world.spawn((
+            Animator::new("ship_full"),
+            TextureAtlas::default(),
+            ReturnToClip("ship_damaged".to_string()),
+            RemoveComponentOnFinish::<Shielded>::default(),
+            Shielded
+        )).id();
+
+        app.world.send_event(PlayClip { entity: ship, clip: "blink".to_string() });
+
+        run_for(&mut app, 0.6);
+
+        assert!(app.world.get_entity(explosion).is_none());
+        assert!(app.world.get::<Shielded>(ship).is_some());
+        assert_eq!(app.world.get::<TextureAtlas>(ship).unwrap().index, 1);
+
+        run_for(&mut app, 0.6);
+
+        assert!(app.world.get::<Shielded>(ship).is_none());
+        assert_eq!(app.world.get::<Animator>(ship).unwrap().clip(), "ship_damaged");
+        assert_eq!(app.world.get::<TextureAtlas>(ship).unwrap().index, 3);
+
+        // Loops never finish
+        run_for(&mut app, 5.0);
+        assert!(app.world.get_entity(rocket).is_some());
+    }
+
+    #[test]
+    fn invalid_clips_are_rejected() {
+        let mut library = default_animations();
+
+        library.clips.get_mut("rocket_flight").unwrap().next = Some("warp".to_string());
+        assert_eq!(library.validate(), Err(AnimationLibraryError::UnknownNext("rocket_flight".to_string())));
+
+        library.clips.get_mut("rocket_flight").unwrap().next = None;
+        library.clips.get_mut("ship_hit_full").unwrap().durations = vec![0.4, 0.4];
+        assert_eq!(library.validate(), Err(AnimationLibraryError::InvalidDurations("ship_hit_full".to_string())));
+
+        library.clips.get_mut("ship_hit_full").unwrap().durations = vec![0.4];
+        library.clips.get_mut("asteroid_explode").unwrap().frames = Frames::List(vec![]);
+        assert_eq!(library.validate(), Err(AnimationLibraryError::NoFrames("asteroid_explode".to_string())));
+    }
+}
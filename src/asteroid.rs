@@ -9,33 +9,318 @@ use bevy::{
 };
 
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     constants::*, 
-    AnimationIndices, 
-    AnimationProperties, 
-    AnimationTimer, 
+    animation::{Animator, DespawnOnFinish},
+    game_assets::GameAssets,
     CollidableComponentNames, 
     Collider, 
-    ColliderShape
+    ColliderShape,
+    Velocity,
+    AngularVelocity
 };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum AsteroidType {
+    Small,
+    Medium,
+    Large,
+    Armored
+}
+
+impl AsteroidType {
+
+    pub fn random(rng: &mut impl Rng) -> AsteroidType {
+        match rng.gen_range(0..10) {
+            0..=2 => AsteroidType::Small,
+            3..=6 => AsteroidType::Medium,
+            7..=8 => AsteroidType::Large,
+            _ => AsteroidType::Armored
+        }
+    }
+
+    pub fn scale(&self) -> f32 {
+        match self {
+            AsteroidType::Small => 0.9,
+            AsteroidType::Medium => ASTEROID_APPLIED_SCALE.x,
+            AsteroidType::Large => 2.2,
+            AsteroidType::Armored => 1.7,
+        }
+    }
+
+    pub fn radius(&self) -> f32 {
+        ASTEROID_TRUE_WIDTH * self.scale() / 2.0
+    }
+
+    pub fn hit_points(&self) -> u32 {
+        match self {
+            AsteroidType::Small => 1,
+            AsteroidType::Medium => 2,
+            AsteroidType::Large => 4,
+            AsteroidType::Armored => 6,
+        }
+    }
+
+    // Downwards drift, on top of the camera's scrolling
+    pub fn speed(&self) -> f32 {
+        match self {
+            AsteroidType::Small => 90.0,
+            AsteroidType::Medium => 55.0,
+            AsteroidType::Large => 30.0,
+            AsteroidType::Armored => 20.0,
+        }
+    }
+
+    // Mass used when asteroids bump into each other, armor makes them denser
+    pub fn mass(&self) -> f32 {
+        let density = match self {
+            AsteroidType::Armored => 2.0,
+            _ => 1.0
+        };
+
+        self.radius() * self.radius() * density
+    }
+
+    pub fn score_value(&self) -> u64 {
+        match self {
+            AsteroidType::Small => 3,
+            AsteroidType::Medium => 5,
+            AsteroidType::Large => 10,
+            AsteroidType::Armored => 15,
+        }
+    }
+
+    /// The asteroids spawned in place of this one once it is destroyed
+    pub fn fragments(&self) -> &'static [AsteroidType] {
+        match self {
+            AsteroidType::Large => &[AsteroidType::Small, AsteroidType::Small, AsteroidType::Small],
+            _ => &[]
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            AsteroidType::Armored => Color::rgb(0.6, 0.7, 0.9),
+            _ => Color::WHITE
+        }
+    }
+}
+
+/// Velocities for the fragments of a destroyed asteroid, fanned out
+/// around the parent's velocity so they drift away from each other
+pub fn fragment_velocities(parent_velocity: Vec2, fragments: &[AsteroidType]) -> Vec<Vec2> {
+    let count = fragments.len();
+
+    fragments.iter().enumerate().map(|(i, fragment)| {
+        // Spread evenly between -60 and 60 degrees off straight down
+        let spread = if count > 1 {
+            -60.0 + 120.0 * i as f32 / (count - 1) as f32
+        } else {
+            0.0
+        };
+
+        let direction = Vec2::from_angle(spread.to_radians()).rotate(Vec2::NEG_Y);
+
+        parent_velocity + direction * fragment.speed()
+    }).collect()
+}
+
+/// The collision radius of an asteroid, derived from its scale
+pub fn asteroid_radius(asteroid_transform: &Transform) -> f32 {
+    ASTEROID_TRUE_WIDTH * asteroid_transform.scale.x / 2.0
+}
+
+/// One side of a collision between two asteroids
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AsteroidBody {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub radius: f32,
+    pub mass: f32,
+}
+
+/// The outcome of two overlapping asteroids bouncing off each other
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AsteroidImpact {
+    pub velocities: (Vec2, Vec2),
+    // How far each asteroid has to move so they no longer overlap
+    pub separations: (Vec2, Vec2),
+    // Closing speed along the contact normal
+    pub impact_speed: f32,
+}
+
+/// Elastic collision between two asteroids modelled as circles.
+/// Returns None when they don't overlap.
+pub fn resolve_asteroid_collision(a: AsteroidBody, b: AsteroidBody) -> Option<AsteroidImpact> {
+    let offset = a.position - b.position;
+    let distance = offset.length();
+    let overlap = a.radius + b.radius - distance;
+
+    if overlap <= 0.0 { return None; }
+
+    // Perfectly stacked asteroids get pushed apart sideways
+    let normal = if distance > f32::EPSILON { offset / distance } else { Vec2::X };
+
+    let inverse_mass_a = 1.0 / a.mass;
+    let inverse_mass_b = 1.0 / b.mass;
+    let inverse_mass_sum = inverse_mass_a + inverse_mass_b;
+
+    let separations = (
+        normal * overlap * inverse_mass_a / inverse_mass_sum,
+        -normal * overlap * inverse_mass_b / inverse_mass_sum
+    );
+
+    let normal_speed = (a.velocity - b.velocity).dot(normal);
+
+    // Already moving apart, only untangle them
+    if normal_speed >= 0.0 {
+        return Some(AsteroidImpact {
+            velocities: (a.velocity, b.velocity),
+            separations,
+            impact_speed: 0.0,
+        });
+    }
+
+    // Restitution of 1.0, no energy is lost
+    let impulse = -2.0 * normal_speed / inverse_mass_sum;
+
+    Some(AsteroidImpact {
+        velocities: (
+            a.velocity + normal * impulse * inverse_mass_a,
+            b.velocity - normal * impulse * inverse_mass_b
+        ),
+        separations,
+        impact_speed: -normal_speed,
+    })
+}
+
+/// What an asteroid does when it reaches one of the side walls
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WallBehavior {
+    Bounce,
+    PassThrough
+}
+
+/// Reflects the horizontal velocity of an asteroid touching a side wall while moving into it
+pub fn bounce_off_side_walls(position_x: f32, radius: f32, velocity: Vec2) -> Vec2 {
+    let hits_left = position_x - radius <= LEFT_WALL && velocity.x < 0.0;
+    let hits_right = position_x + radius >= RIGHT_WALL && velocity.x > 0.0;
+
+    if hits_left || hits_right {
+        Vec2::new(-velocity.x, velocity.y)
+    } else {
+        velocity
+    }
+}
+
+/// The asteroid's hit box, a rectangle the size of the visible rock that rotates with the sprite
+#[derive(Debug, Clone, Copy)]
+pub struct AsteroidHitbox {
+    pub center: Vec2,
+    pub half_size: Vec2,
+    pub rotation: f32,
+}
+
+impl AsteroidHitbox {
+    pub fn from_transform(asteroid_transform: &Transform) -> AsteroidHitbox {
+        AsteroidHitbox {
+            center: asteroid_transform.translation.truncate(),
+            half_size: Vec2::new(ASTEROID_TRUE_WIDTH, ASTEROID_TRUE_HEIGHT) * asteroid_transform.scale.truncate() / 2.0,
+            rotation: asteroid_transform.rotation.to_euler(EulerRot::ZYX).0,
+        }
+    }
+
+    fn axes(&self) -> [Vec2; 2] {
+        let x_axis = Vec2::from_angle(self.rotation);
+        [x_axis, x_axis.perp()]
+    }
+
+    fn corners(&self) -> [Vec2; 4] {
+        let [x_axis, y_axis] = self.axes();
+        let x = x_axis * self.half_size.x;
+        let y = y_axis * self.half_size.y;
+
+        [self.center - x - y, self.center + x - y, self.center + x + y, self.center - x + y]
+    }
+
+    pub fn intersects_circle(&self, circle: &BoundingCircle) -> bool {
+        // In the hit box's own space it is just an axis aligned box
+        let local_center = Vec2::from_angle(-self.rotation).rotate(circle.center - self.center);
+
+        Aabb2d::new(Vec2::ZERO, self.half_size)
+            .intersects(&BoundingCircle::new(local_center, circle.radius()))
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb2d) -> bool {
+        let aabb_corners = [
+            aabb.min,
+            Vec2::new(aabb.max.x, aabb.min.y),
+            aabb.max,
+            Vec2::new(aabb.min.x, aabb.max.y),
+        ];
+        let hitbox_corners = self.corners();
+
+        // Separating axis test, the only candidate axes are the edges of both rectangles
+        let [x_axis, y_axis] = self.axes();
+
+        [Vec2::X, Vec2::Y, x_axis, y_axis].iter().all(|axis| {
+            let project = |corners: &[Vec2; 4]| {
+                corners.iter().fold((f32::MAX, f32::MIN), |(min, max), corner| {
+                    let projection = corner.dot(*axis);
+                    (min.min(projection), max.max(projection))
+                })
+            };
+
+            let (aabb_min, aabb_max) = project(&aabb_corners);
+            let (hitbox_min, hitbox_max) = project(&hitbox_corners);
+
+            aabb_min <= hitbox_max && hitbox_min <= aabb_max
+        })
+    }
+}
+
 #[derive(Component, Debug)]
 pub struct Asteroid {
+    pub asteroid_type: AsteroidType,
+    pub hit_points: u32,
     pub exploding: bool,
+    // Rockets can overlap an asteroid for a couple of frames before despawning
+    pub last_hit_by: Option<Entity>,
+    pub wall_behavior: WallBehavior,
 }
 
 impl Asteroid {
-    pub fn take_damage(&mut self)  {
-        self.exploding = true;
+    pub fn new(asteroid_type: AsteroidType) -> Asteroid {
+        Asteroid {
+            asteroid_type,
+            hit_points: asteroid_type.hit_points(),
+            exploding: false,
+            last_hit_by: None,
+            wall_behavior: WallBehavior::Bounce,
+        }
+    }
+
+    /// Returns true once the asteroid has no hit points left
+    pub fn take_damage(&mut self, damage: u32) -> bool {
+        self.hit_points = self.hit_points.saturating_sub(damage);
+
+        if self.hit_points == 0 {
+            self.exploding = true;
+        }
+
+        self.exploding
     }
     
     pub fn is_outside_window(&self, asteroid_transform: &Transform, camera_transform: &Transform) -> bool {
 
         let lower_bound: f32 = BOTTOM_WALL - WALL_THICKNESS / 2.0 - SHIP_SPEC.y / 2.0 - SHIP_PADDING + camera_transform.translation.y;
 
+        // Asteroids passing through the side walls are gone once fully off screen
+        let side_bound: f32 = RIGHT_WALL + self.asteroid_type.radius();
 
-        asteroid_transform.translation.y < lower_bound 
+        asteroid_transform.translation.y < lower_bound || asteroid_transform.translation.x.abs() > side_bound
         
     }
 
@@ -45,10 +330,7 @@ impl Asteroid {
         other_transform: &Transform, 
         other_name: &CollidableComponentNames,
     ) -> bool {
-        let asteroid_circle = BoundingCircle::new(
-            asteroid_transform.translation.truncate(),
-            ASTEROID_SCALED_RADIUS
-        );
+        let asteroid_hitbox = AsteroidHitbox::from_transform(asteroid_transform);
 
         match other_name {
             // Rocket and Asteroid Collide
@@ -59,15 +341,7 @@ impl Asteroid {
                     other_transform.scale.truncate() / 2.0
                 );
 
-                match asteroid_circle.intersects(&rocket_rectangle) {
-                    true => {
-                        self.take_damage();
-                        true
-                    },
-                    false => {
-                        false
-                    }
-                }
+                asteroid_hitbox.intersects_aabb(&rocket_rectangle)
             }
             // Ship and Asteroid Collide
             CollidableComponentNames::Ship => {
@@ -77,18 +351,27 @@ impl Asteroid {
                     SHIP_TRUE_WIDTH * SHIP_APPLIED_SCALE.x / 2.0
                 );
 
-                if asteroid_circle.intersects(&ship_circle) {
-                    
-                    true
-                } else {
-                    false
-                }
+                asteroid_hitbox.intersects_circle(&ship_circle)
             }
             // Asteroid to Asteroid Collide
             CollidableComponentNames::Asteroid => {
-                println!("dn check_collision | Asteroid to Asteroid -  Should not be in here!");
-                false
+                let own_circle = BoundingCircle::new(
+                    asteroid_transform.translation.truncate(),
+                    self.asteroid_type.radius()
+                );
+
+                let other_circle = BoundingCircle::new(
+                    other_transform.translation.truncate(),
+                    asteroid_radius(other_transform)
+                );
+
+                own_circle.intersects(&other_circle)
             }
+            // Enemy ships fly over the asteroid field
+            CollidableComponentNames::EnemyShip |
+            CollidableComponentNames::EnemyProjectile |
+            CollidableComponentNames::BossPart |
+            CollidableComponentNames::Pickup => false
         }
     }
 }
@@ -99,59 +382,172 @@ pub struct AsteroidBundle {
     pub asteroid: Asteroid,
     pub sprite_bundle: SpriteSheetBundle,
     pub collider: Collider,
-    pub animation: AnimationProperties
+    pub animator: Animator,
+    pub despawn_on_finish: DespawnOnFinish,
+    pub velocity: Velocity,
+    pub angular_velocity: AngularVelocity
 }
 
 impl AsteroidBundle {
     pub fn new(
-        texture: Handle<Image>, 
-        camera_transform: &Transform ,
-        texture_atlas_layouts: &mut ResMut<Assets<TextureAtlasLayout>>,
-        spawn_location: Option<Vec3>
+        game_assets: &GameAssets,
+        camera_transform: &Transform,
+        spawn_location: Option<Vec3>,
+        asteroid_type: AsteroidType,
+        rng: &mut impl Rng
     ) -> AsteroidBundle {
 
         let camera_translation_y = camera_transform.translation.y;
 
-        let x = rand::thread_rng().gen_range(0.0..=1.0) * WINDOW_WIDTH - WINDOW_WIDTH / 2.0;
-        let y: f32 = camera_translation_y + WINDOW_HEIGHT / 2.0;
-
-        let asteroid_layout = TextureAtlasLayout::from_grid(Vec2::new(96.0, 96.0), 8, 1, None, None);
-
-        let texture_atlas_layout = texture_atlas_layouts.add(asteroid_layout);
-
+        let x = rng.gen_range(0.0..=1.0) * WINDOW_WIDTH - WINDOW_WIDTH / 2.0;
 
-        let animation_indices = AnimationIndices { first: 0, last: 7 };
+        // Diagonal trajectory, up to 35 degrees off straight down
+        let heading = Vec2::from_angle(rng.gen_range(-35.0_f32..=35.0).to_radians()).rotate(Vec2::NEG_Y);
 
+        let wall_behavior = if rng.gen_bool(0.5) { WallBehavior::Bounce } else { WallBehavior::PassThrough };
+        let y: f32 = camera_translation_y + WINDOW_HEIGHT / 2.0;
 
         AsteroidBundle {
             asteroid: Asteroid {
-                exploding: false,
+                wall_behavior,
+                ..Asteroid::new(asteroid_type)
             },
             sprite_bundle: SpriteSheetBundle {
                 transform: Transform {
-                    translation: if spawn_location.is_some() { spawn_location.unwrap() } else { Vec2::new(x, y).extend(0.) },
-                    scale: ASTEROID_APPLIED_SCALE,
+                    translation: spawn_location.unwrap_or(Vec2::new(x, y).extend(0.)),
+                    scale: Vec3::new(asteroid_type.scale(), asteroid_type.scale(), 1.0),
                     ..default()
                 },
                 sprite: Sprite {
+                    color: asteroid_type.color(),
                     ..default()
                 },
                 atlas: TextureAtlas {
-                    layout: texture_atlas_layout,
+                    layout: game_assets.asteroid_layout.clone(),
                     index: 0,
                 },
-                texture: texture.clone(),
+                texture: game_assets.asteroid_texture.clone(),
                 ..default()
             },
             collider: Collider {
                 name: CollidableComponentNames::Asteroid,
-                shape: ColliderShape::Circle
+                shape: ColliderShape::OrientedRectangle
             },
-            animation: AnimationProperties {
-                // asset: crate::AnimatableAsset::Asteroid,
-                indices: animation_indices,
-                timer: AnimationTimer(Timer::from_seconds(0.12, TimerMode::Once))
-            }
+            animator: Animator::new("asteroid_idle"),
+            // The only clip that finishes is the explosion
+            despawn_on_finish: DespawnOnFinish,
+            velocity: Velocity(heading * asteroid_type.speed()),
+            angular_velocity: AngularVelocity(rng.gen_range(-1.5..=1.5))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn asteroids_survive_until_their_hit_points_run_out() {
+        let mut asteroid = Asteroid::new(AsteroidType::Armored);
+
+        for _ in 1..AsteroidType::Armored.hit_points() {
+            assert!(!asteroid.take_damage(1));
+        }
+
+        assert!(asteroid.take_damage(1));
+        assert!(asteroid.exploding);
+        assert_eq!(asteroid.hit_points, 0);
+    }
+
+    #[test]
+    fn asteroids_bounce_only_when_moving_into_a_side_wall() {
+        let radius = AsteroidType::Medium.radius();
+
+        assert_eq!(bounce_off_side_walls(LEFT_WALL, radius, Vec2::new(-20.0, -50.0)), Vec2::new(20.0, -50.0));
+        assert_eq!(bounce_off_side_walls(LEFT_WALL, radius, Vec2::new(20.0, -50.0)), Vec2::new(20.0, -50.0));
+        assert_eq!(bounce_off_side_walls(RIGHT_WALL, radius, Vec2::new(20.0, -50.0)), Vec2::new(-20.0, -50.0));
+        assert_eq!(bounce_off_side_walls(0.0, radius, Vec2::new(20.0, -50.0)), Vec2::new(20.0, -50.0));
+    }
+
+    #[test]
+    fn hitbox_follows_rotation() {
+        let mut transform = Transform::from_scale(Vec3::ONE);
+        let hitbox = AsteroidHitbox::from_transform(&transform);
+
+        // Just past the short half height of the unrotated rock, but within its half width
+        let probe = Aabb2d::new(Vec2::new(0.0, ASTEROID_TRUE_HEIGHT / 2.0 + 2.0), Vec2::splat(0.5));
+        assert!(!hitbox.intersects_aabb(&probe));
+
+        transform.rotate_z(std::f32::consts::FRAC_PI_2);
+        let rotated = AsteroidHitbox::from_transform(&transform);
+        assert!(rotated.intersects_aabb(&probe));
+
+        let circle = BoundingCircle::new(Vec2::new(0.0, ASTEROID_TRUE_HEIGHT / 2.0 + 2.5), 1.0);
+        assert!(!hitbox.intersects_circle(&circle));
+        assert!(rotated.intersects_circle(&circle));
+    }
+
+    fn body(position: Vec2, velocity: Vec2, asteroid_type: AsteroidType) -> AsteroidBody {
+        AsteroidBody {
+            position,
+            velocity,
+            radius: asteroid_type.radius(),
+            mass: asteroid_type.mass(),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn asteroid_collisions_conserve_momentum_and_energy() {
+        let a = body(Vec2::new(-20.0, 0.0), Vec2::new(50.0, -10.0), AsteroidType::Large);
+        let b = body(Vec2::new(20.0, 5.0), Vec2::new(-80.0, -30.0), AsteroidType::Small);
+
+        let impact = resolve_asteroid_collision(a, b).unwrap();
+        let (velocity_a, velocity_b) = impact.velocities;
+
+        let momentum_before = a.velocity * a.mass + b.velocity * b.mass;
+        let momentum_after = velocity_a * a.mass + velocity_b * b.mass;
+        assert!((momentum_before - momentum_after).length() < 1e-2);
+
+        let energy_before = a.mass * a.velocity.length_squared() + b.mass * b.velocity.length_squared();
+        let energy_after = a.mass * velocity_a.length_squared() + b.mass * velocity_b.length_squared();
+        assert!((energy_before - energy_after).abs() / energy_before < 1e-4);
+
+        // The lighter asteroid gets knocked back harder
+        assert!(velocity_b.x > 0.0);
+        assert!(impact.separations.1.length() > impact.separations.0.length());
+        assert!(impact.impact_speed > 0.0);
+    }
+
+    #[test]
+    fn separating_or_distant_asteroids_keep_their_velocity() {
+        let a = body(Vec2::new(-10.0, 0.0), Vec2::new(-40.0, 0.0), AsteroidType::Medium);
+        let b = body(Vec2::new(10.0, 0.0), Vec2::new(40.0, 0.0), AsteroidType::Medium);
+
+        let impact = resolve_asteroid_collision(a, b).unwrap();
+        assert_eq!(impact.velocities, (a.velocity, b.velocity));
+        assert_eq!(impact.impact_speed, 0.0);
+
+        let far = body(Vec2::new(500.0, 0.0), Vec2::ZERO, AsteroidType::Medium);
+        assert!(resolve_asteroid_collision(a, far).is_none());
+    }
+
+    #[test]
+    fn only_large_asteroids_split() {
+        assert!(AsteroidType::Small.fragments().is_empty());
+        assert!(AsteroidType::Medium.fragments().is_empty());
+        assert!(AsteroidType::Armored.fragments().is_empty());
+        assert!(AsteroidType::Large.fragments().iter().all(|fragment| fragment.radius() < AsteroidType::Large.radius()));
+    }
+
+    #[test]
+    fn fragments_diverge() {
+        let parent_velocity = Vec2::new(0.0, -30.0);
+        let velocities = fragment_velocities(parent_velocity, AsteroidType::Large.fragments());
+
+        assert_eq!(velocities.len(), 3);
+        assert!(velocities[0].x < 0.0);
+        assert!(velocities[1].x.abs() < 1e-4);
+        assert!(velocities[2].x > 0.0);
+        assert!(velocities.iter().all(|velocity| velocity.y < parent_velocity.y));
+    }
+}
@@ -0,0 +1,151 @@
+//! Headless setups for the criterion benchmarks in `benches/`, only built with the
+//! `bench` feature.
+//! The collision systems get their own schedules so each part can be timed on its own.
+//! Time never advances, so between runs only overlapping asteroids pushed apart by
+//! `asteroid_collisions` move.
+
+use bevy::{
+    ecs::{schedule::ScheduleLabel, system::CommandQueue},
+    prelude::*
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    asteroid::{AsteroidBundle, AsteroidType},
+    constants::*,
+    game_assets::GameAssets,
+    pool::EntityPools,
+    scroll::ScrollSpeed,
+    stats::RunStats,
+    ship::{RocketBundle, Ship, ShipBundle},
+    weapon::ProjectileSpec,
+    asteroid_collisions,
+    asteroid_manager,
+    collision_checks,
+    move_asteroids,
+    update_active_rockets,
+    update_grid,
+    update_kinematic_objects,
+    DistanceTravelled,
+    ExplosionEvent,
+    GameCamera,
+    Grid,
+    KinematicObject
+};
+
+/// `update_grid` alone
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BroadPhase;
+
+/// `collision_checks` against the grid left by the last `BroadPhase`
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NarrowPhase;
+
+/// Everything that runs every tick, from moving asteroids to asteroid bounces
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FullTick;
+
+/// An app with assets and a camera but nothing else spawned
+pub fn headless_app() -> App {
+    let mut app = App::new();
+
+    app
+        .add_plugins((MinimalPlugins, AssetPlugin::default()))
+        .init_asset::<Image>()
+        .init_asset::<TextureAtlasLayout>()
+        .init_asset::<AudioSource>()
+        .init_resource::<GameAssets>()
+        .insert_resource(EntityPools::new(false))
+        .insert_resource(Grid::new())
+        .init_resource::<ScrollSpeed>()
+        .init_resource::<DistanceTravelled>()
+        .init_resource::<RunStats>()
+        .add_event::<ExplosionEvent>()
+        .add_schedule(Schedule::new(BroadPhase))
+        .add_schedule(Schedule::new(NarrowPhase))
+        .add_schedule(Schedule::new(FullTick))
+        .add_systems(BroadPhase, update_grid)
+        .add_systems(NarrowPhase, collision_checks)
+        .add_systems(FullTick, (
+            move_asteroids,
+            asteroid_manager,
+            update_kinematic_objects,
+            update_active_rockets,
+            update_grid,
+            collision_checks,
+            asteroid_collisions
+        ).chain());
+
+    app.world.spawn((Camera2dBundle::default(), GameCamera, KinematicObject));
+
+    app
+}
+
+/// `headless_app` with `colliders` asteroids and rockets spread over the screen, three
+/// asteroids to a rocket. Nothing can be destroyed: asteroids can't run out of hit
+/// points, rockets pierce and the ship is invulnerable, so every run does the same work.
+pub fn collision_app(colliders: usize) -> App {
+    let mut app = headless_app();
+
+    let world = &mut app.world;
+
+    let ship = ShipBundle::new(world.resource::<GameAssets>());
+    world.spawn(ship).get_mut::<Ship>().unwrap().invulnerable = true;
+
+    let mut rng = StdRng::seed_from_u64(colliders as u64);
+
+    let rocket = ProjectileSpec {
+        pierce: true,
+        ..default()
+    };
+
+    for i in 0..colliders {
+        let position = Vec3::new(
+            rng.gen_range(LEFT_WALL + 1.0..RIGHT_WALL - 1.0),
+            rng.gen_range(BOTTOM_WALL + 1.0..TOP_WALL - 1.0),
+            0.0
+        );
+
+        if i % 4 == 3 {
+            let bundle = RocketBundle::new(world.resource::<GameAssets>(), &rocket, position, Vec2::Y);
+            world.spawn(bundle);
+
+            continue;
+        }
+
+        let mut bundle = AsteroidBundle::new(
+            world.resource::<GameAssets>(),
+            &Transform::default(),
+            Some(position),
+            AsteroidType::random(&mut rng),
+            &mut rng
+        );
+
+        bundle.asteroid.hit_points = u32::MAX;
+        world.spawn(bundle);
+    }
+
+    // Fills the grid for NarrowPhase
+    app.world.run_schedule(BroadPhase);
+
+    app
+}
+
+/// Spawns `count` asteroids through `Commands` the way the wave director does
+pub fn spawn_asteroids(app: &mut App, count: usize) {
+    let mut queue = CommandQueue::default();
+
+    {
+        let world = &app.world;
+        let mut commands = Commands::new(&mut queue, world);
+        let game_assets = world.resource::<GameAssets>();
+        let mut rng = StdRng::seed_from_u64(count as u64);
+
+        for _ in 0..count {
+            commands.spawn(AsteroidBundle::new(game_assets, &Transform::default(), None, AsteroidType::Medium, &mut rng));
+        }
+    }
+
+    queue.apply(&mut app.world);
+}
@@ -0,0 +1,669 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadState},
+    math::bounding::{Aabb2d, BoundingCircle, IntersectsVolume},
+    prelude::*,
+    utils::BoxedFuture
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    asteroid::{AsteroidBundle, AsteroidType},
+    constants::*,
+    enemy::EnemyProjectileBundle,
+    game_assets::GameAssets,
+    pool::{EntityPools, PoolKind},
+    ship::Ship,
+    telemetry::category,
+    CollidableComponentNames,
+    Collider,
+    ColliderShape,
+    GameCamera,
+    GameRng,
+    MyGameSet,
+    Velocity
+};
+
+/// Boss fights, started from the wave script with `Boss("bosses/<name>.boss.ron")`.
+/// Scrolling and the wave script are held for as long as a boss is around.
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<BossDefinition>()
+            .register_asset_loader(BossDefinitionLoader)
+            .init_resource::<BossFight>()
+            .add_event::<BossDefeated>()
+            .add_systems(FixedUpdate, (
+                spawn_pending_boss,
+                move_boss.after(spawn_pending_boss),
+                boss_attacks.after(move_boss),
+            ).in_set(MyGameSet))
+            .add_systems(PostUpdate, update_boss_health.in_set(MyGameSet));
+    }
+}
+
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct BossDefinition {
+    pub name: String,
+    /// Drawn with `GameAssets::boss_texture`, tinted with `color`
+    pub scale: f32,
+    #[serde(default = "default_color")]
+    pub color: (f32, f32, f32),
+    /// How far below the top of the screen the boss settles
+    pub entry_depth: f32,
+    pub score_value: u64,
+    pub parts: Vec<BossPartDefinition>,
+    /// Ordered from full health down, the last phase whose threshold is at or above
+    /// the boss's remaining health is the active one
+    pub phases: Vec<BossPhase>,
+}
+
+fn default_color() -> (f32, f32, f32) { (1.0, 1.0, 1.0) }
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BossPartDefinition {
+    pub name: String,
+    /// From the center of the boss
+    pub offset: (f32, f32),
+    pub size: (f32, f32),
+    pub hit_points: u32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BossPhase {
+    /// Fraction of the total hit points, 1.0 for the opening phase
+    pub health_below: f32,
+    /// Side to side speed
+    pub sway_speed: f32,
+    pub attacks: Vec<BossAttack>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BossAttack {
+    /// Seconds between volleys
+    pub interval: f32,
+    /// Part the attack comes from, it stops once the part is destroyed. The boss's center if left out.
+    #[serde(default)]
+    pub from: Option<String>,
+    pub pattern: AttackPattern,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum AttackPattern {
+    /// One shot at the player's ship
+    Aimed,
+    /// A fan of shots, `angle` degrees wide, centered straight down
+    Spread { count: u32, angle: f32 },
+    /// Shots in every direction
+    Ring { count: u32 },
+    /// Asteroids dropped in from the top of the screen
+    AsteroidBarrage {
+        count: u32,
+        #[serde(default)]
+        asteroid_type: Option<AsteroidType>
+    },
+}
+
+impl AttackPattern {
+    /// Directions of every shot in a volley, `towards_ship` is the normalized direction to the player
+    pub fn directions(&self, towards_ship: Vec2) -> Vec<Vec2> {
+        match self {
+            AttackPattern::Aimed => vec![towards_ship],
+            AttackPattern::Spread { count, angle } => {
+                if *count == 1 { return vec![Vec2::NEG_Y]; }
+
+                let step = angle.to_radians() / (*count as f32 - 1.0);
+                let start = -angle.to_radians() / 2.0;
+
+                (0..*count).map(|i| Vec2::from_angle(start + step * i as f32).rotate(Vec2::NEG_Y)).collect()
+            },
+            AttackPattern::Ring { count } => {
+                let step = std::f32::consts::TAU / *count as f32;
+
+                (0..*count).map(|i| Vec2::from_angle(step * i as f32).rotate(Vec2::NEG_Y)).collect()
+            },
+            AttackPattern::AsteroidBarrage { .. } => Vec::new(),
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum BossDefinitionError {
+    #[error("the boss has no parts")]
+    NoParts,
+    #[error("part \"{0}\" needs a positive size and hit points")]
+    InvalidPart(String),
+    #[error("the boss has no phases")]
+    NoPhases,
+    #[error("the first phase must start at full health (health_below: 1.0)")]
+    FirstPhaseNotFull,
+    #[error("phase {0} must have a lower health threshold than the one before it, and above zero")]
+    UnorderedPhase(usize),
+    #[error("phase {phase}: {reason}")]
+    InvalidAttack { phase: usize, reason: String },
+}
+
+impl BossDefinition {
+
+    pub fn validate(&self) -> Result<(), BossDefinitionError> {
+        if self.parts.is_empty() {
+            return Err(BossDefinitionError::NoParts);
+        }
+
+        for part in &self.parts {
+            if part.size.0 <= 0.0 || part.size.1 <= 0.0 || part.hit_points == 0 {
+                return Err(BossDefinitionError::InvalidPart(part.name.clone()));
+            }
+        }
+
+        let Some(first_phase) = self.phases.first() else {
+            return Err(BossDefinitionError::NoPhases);
+        };
+
+        if first_phase.health_below != 1.0 {
+            return Err(BossDefinitionError::FirstPhaseNotFull);
+        }
+
+        for (index, phase) in self.phases.iter().enumerate() {
+            if index > 0 && (phase.health_below >= self.phases[index - 1].health_below || phase.health_below <= 0.0) {
+                return Err(BossDefinitionError::UnorderedPhase(index));
+            }
+
+            for attack in &phase.attacks {
+                let invalid = |reason: String| BossDefinitionError::InvalidAttack { phase: index, reason };
+
+                if attack.interval <= 0.0 {
+                    return Err(invalid("attack intervals must be longer than zero seconds".to_string()));
+                }
+
+                if let Some(from) = &attack.from {
+                    if !self.parts.iter().any(|part| &part.name == from) {
+                        return Err(invalid(format!("there is no part named \"{}\"", from)));
+                    }
+                }
+
+                let count = match attack.pattern {
+                    AttackPattern::Aimed => 1,
+                    AttackPattern::Spread { count, .. } |
+                    AttackPattern::Ring { count } |
+                    AttackPattern::AsteroidBarrage { count, .. } => count,
+                };
+
+                if count == 0 {
+                    return Err(invalid("attacks need at least one shot".to_string()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn max_health(&self) -> u32 {
+        self.parts.iter().map(|part| part.hit_points).sum()
+    }
+
+    pub fn phase_for(&self, health: u32) -> usize {
+        let fraction = health as f32 / self.max_health() as f32;
+
+        self.phases.iter().rposition(|phase| phase.health_below >= fraction).unwrap_or(0)
+    }
+}
+
+/// The state of the current boss fight, shown in the HUD
+#[derive(Resource, Debug, Default)]
+pub struct BossFight {
+    /// Set by the wave director, spawned once it has loaded
+    pub pending: Option<Handle<BossDefinition>>,
+    pub boss: Option<Entity>,
+    pub name: String,
+    pub health: u32,
+    pub max_health: u32,
+}
+
+impl BossFight {
+    pub fn active(&self) -> bool {
+        self.pending.is_some() || self.boss.is_some()
+    }
+}
+
+#[derive(Event)]
+pub struct BossDefeated {
+    pub score_value: u64
+}
+
+#[derive(Component, Debug)]
+pub struct Boss {
+    pub definition: Handle<BossDefinition>,
+    pub phase: usize,
+    pub attack_timers: Vec<Timer>,
+    pub sway_direction: f32,
+}
+
+#[derive(Component, Debug)]
+pub struct BossPart {
+    pub boss: Entity,
+    pub name: String,
+    pub offset: Vec2,
+    pub hit_points: u32,
+    // Rockets can overlap a part for a couple of frames before despawning
+    pub last_hit_by: Option<Entity>,
+}
+
+impl BossPart {
+    /// Returns true once the part is destroyed
+    pub fn take_damage(&mut self, damage: u32) -> bool {
+        self.hit_points = self.hit_points.saturating_sub(damage);
+
+        self.hit_points == 0
+    }
+
+    /// Parts are drawn as a 1x1 sprite scaled to their size, so the scale is the hit box
+    pub fn check_collision(
+        &self,
+        part_transform: &Transform,
+        other_transform: &Transform,
+        other_name: &CollidableComponentNames
+    ) -> bool {
+        let part_rectangle = Aabb2d::new(
+            part_transform.translation.truncate(),
+            part_transform.scale.truncate() / 2.0
+        );
+
+        match other_name {
+            CollidableComponentNames::Rocket => {
+                let rocket_rectangle = Aabb2d::new(
+                    other_transform.translation.truncate(),
+                    other_transform.scale.truncate() / 2.0
+                );
+
+                part_rectangle.intersects(&rocket_rectangle)
+            },
+            CollidableComponentNames::Ship => {
+                let ship_circle = BoundingCircle::new(
+                    other_transform.translation.truncate(),
+                    SHIP_TRUE_WIDTH * SHIP_APPLIED_SCALE.x / 2.0
+                );
+
+                part_rectangle.intersects(&ship_circle)
+            },
+            _ => false
+        }
+    }
+}
+
+fn phase_timers(definition: &BossDefinition, phase: usize) -> Vec<Timer> {
+    definition.phases[phase].attacks.iter()
+        .map(|attack| Timer::from_seconds(attack.interval, TimerMode::Repeating))
+        .collect()
+}
+
+fn spawn_pending_boss(
+    mut commands: Commands,
+    mut boss_fight: ResMut<BossFight>,
+    definitions: Res<Assets<BossDefinition>>,
+    camera_query: Query<&Transform, With<GameCamera>>,
+    game_assets: Res<GameAssets>,
+    asset_server: Res<AssetServer>,
+) {
+
+    let Some(handle) = boss_fight.pending.clone() else { return; };
+
+    // A boss that can't load would hold the scroll and the wave script for good, skip it
+    if asset_server.load_state(&handle) == LoadState::Failed {
+        let path = asset_server.get_path(handle.id()).map(|path| path.to_string()).unwrap_or_default();
+
+        warn!(target: category::GAMEPLAY, %path, "couldn't load boss, skipping the fight");
+
+        boss_fight.pending = None;
+        return;
+    }
+
+    let Some(definition) = definitions.get(&handle) else { return; };
+
+    let camera_translation = camera_query.single().translation;
+    let (red, green, blue) = definition.color;
+
+    let boss = commands.spawn((
+        SpriteBundle {
+            transform: Transform {
+                translation: Vec3::new(0.0, camera_translation.y + TOP_WALL + 24.0 * definition.scale, 0.0),
+                rotation: Quat::from_rotation_z(std::f32::consts::PI),
+                scale: Vec3::new(definition.scale, definition.scale, 1.0),
+            },
+            sprite: Sprite {
+                color: Color::rgb(red, green, blue),
+                ..default()
+            },
+            texture: game_assets.boss_texture.clone(),
+            ..default()
+        },
+        Boss {
+            definition: handle,
+            phase: 0,
+            attack_timers: phase_timers(definition, 0),
+            sway_direction: 1.0,
+        }
+    )).id();
+
+    for part in &definition.parts {
+        commands.spawn((
+            SpriteBundle {
+                transform: Transform {
+                    translation: Vec3::new(0.0, camera_translation.y + TOP_WALL * 2.0, 0.1),
+                    scale: Vec3::new(part.size.0, part.size.1, 1.0),
+                    ..default()
+                },
+                sprite: Sprite {
+                    color: Color::rgba(red, green, blue, 0.35),
+                    custom_size: Some(Vec2::ONE),
+                    ..default()
+                },
+                ..default()
+            },
+            BossPart {
+                boss,
+                name: part.name.clone(),
+                offset: Vec2::new(part.offset.0, part.offset.1),
+                hit_points: part.hit_points,
+                last_hit_by: None,
+            },
+            Collider {
+                name: CollidableComponentNames::BossPart,
+                shape: ColliderShape::Rectangle
+            }
+        ));
+    }
+
+    boss_fight.pending = None;
+    boss_fight.boss = Some(boss);
+    boss_fight.name = definition.name.clone();
+    boss_fight.max_health = definition.max_health();
+    boss_fight.health = boss_fight.max_health;
+}
+
+#[allow(clippy::type_complexity)]
+fn move_boss(
+    time: Res<Time<Fixed>>,
+    definitions: Res<Assets<BossDefinition>>,
+    mut boss_query: Query<(Entity, &mut Transform, &mut Boss), Without<BossPart>>,
+    mut part_query: Query<(&mut Transform, &BossPart), Without<Boss>>,
+    camera_query: Query<&Transform, (With<GameCamera>, Without<Boss>, Without<BossPart>)>,
+) {
+
+    let camera_translation = camera_query.single().translation;
+
+    for (entity, mut transform, mut boss) in boss_query.iter_mut() {
+        let Some(definition) = definitions.get(&boss.definition) else { continue; };
+
+        let settled_y = camera_translation.y + TOP_WALL - definition.entry_depth;
+
+        if transform.translation.y > settled_y {
+            // Flying in
+            transform.translation.y = (transform.translation.y - 80.0 * time.delta_seconds()).max(settled_y);
+        } else {
+            let half_width = definition.parts.iter()
+                .map(|part| part.offset.0.abs() + part.size.0 / 2.0)
+                .fold(0.0, f32::max);
+
+            let hits_left = transform.translation.x - half_width <= LEFT_WALL && boss.sway_direction < 0.0;
+            let hits_right = transform.translation.x + half_width >= RIGHT_WALL && boss.sway_direction > 0.0;
+
+            if hits_left || hits_right {
+                boss.sway_direction = -boss.sway_direction;
+            }
+
+            transform.translation.x += boss.sway_direction * definition.phases[boss.phase].sway_speed * time.delta_seconds();
+        }
+
+        for (mut part_transform, part) in part_query.iter_mut() {
+            if part.boss != entity { continue; }
+
+            part_transform.translation.x = transform.translation.x + part.offset.x;
+            part_transform.translation.y = transform.translation.y + part.offset.y;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+#[allow(clippy::too_many_arguments)]
+fn boss_attacks(
+    mut commands: Commands,
+    time: Res<Time<Fixed>>,
+    definitions: Res<Assets<BossDefinition>>,
+    mut boss_query: Query<(Entity, &Transform, &mut Boss)>,
+    part_query: Query<(&Transform, &BossPart), Without<Boss>>,
+    ship_query: Query<&Transform, (With<Ship>, Without<Boss>, Without<BossPart>)>,
+    camera_query: Query<&Transform, (With<GameCamera>, Without<Boss>, Without<BossPart>)>,
+    game_assets: Res<GameAssets>,
+    mut pools: ResMut<EntityPools>,
+    mut rng: ResMut<GameRng>,
+) {
+
+    let ship_translation = ship_query.single().translation;
+    let camera_transform = camera_query.single();
+
+    for (entity, transform, mut boss) in boss_query.iter_mut() {
+        let Some(definition) = definitions.get(&boss.definition) else { continue; };
+
+        // Still flying in
+        if transform.translation.y > camera_transform.translation.y + TOP_WALL - definition.entry_depth { continue; }
+
+        let phase = boss.phase;
+
+        for (attack, timer) in definition.phases[phase].attacks.iter().zip(boss.attack_timers.iter_mut()) {
+            if !timer.tick(time.delta()).just_finished() { continue; }
+
+            let origin = match &attack.from {
+                None => transform.translation,
+                Some(from) => {
+                    let part = part_query.iter().find(|(_, part)| part.boss == entity && &part.name == from);
+
+                    // Destroyed parts don't shoot
+                    let Some((part_transform, _)) = part else { continue; };
+
+                    part_transform.translation
+                }
+            };
+
+            if let AttackPattern::AsteroidBarrage { count, asteroid_type } = attack.pattern {
+                let spacing = WINDOW_WIDTH / (count as f32 + 1.0);
+
+                for i in 0..count {
+                    let asteroid_type = asteroid_type.unwrap_or_else(|| AsteroidType::random(&mut rng.0));
+
+                    let mut asteroid_bundle = AsteroidBundle::new(
+                        &game_assets,
+                        camera_transform,
+                        Some(Vec3::new(
+                            LEFT_WALL + spacing * (i + 1) as f32,
+                            camera_transform.translation.y + TOP_WALL + asteroid_type.radius(),
+                            0.0
+                        )),
+                        asteroid_type,
+                        &mut rng.0
+                    );
+
+                    asteroid_bundle.velocity = Velocity(Vec2::NEG_Y * asteroid_type.speed() * 2.0);
+
+                    pools.spawn(&mut commands, PoolKind::Asteroid, asteroid_bundle);
+                }
+
+                continue;
+            }
+
+            let towards_ship = (ship_translation - origin).truncate().try_normalize().unwrap_or(Vec2::NEG_Y);
+
+            for direction in attack.pattern.directions(towards_ship) {
+                commands.spawn(EnemyProjectileBundle::new(
+                    &game_assets,
+                    origin,
+                    direction * ENEMY_PROJECTILE_SPEED
+                ));
+            }
+        }
+    }
+}
+
+/// Sums up the parts into the boss's health, moves between phases and ends the fight
+#[allow(clippy::type_complexity)]
+fn update_boss_health(
+    mut commands: Commands,
+    mut boss_fight: ResMut<BossFight>,
+    definitions: Res<Assets<BossDefinition>>,
+    mut boss_query: Query<&mut Boss>,
+    part_query: Query<(Entity, &BossPart)>,
+    mut boss_defeated: EventWriter<BossDefeated>,
+) {
+
+    let Some(boss_entity) = boss_fight.boss else { return; };
+
+    let Ok(mut boss) = boss_query.get_mut(boss_entity) else { return; };
+    let Some(definition) = definitions.get(&boss.definition) else { return; };
+
+    let mut health = 0;
+
+    for (part_entity, part) in part_query.iter() {
+        if part.boss != boss_entity { continue; }
+
+        if part.hit_points == 0 {
+            commands.entity(part_entity).despawn();
+        }
+
+        health += part.hit_points;
+    }
+
+    if boss_fight.health != health {
+        boss_fight.health = health;
+    }
+
+    if health == 0 {
+        boss_defeated.send(BossDefeated {
+            score_value: definition.score_value
+        });
+
+        commands.entity(boss_entity).despawn();
+
+        boss_fight.boss = None;
+        return;
+    }
+
+    let phase = definition.phase_for(health);
+
+    if phase != boss.phase {
+        boss.phase = phase;
+        boss.attack_timers = phase_timers(definition, phase);
+    }
+}
+
+#[derive(Default)]
+pub struct BossDefinitionLoader;
+
+#[derive(Error, Debug)]
+pub enum BossDefinitionLoaderError {
+    #[error("could not read boss definition: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse boss definition: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("invalid boss definition: {0}")]
+    Invalid(#[from] BossDefinitionError),
+}
+
+impl AssetLoader for BossDefinitionLoader {
+    type Asset = BossDefinition;
+    type Settings = ();
+    type Error = BossDefinitionLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<BossDefinition, BossDefinitionLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            let definition: BossDefinition = ron::de::from_bytes(&bytes)?;
+            definition.validate()?;
+
+            Ok(definition)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["boss.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mothership() -> BossDefinition {
+        let source = std::fs::read_to_string(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/assets/", "bosses/mothership.boss.ron")
+        ).unwrap();
+
+        ron::from_str(&source).unwrap()
+    }
+
+    #[test]
+    fn shipped_bosses_are_valid() {
+        assert_eq!(mothership().validate(), Ok(()));
+    }
+
+    #[test]
+    fn phases_follow_the_health_thresholds() {
+        let boss = mothership();
+        let max_health = boss.max_health();
+
+        assert_eq!(boss.phase_for(max_health), 0);
+
+        let thresholds: Vec<f32> = boss.phases.iter().map(|phase| phase.health_below).collect();
+
+        for (index, threshold) in thresholds.iter().enumerate().skip(1) {
+            let at_threshold = (threshold * max_health as f32).floor() as u32;
+
+            assert_eq!(boss.phase_for(at_threshold), index);
+            assert_eq!(boss.phase_for(at_threshold + 2), index - 1);
+        }
+
+        assert_eq!(boss.phase_for(1), boss.phases.len() - 1);
+    }
+
+    #[test]
+    fn bullet_patterns_cover_their_angles() {
+        let spread = AttackPattern::Spread { count: 3, angle: 90.0 }.directions(Vec2::X);
+        assert!((spread[0] - Vec2::new(-1.0, -1.0).normalize()).length() < 0.001);
+        assert!((spread[1] - Vec2::NEG_Y).length() < 0.001);
+        assert!((spread[2] - Vec2::new(1.0, -1.0).normalize()).length() < 0.001);
+
+        let ring = AttackPattern::Ring { count: 8 }.directions(Vec2::X);
+        assert_eq!(ring.len(), 8);
+        assert!(ring.iter().sum::<Vec2>().length() < 0.001);
+
+        assert_eq!(AttackPattern::Aimed.directions(Vec2::X), vec![Vec2::X]);
+    }
+
+    #[test]
+    fn invalid_bosses_are_rejected() {
+        let mut boss = mothership();
+        boss.phases[0].health_below = 0.9;
+        assert_eq!(boss.validate(), Err(BossDefinitionError::FirstPhaseNotFull));
+
+        let mut boss = mothership();
+        boss.phases.swap(1, 2);
+        assert!(matches!(boss.validate(), Err(BossDefinitionError::UnorderedPhase(_))));
+
+        let mut boss = mothership();
+        boss.phases[0].attacks[0].from = Some("nothing".to_string());
+        assert!(matches!(boss.validate(), Err(BossDefinitionError::InvalidAttack { phase: 0, .. })));
+
+        let mut boss = mothership();
+        boss.parts.clear();
+        assert_eq!(boss.validate(), Err(BossDefinitionError::NoParts));
+    }
+}
@@ -0,0 +1,765 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    str::FromStr
+};
+
+use bevy::{
+    input::{keyboard::KeyboardInput, ButtonState, InputSystem},
+    prelude::*
+};
+
+use thiserror::Error;
+
+use crate::{
+    asteroid::{AsteroidBundle, AsteroidType},
+    game_assets::GameAssets,
+    particles::ParticleRng,
+    ship::Ship,
+    AppState,
+    GameCamera,
+    GameDifficulty,
+    GameRng,
+    ScoreTracker
+};
+
+/// Drop-down developer console, opened with the backtick key. Commands live in the
+/// `ConsoleCommands` resource, any plugin can add its own with
+/// `app.register_console_command`. Up and down walk the history, tab completes command
+/// names and their first argument. `--console-script=<path>` runs a file of commands,
+/// one per line, when the game first starts.
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+
+        for (name, command) in builtin_commands() {
+            app.register_console_command(name, command);
+        }
+
+        app
+            .init_resource::<Console>()
+            .add_systems(Startup, (setup_console, load_console_script))
+            .add_systems(PreUpdate, (
+                handle_console_input,
+                // Keys typed into the console don't fly the ship
+                block_game_input.run_if(console_open),
+            ).chain().after(InputSystem))
+            .add_systems(OnEnter(AppState::InGame), run_console_script)
+            .add_systems(Update, (
+                run_console_commands,
+                update_console_text.after(run_console_commands),
+            ));
+    }
+}
+
+const CONSOLE_TOGGLE_KEY: KeyCode = KeyCode::Backquote;
+
+// Lines kept in the log, and how many of the newest are shown
+const CONSOLE_LOG_LINES: usize = 200;
+const CONSOLE_VISIBLE_LINES: usize = 14;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ConsoleError {
+    #[error("unknown command {0}, try help")]
+    UnknownCommand(String),
+    #[error("missing {0}")]
+    MissingArgument(&'static str),
+    #[error("{argument} isn't {expected}")]
+    InvalidArgument { argument: String, expected: &'static str },
+    #[error("no {0} right now")]
+    Unavailable(&'static str),
+}
+
+/// Gets the world and the words after the command name, returns what to print
+pub type ConsoleHandler = fn(&mut World, &[&str]) -> Result<String, ConsoleError>;
+
+#[derive(Clone, Copy)]
+pub struct ConsoleCommand {
+    /// Usage, shown by `help`
+    pub help: &'static str,
+    /// Offered by tab completion for the first argument
+    pub arguments: &'static [&'static str],
+    pub run: ConsoleHandler,
+}
+
+#[derive(Resource, Default)]
+pub struct ConsoleCommands {
+    commands: BTreeMap<&'static str, ConsoleCommand>,
+}
+
+impl ConsoleCommands {
+    /// Replaces any command already registered under `name`
+    pub fn register(&mut self, name: &'static str, command: ConsoleCommand) {
+        self.commands.insert(name, command);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ConsoleCommand> {
+        self.commands.get(name)
+    }
+
+    /// Completes the command name, or its first argument once the name is typed out.
+    /// Returns the completed line and, when it is ambiguous, every candidate.
+    pub fn complete(&self, input: &str) -> (String, Vec<&'static str>) {
+
+        let unchanged = (input.to_string(), Vec::new());
+
+        let (prefix, partial, candidates): (String, &str, Vec<&'static str>) = match input.split_once(' ') {
+            None => (String::new(), input, self.commands.keys().copied().collect()),
+            Some((name, partial)) if !partial.contains(' ') => {
+                let Some(command) = self.get(name) else { return unchanged; };
+
+                (format!("{name} "), partial, command.arguments.to_vec())
+            },
+            Some(_) => return unchanged,
+        };
+
+        let matches: Vec<&'static str> = candidates.into_iter()
+            .filter(|candidate| candidate.starts_with(partial))
+            .collect();
+
+        match matches.len() {
+            0 => unchanged,
+            1 => (format!("{prefix}{} ", matches[0]), Vec::new()),
+            _ => {
+                let common = matches.iter().fold(matches[0], |common, candidate| {
+                    let length = common.bytes().zip(candidate.bytes())
+                        .take_while(|(a, b)| a == b)
+                        .count();
+
+                    &common[..length]
+                });
+
+                (format!("{prefix}{common}"), matches)
+            }
+        }
+    }
+}
+
+pub trait RegisterConsoleCommand {
+    fn register_console_command(&mut self, name: &'static str, command: ConsoleCommand) -> &mut Self;
+}
+
+impl RegisterConsoleCommand for App {
+    fn register_console_command(&mut self, name: &'static str, command: ConsoleCommand) -> &mut Self {
+        self.init_resource::<ConsoleCommands>();
+        self.world.resource_mut::<ConsoleCommands>().register(name, command);
+
+        self
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Console {
+    pub open: bool,
+    pub input: String,
+    /// What was run and what it printed, newest last
+    pub log: VecDeque<String>,
+    history: Vec<String>,
+    // Index into the history while walking it with up and down
+    history_cursor: Option<usize>,
+    // Run by run_console_commands
+    pending: VecDeque<String>,
+    // From --console-script, queued the first time the game starts
+    script: Vec<String>,
+}
+
+impl Console {
+    pub fn print(&mut self, line: impl Into<String>) {
+        self.log.push_back(line.into());
+
+        while self.log.len() > CONSOLE_LOG_LINES {
+            self.log.pop_front();
+        }
+    }
+
+    /// Runs a command next frame without adding it to the history
+    pub fn queue(&mut self, line: impl Into<String>) {
+        self.pending.push_back(line.into());
+    }
+
+    /// Runs what was typed and adds it to the history
+    pub fn submit(&mut self) {
+        let line = std::mem::take(&mut self.input).trim().to_string();
+
+        self.history_cursor = None;
+
+        if line.is_empty() { return; }
+
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+
+        self.queue(line);
+    }
+
+    pub fn history_back(&mut self) {
+        if self.history.is_empty() { return; }
+
+        let index = match self.history_cursor {
+            Some(index) => index.saturating_sub(1),
+            None => self.history.len() - 1,
+        };
+
+        self.history_cursor = Some(index);
+        self.input = self.history[index].clone();
+    }
+
+    pub fn history_forward(&mut self) {
+        let Some(index) = self.history_cursor else { return; };
+
+        if index + 1 < self.history.len() {
+            self.history_cursor = Some(index + 1);
+            self.input = self.history[index + 1].clone();
+        } else {
+            // Past the newest entry, back to an empty line
+            self.history_cursor = None;
+            self.input.clear();
+        }
+    }
+}
+
+/// Runs one line against the world
+pub fn run_line(world: &mut World, line: &str) -> Result<String, ConsoleError> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    let Some((name, args)) = words.split_first() else { return Ok(String::new()); };
+
+    let command = world.get_resource::<ConsoleCommands>()
+        .and_then(|commands| commands.get(name).copied())
+        .ok_or_else(|| ConsoleError::UnknownCommand(name.to_string()))?;
+
+    (command.run)(world, args)
+}
+
+/// The commands in a script, one per line, skipping blank lines and `#` comments
+pub fn script_lines(script: &str) -> Vec<String> {
+    script.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+fn argument<'a>(args: &[&'a str], index: usize, name: &'static str) -> Result<&'a str, ConsoleError> {
+    args.get(index).copied().ok_or(ConsoleError::MissingArgument(name))
+}
+
+fn parse<T: FromStr>(argument: &str, expected: &'static str) -> Result<T, ConsoleError> {
+    argument.parse().map_err(|_| ConsoleError::InvalidArgument { argument: argument.to_string(), expected })
+}
+
+fn invalid(argument: &str, expected: &'static str) -> ConsoleError {
+    ConsoleError::InvalidArgument { argument: argument.to_string(), expected }
+}
+
+fn builtin_commands() -> Vec<(&'static str, ConsoleCommand)> {
+    vec![
+        ("help", ConsoleCommand { help: "help [command]", arguments: &[], run: help }),
+        ("clear", ConsoleCommand { help: "clear, empties the log", arguments: &[], run: clear }),
+        ("spawn", ConsoleCommand {
+            help: "spawn asteroid <x> <y> [small|medium|large|armored], from the middle of the screen",
+            arguments: &["asteroid"],
+            run: spawn
+        }),
+        ("god", ConsoleCommand { help: "god, toggles invulnerability", arguments: &[], run: god }),
+        ("set_health", ConsoleCommand { help: "set_health full|<hull points>", arguments: &["full"], run: set_health }),
+        ("score", ConsoleCommand { help: "score add|set <points>", arguments: &["add", "set"], run: score }),
+        ("difficulty", ConsoleCommand { help: "difficulty easy|medium|hard", arguments: &["easy", "medium", "hard"], run: difficulty }),
+        ("state", ConsoleCommand {
+            help: "state StartMenu|InGame|Paused|GameOverMenu",
+            arguments: &["StartMenu", "InGame", "Paused", "GameOverMenu"],
+            run: state
+        }),
+        ("timescale", ConsoleCommand { help: "timescale <speed>, 1 is normal", arguments: &[], run: timescale }),
+        ("seed", ConsoleCommand { help: "seed <number>, reseeds spawns, drops and particles", arguments: &[], run: seed }),
+    ]
+}
+
+fn help(world: &mut World, args: &[&str]) -> Result<String, ConsoleError> {
+    let commands = world.resource::<ConsoleCommands>();
+
+    if let Some(name) = args.first() {
+        return commands.get(name)
+            .map(|command| command.help.to_string())
+            .ok_or_else(|| ConsoleError::UnknownCommand(name.to_string()));
+    }
+
+    Ok(commands.commands.values().map(|command| command.help).collect::<Vec<_>>().join("\n"))
+}
+
+fn clear(world: &mut World, _args: &[&str]) -> Result<String, ConsoleError> {
+    world.resource_mut::<Console>().log.clear();
+
+    Ok(String::new())
+}
+
+fn spawn(world: &mut World, args: &[&str]) -> Result<String, ConsoleError> {
+    let what = argument(args, 0, "what to spawn")?;
+
+    if what != "asteroid" {
+        return Err(invalid(what, "something that can be spawned"));
+    }
+
+    let x: f32 = parse(argument(args, 1, "x")?, "a number")?;
+    let y: f32 = parse(argument(args, 2, "y")?, "a number")?;
+
+    let asteroid_type = match args.get(3).copied() {
+        None | Some("medium") => AsteroidType::Medium,
+        Some("small") => AsteroidType::Small,
+        Some("large") => AsteroidType::Large,
+        Some("armored") => AsteroidType::Armored,
+        Some(other) => return Err(invalid(other, "an asteroid type")),
+    };
+
+    let camera_transform = *world.query_filtered::<&Transform, With<GameCamera>>()
+        .get_single(world)
+        .map_err(|_| ConsoleError::Unavailable("camera"))?;
+
+    let position = camera_transform.translation.truncate() + Vec2::new(x, y);
+
+    let asteroid_bundle = world.resource_scope(|world, mut rng: Mut<GameRng>| {
+        AsteroidBundle::new(
+            world.resource::<GameAssets>(),
+            &camera_transform,
+            Some(position.extend(0.0)),
+            asteroid_type,
+            &mut rng.0
+        )
+    });
+
+    world.spawn(asteroid_bundle);
+
+    Ok(format!("spawned a {asteroid_type:?} asteroid at {x}, {y}"))
+}
+
+fn player_ship(world: &mut World) -> Result<Mut<'_, Ship>, ConsoleError> {
+    world.query::<&mut Ship>()
+        .get_single_mut(world)
+        .map_err(|_| ConsoleError::Unavailable("ship"))
+}
+
+fn god(world: &mut World, _args: &[&str]) -> Result<String, ConsoleError> {
+    let mut ship = player_ship(world)?;
+
+    ship.god_mode = !ship.god_mode;
+
+    Ok(format!("god mode {}", if ship.god_mode { "on" } else { "off" }))
+}
+
+fn set_health(world: &mut World, args: &[&str]) -> Result<String, ConsoleError> {
+    let amount = argument(args, 0, "hull points")?;
+
+    let mut ship = player_ship(world)?;
+
+    ship.hull.current = match amount {
+        "full" => ship.hull.max,
+        amount => parse::<u32>(amount, "full or a number")?.min(ship.hull.max),
+    };
+
+    Ok(format!("hull at {}/{}", ship.hull.current, ship.hull.max))
+}
+
+fn score(world: &mut World, args: &[&str]) -> Result<String, ConsoleError> {
+    let action = argument(args, 0, "add or set")?;
+    let points: u64 = parse(argument(args, 1, "points")?, "a number")?;
+
+    let mut score_tracker = world.get_resource_mut::<ScoreTracker>().ok_or(ConsoleError::Unavailable("score"))?;
+
+    match action {
+        "add" => *score_tracker.score_count += points,
+        "set" => *score_tracker.score_count = points,
+        other => return Err(invalid(other, "add or set")),
+    }
+
+    Ok(format!("score {}", *score_tracker.score_count))
+}
+
+fn difficulty(world: &mut World, args: &[&str]) -> Result<String, ConsoleError> {
+    let name = argument(args, 0, "difficulty")?;
+
+    let difficulty = match name {
+        "easy" => GameDifficulty::Easy,
+        "medium" => GameDifficulty::Medium,
+        "hard" => GameDifficulty::Hard,
+        other => return Err(invalid(other, "easy, medium or hard")),
+    };
+
+    world.insert_resource(difficulty);
+
+    Ok(format!("difficulty {name}"))
+}
+
+fn state(world: &mut World, args: &[&str]) -> Result<String, ConsoleError> {
+    let name = argument(args, 0, "state")?;
+
+    // Loading is left out, it only makes sense once
+    let state = match name {
+        "StartMenu" => AppState::StartMenu,
+        "InGame" => AppState::InGame,
+        "Paused" => AppState::Paused,
+        "GameOverMenu" => AppState::GameOverMenu,
+        other => return Err(invalid(other, "a state")),
+    };
+
+    world.get_resource_mut::<NextState<AppState>>()
+        .ok_or(ConsoleError::Unavailable("app state"))?
+        .set(state);
+
+    Ok(format!("state {name}"))
+}
+
+fn timescale(world: &mut World, args: &[&str]) -> Result<String, ConsoleError> {
+    let speed: f32 = parse(argument(args, 0, "speed")?, "a number")?;
+
+    if speed <= 0.0 || !speed.is_finite() {
+        return Err(invalid(args[0], "a speed above 0"));
+    }
+
+    world.get_resource_mut::<Time<Virtual>>()
+        .ok_or(ConsoleError::Unavailable("clock"))?
+        .set_relative_speed(speed);
+
+    Ok(format!("time scale {speed}"))
+}
+
+fn seed(world: &mut World, args: &[&str]) -> Result<String, ConsoleError> {
+    let seed: u64 = parse(argument(args, 0, "seed")?, "a number")?;
+
+    world.insert_resource(GameRng::seeded(seed));
+    world.insert_resource(ParticleRng::seeded(seed));
+
+    Ok(format!("seed {seed}"))
+}
+
+fn console_open(console: Res<Console>) -> bool {
+    console.open
+}
+
+#[derive(Component)]
+struct ConsoleRootNode;
+
+#[derive(Component)]
+struct ConsoleLogText;
+
+#[derive(Component)]
+struct ConsoleInputText;
+
+fn setup_console(mut commands: Commands) {
+
+    let text_style = TextStyle {
+        font_size: 12.0,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(40.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::FlexEnd,
+                padding: UiRect::all(Val::Px(6.0)),
+                overflow: Overflow::clip(),
+                display: Display::None,
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.85).into(),
+            // Above the HUD and the menus
+            z_index: ZIndex::Global(10),
+            ..default()
+        },
+        ConsoleRootNode
+    )).with_children(|parent| {
+        parent.spawn((TextBundle::from_section("", text_style.clone()), ConsoleLogText));
+
+        parent.spawn((
+            TextBundle::from_section("> ", TextStyle {
+                color: Color::rgb(0.6, 1.0, 0.6),
+                ..text_style
+            }),
+            ConsoleInputText
+        ));
+    });
+}
+
+fn load_console_script(mut console: ResMut<Console>) {
+
+    let Some(path) = std::env::args().find_map(|arg| arg.strip_prefix("--console-script=").map(str::to_string)) else {
+        return;
+    };
+
+    match std::fs::read_to_string(&path) {
+        Ok(script) => console.script = script_lines(&script),
+        Err(error) => console.print(format!("couldn't read {path}: {error}")),
+    }
+}
+
+/// Queues the startup script the first time the game starts, once the ship is around
+fn run_console_script(mut console: ResMut<Console>) {
+    let script = std::mem::take(&mut console.script);
+
+    for line in script {
+        console.queue(line);
+    }
+}
+
+fn handle_console_input(
+    mut console: ResMut<Console>,
+    commands: Res<ConsoleCommands>,
+    mut key_events: EventReader<KeyboardInput>,
+    mut characters: EventReader<ReceivedCharacter>,
+) {
+
+    for event in key_events.read() {
+        if event.state != ButtonState::Pressed { continue; }
+
+        if event.key_code == CONSOLE_TOGGLE_KEY {
+            console.open = !console.open;
+            continue;
+        }
+
+        if !console.open { continue; }
+
+        match event.key_code {
+            KeyCode::Enter => console.submit(),
+            KeyCode::Backspace => { console.input.pop(); },
+            KeyCode::ArrowUp => console.history_back(),
+            KeyCode::ArrowDown => console.history_forward(),
+            KeyCode::Tab => {
+                let (line, candidates) = commands.complete(&console.input);
+
+                if !candidates.is_empty() {
+                    console.print(candidates.join("  "));
+                }
+
+                console.input = line;
+            },
+            _ => {}
+        }
+    }
+
+    if !console.open {
+        characters.clear();
+        return;
+    }
+
+    for character in characters.read() {
+        // The toggle key types a backtick too
+        for c in character.char.chars().filter(|c| !c.is_control() && *c != '`' && *c != '~') {
+            console.input.push(c);
+        }
+    }
+}
+
+fn block_game_input(mut keyboard_input: ResMut<ButtonInput<KeyCode>>) {
+    keyboard_input.reset_all();
+}
+
+fn run_console_commands(world: &mut World) {
+
+    while let Some(line) = world.resource_mut::<Console>().pending.pop_front() {
+        world.resource_mut::<Console>().print(format!("> {line}"));
+
+        let output = run_line(world, &line);
+
+        let mut console = world.resource_mut::<Console>();
+
+        match output {
+            Ok(output) => {
+                for output_line in output.lines() {
+                    console.print(output_line.to_string());
+                }
+            },
+            Err(error) => console.print(format!("error: {error}")),
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_console_text(
+    console: Res<Console>,
+    mut root_query: Query<&mut Style, With<ConsoleRootNode>>,
+    mut log_query: Query<&mut Text, (With<ConsoleLogText>, Without<ConsoleInputText>)>,
+    mut input_query: Query<&mut Text, (With<ConsoleInputText>, Without<ConsoleLogText>)>,
+) {
+
+    if !console.is_changed() { return; }
+
+    let display = if console.open { Display::Flex } else { Display::None };
+
+    for mut style in root_query.iter_mut() {
+        if style.display != display {
+            style.display = display;
+        }
+    }
+
+    let skip = console.log.len().saturating_sub(CONSOLE_VISIBLE_LINES);
+    let log = console.log.iter().skip(skip).cloned().collect::<Vec<_>>().join("\n");
+
+    for mut text in log_query.iter_mut() {
+        text.sections[0].value = log.clone();
+    }
+
+    for mut text in input_query.iter_mut() {
+        text.sections[0].value = format!("> {}_", console.input);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        pool::EntityPools,
+        ship::{Health, Rocket},
+        shield::Shield,
+        touch_controls::VirtualControls,
+        weapon::{Weapon, WeaponArsenal},
+        check_if_firing,
+        ScoreCounter
+    };
+
+    fn console_world() -> World {
+        let mut world = World::new();
+
+        let mut commands = ConsoleCommands::default();
+
+        for (name, command) in builtin_commands() {
+            commands.register(name, command);
+        }
+
+        world.insert_resource(commands);
+        world.init_resource::<Console>();
+        world.insert_resource(ScoreTracker {
+            score_count: ScoreCounter(0),
+            timer: Timer::from_seconds(0.5, TimerMode::Repeating),
+        });
+
+        world.spawn(Ship {
+            hull: Health { current: 1, max: 5 },
+            shield: Shield::new(0),
+            invulnerable: false,
+            god_mode: false,
+            thrust: 0.0,
+        });
+
+        world
+    }
+
+    #[test]
+    fn commands_change_the_world() {
+        let mut world = console_world();
+
+        assert_eq!(run_line(&mut world, "god"), Ok("god mode on".to_string()));
+        assert_eq!(run_line(&mut world, "set_health full"), Ok("hull at 5/5".to_string()));
+        assert_eq!(run_line(&mut world, "score add 100"), Ok("score 100".to_string()));
+        assert_eq!(run_line(&mut world, "  score   add 5 "), Ok("score 105".to_string()));
+
+        let ship = world.query::<&Ship>().single(&world);
+        assert!(ship.god_mode);
+        assert!(!ship.invulnerable);
+
+        assert_eq!(run_line(&mut world, "difficulty easy"), Ok("difficulty easy".to_string()));
+        assert!(*world.resource::<GameDifficulty>() == GameDifficulty::Easy);
+
+        run_line(&mut world, "seed 1234").unwrap();
+        assert!(world.contains_resource::<GameRng>());
+    }
+
+    #[test]
+    fn bad_input_is_reported() {
+        let mut world = console_world();
+
+        assert_eq!(run_line(&mut world, "fly"), Err(ConsoleError::UnknownCommand("fly".to_string())));
+        assert_eq!(run_line(&mut world, "score add"), Err(ConsoleError::MissingArgument("points")));
+        assert_eq!(
+            run_line(&mut world, "set_health lots"),
+            Err(ConsoleError::InvalidArgument { argument: "lots".to_string(), expected: "full or a number" })
+        );
+        // No clock in this world
+        assert_eq!(run_line(&mut world, "timescale 0.25"), Err(ConsoleError::Unavailable("clock")));
+        assert_eq!(run_line(&mut world, ""), Ok(String::new()));
+    }
+
+    #[test]
+    fn tab_completes_names_and_arguments() {
+        let world = console_world();
+        let commands = world.resource::<ConsoleCommands>();
+
+        assert_eq!(commands.complete("go"), ("god ".to_string(), vec![]));
+        assert_eq!(commands.complete("s"), ("s".to_string(), vec!["score", "seed", "set_health", "spawn", "state"]));
+        assert_eq!(commands.complete("se"), ("se".to_string(), vec!["seed", "set_health"]));
+        assert_eq!(commands.complete("difficulty h"), ("difficulty hard ".to_string(), vec![]));
+        assert_eq!(commands.complete("state "), ("state ".to_string(), vec!["StartMenu", "InGame", "Paused", "GameOverMenu"]));
+        assert_eq!(commands.complete("spawn asteroid 1"), ("spawn asteroid 1".to_string(), vec![]));
+        assert_eq!(commands.complete("nothing "), ("nothing ".to_string(), vec![]));
+    }
+
+    #[test]
+    fn history_walks_back_and_forth() {
+        let mut console = Console::default();
+
+        for line in ["god", "score add 1", "score add 1", "  "] {
+            console.input = line.to_string();
+            console.submit();
+        }
+
+        // Repeats and blank lines aren't kept
+        assert_eq!(console.history, vec!["god", "score add 1"]);
+        assert_eq!(console.pending.len(), 3);
+
+        console.history_back();
+        assert_eq!(console.input, "score add 1");
+        console.history_back();
+        console.history_back();
+        assert_eq!(console.input, "god");
+        console.history_forward();
+        assert_eq!(console.input, "score add 1");
+        console.history_forward();
+        assert_eq!(console.input, "");
+    }
+
+    #[test]
+    fn scripts_skip_comments_and_blank_lines() {
+        let script = "# boss practice\ngod\n\n  score set 500  \n# done\n";
+
+        assert_eq!(script_lines(script), vec!["god", "score set 500"]);
+    }
+
+    #[test]
+    fn god_mode_keeps_the_weapon_firing() {
+        let mut app = App::new();
+
+        app
+            .add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Image>()
+            .init_asset::<TextureAtlasLayout>()
+            .init_asset::<AudioSource>()
+            .init_resource::<GameAssets>()
+            .init_resource::<WeaponArsenal>()
+            .init_resource::<EntityPools>()
+            .init_resource::<VirtualControls>()
+            .init_resource::<ButtonInput<KeyCode>>()
+            .add_systems(Update, check_if_firing);
+
+        app.world.spawn((
+            Transform::default(),
+            Weapon::default(),
+            Ship {
+                hull: Health { current: 1, max: 5 },
+                shield: Shield::new(0),
+                invulnerable: false,
+                god_mode: false,
+                thrust: 0.0,
+            }
+        ));
+
+        assert_eq!(god(&mut app.world, &[]), Ok("god mode on".to_string()));
+
+        app.world.resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::Space);
+        app.update();
+
+        assert!(app.world.query::<&Rocket>().iter(&app.world).count() > 0);
+    }
+}
@@ -12,8 +12,15 @@ pub const GRID_SIZE: f32 = 40.0;
 
 pub const BACKGROUND_DIMENSIONS: Vec2 = Vec2::new(360.0, 5760.0);
 
-// will eventually be decided by the players
+// Cruising scroll speed, the player throttles between SCROLL_SPEED_MIN and SCROLL_SPEED_MAX
 pub const KINEMATIC_OBJECTS_SPEED: f32 = 150.;
+pub const SCROLL_SPEED_MIN: f32 = 80.;
+pub const SCROLL_SPEED_MAX: f32 = 330.;
+// Units per second squared
+pub const SCROLL_ACCELERATION: f32 = 90.;
+pub const SCROLL_BRAKING: f32 = 160.;
+// How fast the scroll eases back to cruising speed once the throttle is let go
+pub const SCROLL_RETURN: f32 = 60.;
 
 
 
@@ -28,6 +35,20 @@ pub const SHIP_SPEED: f32 = 100.;
 pub const SHIP_SPEC: Vec2 = Vec2::new(SHIP_GAME_WIDTH, SHIP_GAME_HEIGHT);
 pub const SHIP_PADDING: f32 = 0.0;
 pub const SHIP_APPLIED_SCALE: Vec3 = Vec3::new(1.5, 1.5, 1.0);
+pub const SHIP_HULL_POINTS: u32 = 3;
+// The ship's hit circle
+pub const SHIP_RADIUS: f32 = SHIP_TRUE_WIDTH * SHIP_APPLIED_SCALE.x / 2.0;
+
+// Shield
+pub const SHIP_SHIELD_POINTS: u32 = 2;
+// Seconds after the last hit before the shield starts coming back
+pub const SHIELD_REGEN_DELAY: f32 = 3.0;
+// Seconds per point once it does
+pub const SHIELD_REGEN_INTERVAL: f32 = 1.5;
+// Hits right after the shield absorbed one are ignored, so one ram doesn't drain it in a few frames
+pub const SHIELD_GRACE_PERIOD: f32 = 0.5;
+pub const SHIELD_BUBBLE_RADIUS: f32 = 20.0;
+pub const SHIELD_COLOR: Color = Color::rgba(0.3, 0.7, 1.0, 0.35);
 
 
 pub const MAGNITUDE_FORCE: f32 = 1.5;
@@ -41,9 +62,10 @@ pub const ASTEROID_TRUE_WIDTH: f32 = 37.0;
 pub const ASTEROID_TRUE_HEIGHT: f32 = 32.0;
 pub const ASTEROID_APPLIED_SCALE: Vec3 = Vec3::new(1.5, 1.5, 1.0);
 pub const ASTEROID_SCALED_RADIUS: f32 = ASTEROID_TRUE_WIDTH * ASTEROID_APPLIED_SCALE.x / 2.0;
+// Relative speed along the contact normal above which colliding asteroids chip each other
+pub const ASTEROID_CHIP_DAMAGE_SPEED: f32 = 120.0;
 
 // Rocket
-pub const ROCKET_SPEED: f32 = 300.;
 pub const ROCKET_GAME_WIDTH: f32 = 50.0;
 pub const ROCKET_GAME_HEIGHT: f32 = 50.0;
 pub const ROCKET_TRUE_WIDTH: f32 = 6.0;
@@ -55,6 +77,27 @@ pub const ASTEROID_HALF_SIZE: Vec2 = Vec2::new(
     ROCKET_TRUE_WIDTH
 );
 
+// Enemy Ships
+pub const ENEMY_SHIP_APPLIED_SCALE: Vec3 = Vec3::new(1.2, 1.2, 1.0);
+pub const ENEMY_SHIP_RADIUS: f32 = SHIP_TRUE_WIDTH * ENEMY_SHIP_APPLIED_SCALE.x / 2.0;
+pub const ENEMY_PROJECTILE_SPEED: f32 = 220.;
+pub const ENEMY_PROJECTILE_HALF_SIZE: Vec2 = Vec2::new(3.0, 6.0);
+pub const ENEMY_WEAVE_AMPLITUDE: f32 = 70.0;
+pub const ENEMY_WEAVE_FREQUENCY: f32 = 2.5;
+// Distance below the top of the screen where strafing enemies stop descending
+pub const ENEMY_STRAFE_DEPTH: f32 = 130.0;
+pub const ENEMY_STRAFE_DURATION: f32 = 7.0;
+
+// Pickups
+pub const PICKUP_SIZE: Vec2 = Vec2::new(16.0, 16.0);
+pub const PICKUP_RADIUS: f32 = 10.0;
+pub const PICKUP_RAPID_FIRE_DURATION: f32 = 10.0;
+pub const PICKUP_SCORE_MULTIPLIER_DURATION: f32 = 12.0;
+pub const RAPID_FIRE_COOLDOWN_MULTIPLIER: f32 = 0.5;
+pub const SCORE_MULTIPLIER: u64 = 2;
+
+// Particles
+pub const PARTICLE_BUDGET: usize = 800;
 
 
 // Default Wall Positions
@@ -0,0 +1,447 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+    window::PrimaryWindow
+};
+
+use bevy_screen_diagnostics::{Aggregate, ScreenDiagnostics};
+
+use crate::{
+    asteroid::{Asteroid, AsteroidHitbox},
+    console::{ConsoleCommand, ConsoleError, RegisterConsoleCommand},
+    constants::*,
+    touch_controls::window_to_camera_offset,
+    wall::GameWall,
+    CollidableComponentNames,
+    Collider,
+    GameCamera,
+    Grid,
+    Velocity
+};
+
+/// Developer overlay toggled with F3, or on from the start with `--debug`. Draws every
+/// collider with the extents `check_collision` uses, the collision grid with how many
+/// colliders each cell holds, the walls and velocity vectors, and describes the collider
+/// under the cursor. The collider count and busiest cell join the screen diagnostics.
+pub struct DebugOverlayPlugin;
+
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(DebugOverlay::from_args())
+            .register_console_command("debug", ConsoleCommand {
+                help: "debug, toggles the debug overlay",
+                arguments: &[],
+                run: toggle_from_console
+            })
+            .register_diagnostic(Diagnostic::new(COLLIDER_COUNT))
+            .register_diagnostic(Diagnostic::new(BUSIEST_CELL))
+            .add_systems(Startup, (setup_debug_diagnostics, setup_hover_text))
+            .add_systems(Update, toggle_debug_overlay)
+            .add_systems(PostUpdate, (
+                measure_colliders,
+                update_cell_labels,
+                describe_hovered_collider,
+                (
+                    draw_colliders,
+                    draw_grid,
+                    draw_walls,
+                    draw_velocities,
+                ).run_if(debug_overlay_enabled),
+            ));
+    }
+}
+
+pub const COLLIDER_COUNT: DiagnosticPath = DiagnosticPath::const_new("debug/colliders");
+pub const BUSIEST_CELL: DiagnosticPath = DiagnosticPath::const_new("debug/busiest_cell");
+
+const DEBUG_TOGGLE_KEY: KeyCode = KeyCode::F3;
+
+// How far ahead velocity arrows reach
+const VELOCITY_ARROW_SECONDS: f32 = 0.5;
+
+// Colliders further than this from the cursor aren't described
+const HOVER_DISTANCE: f32 = 30.0;
+
+#[derive(Resource, Debug, Default)]
+pub struct DebugOverlay {
+    pub enabled: bool,
+}
+
+impl DebugOverlay {
+    pub fn from_args() -> DebugOverlay {
+        DebugOverlay {
+            enabled: std::env::args().any(|arg| arg == "--debug")
+        }
+    }
+}
+
+fn debug_overlay_enabled(overlay: Res<DebugOverlay>) -> bool {
+    overlay.enabled
+}
+
+/// Shape a collider is tested with, in world space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColliderOutline {
+    Circle { center: Vec2, radius: f32 },
+    Rectangle { center: Vec2, rotation: f32, size: Vec2 },
+}
+
+/// The outlines `check_collision` tests `collider` with. Asteroids have two: the rotated
+/// hit box rockets and the ship hit, and the circle other asteroids bounce off.
+pub fn collider_outlines(
+    transform: &Transform,
+    collider: &Collider,
+    asteroid: Option<&Asteroid>
+) -> Vec<ColliderOutline> {
+
+    let center = transform.translation.truncate();
+
+    match collider.name {
+        CollidableComponentNames::Ship => vec![ColliderOutline::Circle {
+            center,
+            radius: SHIP_TRUE_WIDTH * SHIP_APPLIED_SCALE.x / 2.0
+        }],
+        CollidableComponentNames::EnemyShip => vec![ColliderOutline::Circle { center, radius: ENEMY_SHIP_RADIUS }],
+        CollidableComponentNames::Pickup => vec![ColliderOutline::Circle { center, radius: PICKUP_RADIUS }],
+        // Drawn at their hit box size
+        CollidableComponentNames::Rocket |
+        CollidableComponentNames::EnemyProjectile |
+        CollidableComponentNames::BossPart => vec![ColliderOutline::Rectangle {
+            center,
+            rotation: 0.0,
+            size: transform.scale.truncate()
+        }],
+        CollidableComponentNames::Asteroid => {
+            let hitbox = AsteroidHitbox::from_transform(transform);
+
+            let mut outlines = vec![ColliderOutline::Rectangle {
+                center: hitbox.center,
+                rotation: hitbox.rotation,
+                size: hitbox.half_size * 2.0
+            }];
+
+            if let Some(asteroid) = asteroid {
+                outlines.push(ColliderOutline::Circle { center, radius: asteroid.asteroid_type.radius() });
+            }
+
+            outlines
+        }
+    }
+}
+
+fn collider_color(name: &CollidableComponentNames) -> Color {
+    match name {
+        CollidableComponentNames::Ship => Color::GREEN,
+        CollidableComponentNames::Rocket => Color::YELLOW,
+        CollidableComponentNames::Asteroid => Color::ORANGE_RED,
+        CollidableComponentNames::EnemyShip | CollidableComponentNames::BossPart => Color::FUCHSIA,
+        CollidableComponentNames::EnemyProjectile => Color::PINK,
+        CollidableComponentNames::Pickup => Color::CYAN,
+    }
+}
+
+/// Labels the grid cells with how many colliders they hold
+#[derive(Component)]
+struct CellLabel {
+    x: usize,
+    y: usize,
+}
+
+/// Names the collider under the cursor
+#[derive(Component)]
+struct HoverText;
+
+fn toggle_debug_overlay(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut overlay: ResMut<DebugOverlay>,
+) {
+    if keyboard_input.just_pressed(DEBUG_TOGGLE_KEY) {
+        overlay.enabled = !overlay.enabled;
+    }
+}
+
+fn toggle_from_console(world: &mut World, _args: &[&str]) -> Result<String, ConsoleError> {
+    let mut overlay = world.resource_mut::<DebugOverlay>();
+
+    overlay.enabled = !overlay.enabled;
+
+    Ok(format!("debug overlay {}", if overlay.enabled { "on" } else { "off" }))
+}
+
+fn setup_debug_diagnostics(mut screen_diagnostics: ResMut<ScreenDiagnostics>) {
+
+    for (name, path) in [
+        ("colliders", COLLIDER_COUNT),
+        ("busiest cell", BUSIEST_CELL),
+    ] {
+        screen_diagnostics
+            .add(name.to_string(), path)
+            .aggregate(Aggregate::Value)
+            .format(|value| format!("{value:.0}"));
+    }
+}
+
+fn setup_hover_text(mut commands: Commands) {
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 12.0,
+                color: Color::WHITE,
+                ..default()
+            }
+        ).with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(8.0),
+            bottom: Val::Px(8.0),
+            ..default()
+        }),
+        HoverText
+    ));
+}
+
+fn measure_colliders(grid: Res<Grid>, mut diagnostics: Diagnostics) {
+
+    let colliders: usize = grid.iter().flatten().map(|cell| cell.len()).sum();
+    let busiest = grid.iter().flatten().map(|cell| cell.len()).max().unwrap_or(0);
+
+    diagnostics.add_measurement(&COLLIDER_COUNT, || colliders as f64);
+    diagnostics.add_measurement(&BUSIEST_CELL, || busiest as f64);
+}
+
+fn draw_colliders(
+    mut gizmos: Gizmos,
+    collider_query: Query<(&Transform, &Collider, Option<&Asteroid>)>,
+) {
+    for (transform, collider, asteroid) in collider_query.iter() {
+        let color = collider_color(&collider.name);
+
+        for outline in collider_outlines(transform, collider, asteroid) {
+            match outline {
+                ColliderOutline::Circle { center, radius } => {
+                    gizmos.circle_2d(center, radius, color);
+                },
+                ColliderOutline::Rectangle { center, rotation, size } => {
+                    gizmos.rect_2d(center, rotation, size, color);
+                },
+            }
+        }
+    }
+}
+
+fn draw_grid(
+    mut gizmos: Gizmos,
+    grid: Res<Grid>,
+    camera_query: Query<&Transform, With<GameCamera>>,
+) {
+    let Ok(camera_transform) = camera_query.get_single() else { return; };
+
+    // Cells are laid out from the bottom left corner of the screen, see Grid::cell_of
+    let origin = camera_transform.translation.truncate() - Vec2::new(WINDOW_WIDTH, WINDOW_HEIGHT) / 2.0;
+
+    let columns = grid.len();
+    let rows = grid.first().map_or(0, |column| column.len());
+
+    let width = columns as f32 * grid.grid_size;
+    let height = rows as f32 * grid.grid_size;
+
+    let color = Color::rgba(1.0, 1.0, 1.0, 0.15);
+
+    for x in 0..=columns {
+        let offset = origin + Vec2::new(x as f32 * grid.grid_size, 0.0);
+        gizmos.line_2d(offset, offset + Vec2::new(0.0, height), color);
+    }
+
+    for y in 0..=rows {
+        let offset = origin + Vec2::new(0.0, y as f32 * grid.grid_size);
+        gizmos.line_2d(offset, offset + Vec2::new(width, 0.0), color);
+    }
+}
+
+fn draw_walls(
+    mut gizmos: Gizmos,
+    camera_query: Query<&Transform, With<GameCamera>>,
+) {
+    let Ok(camera_transform) = camera_query.get_single() else { return; };
+
+    // The walls scroll with the camera
+    let camera_translation = camera_transform.translation.truncate();
+
+    for wall in [GameWall::Top, GameWall::Bottom, GameWall::Left, GameWall::Right] {
+        gizmos.rect_2d(camera_translation + wall.position(), 0.0, wall.size(), Color::BLUE);
+    }
+}
+
+fn draw_velocities(
+    mut gizmos: Gizmos,
+    velocity_query: Query<(&Transform, &Velocity)>,
+) {
+    for (transform, velocity) in velocity_query.iter() {
+        let start = transform.translation.truncate();
+
+        gizmos.arrow_2d(start, start + **velocity * VELOCITY_ARROW_SECONDS, Color::LIME_GREEN);
+    }
+}
+
+/// Counts come from the grid, so they are what the collision checks saw this frame
+fn update_cell_labels(
+    mut commands: Commands,
+    overlay: Res<DebugOverlay>,
+    grid: Res<Grid>,
+    camera_query: Query<Entity, With<GameCamera>>,
+    mut label_query: Query<(&CellLabel, &mut Text, &mut Visibility)>,
+) {
+
+    if label_query.is_empty() {
+
+        if !overlay.enabled { return; }
+
+        let Ok(camera) = camera_query.get_single() else { return; };
+
+        // Children of the camera, so they scroll with the grid
+        commands.entity(camera).with_children(|parent| {
+            for (x, column) in grid.iter().enumerate() {
+                for y in 0..column.len() {
+                    let center = (Vec2::new(x as f32, y as f32) + 0.5) * grid.grid_size
+                        - Vec2::new(WINDOW_WIDTH, WINDOW_HEIGHT) / 2.0;
+
+                    parent.spawn((
+                        Text2dBundle {
+                            text: Text::from_section(
+                                "",
+                                TextStyle {
+                                    font_size: 10.0,
+                                    color: Color::rgba(1.0, 1.0, 1.0, 0.6),
+                                    ..default()
+                                }
+                            ),
+                            // The camera sits at the far end of the view, pull the labels back in front
+                            transform: Transform::from_translation(center.extend(-990.0)),
+                            ..default()
+                        },
+                        CellLabel { x, y }
+                    ));
+                }
+            }
+        });
+
+        return;
+    }
+
+    let visibility = if overlay.enabled { Visibility::Inherited } else { Visibility::Hidden };
+
+    for (label, mut text, mut label_visibility) in label_query.iter_mut() {
+        if *label_visibility != visibility {
+            *label_visibility = visibility;
+        }
+
+        if !overlay.enabled { continue; }
+
+        let count = grid[label.x][label.y].len();
+        let value = if count == 0 { String::new() } else { count.to_string() };
+
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn describe_hovered_collider(
+    overlay: Res<DebugOverlay>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<&Transform, With<GameCamera>>,
+    collider_query: Query<(Entity, &Transform, &Collider, Option<&Velocity>, Option<&Asteroid>)>,
+    mut text_query: Query<&mut Text, With<HoverText>>,
+) {
+
+    let Ok(mut text) = text_query.get_single_mut() else { return; };
+
+    let (Ok(window), Ok(camera_transform)) = (window_query.get_single(), camera_query.get_single()) else { return; };
+
+    // Cleared as soon as the overlay is off
+    let cursor_position = window.cursor_position().filter(|_| overlay.enabled);
+
+    let hovered = cursor_position.and_then(|cursor_position| {
+        let cursor = camera_transform.translation.truncate() + window_to_camera_offset(window, cursor_position);
+
+        collider_query.iter()
+            .map(|hovered| (hovered.1.translation.truncate().distance(cursor), hovered))
+            .filter(|(distance, _)| *distance <= HOVER_DISTANCE)
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, hovered)| hovered)
+    });
+
+    let description = match hovered {
+        Some((entity, transform, collider, velocity, asteroid)) => {
+            let mut lines = vec![
+                format!("{:?} {:?}", collider.name, entity),
+                format!("position {:.0}, {:.0}", transform.translation.x, transform.translation.y),
+            ];
+
+            if let Some(velocity) = velocity {
+                lines.push(format!("velocity {:.0}, {:.0}", velocity.x, velocity.y));
+            }
+
+            if let Some(asteroid) = asteroid {
+                lines.push(format!("{:?}, {} hit points", asteroid.asteroid_type, asteroid.hit_points));
+            }
+
+            lines.join("\n")
+        },
+        None => String::new()
+    };
+
+    if text.sections[0].value != description {
+        text.sections[0].value = description;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::bounding::Aabb2d;
+
+    use super::*;
+    use crate::{asteroid::AsteroidType, ColliderShape};
+
+    #[test]
+    fn rocket_outline_matches_its_hit_box() {
+        let transform = Transform::from_xyz(10.0, 20.0, 0.0).with_scale(ROCKET_APPLIED_SCALE);
+        let collider = Collider { name: CollidableComponentNames::Rocket, shape: ColliderShape::Rectangle };
+
+        let outlines = collider_outlines(&transform, &collider, None);
+
+        let [ColliderOutline::Rectangle { center, rotation, size }] = outlines[..] else {
+            panic!("expected a single rectangle, got {outlines:?}");
+        };
+
+        assert_eq!(rotation, 0.0);
+
+        // Same box as Rocket::check_collision builds
+        let hit_box = Aabb2d::new(transform.translation.truncate(), transform.scale.truncate() / 2.0);
+        let drawn = Aabb2d::new(center, size / 2.0);
+
+        assert_eq!((drawn.min, drawn.max), (hit_box.min, hit_box.max));
+    }
+
+    #[test]
+    fn asteroid_outlines_are_its_hit_box_and_bounce_circle() {
+        let asteroid = Asteroid::new(AsteroidType::Large);
+        let scale = AsteroidType::Large.scale();
+
+        let mut transform = Transform::from_xyz(-30.0, 5.0, 0.0).with_scale(Vec3::new(scale, scale, 1.0));
+        transform.rotate_z(0.4);
+
+        let collider = Collider { name: CollidableComponentNames::Asteroid, shape: ColliderShape::OrientedRectangle };
+
+        let outlines = collider_outlines(&transform, &collider, Some(&asteroid));
+        let hitbox = AsteroidHitbox::from_transform(&transform);
+
+        assert_eq!(outlines, vec![
+            ColliderOutline::Rectangle { center: hitbox.center, rotation: hitbox.rotation, size: hitbox.half_size * 2.0 },
+            ColliderOutline::Circle { center: Vec2::new(-30.0, 5.0), radius: AsteroidType::Large.radius() },
+        ]);
+    }
+}
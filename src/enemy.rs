@@ -0,0 +1,539 @@
+use bevy::{
+    math::bounding::{Aabb2d, BoundingCircle, IntersectsVolume},
+    prelude::*
+};
+
+use serde::Deserialize;
+
+use crate::{
+    constants::*,
+    scroll::ScrollSpeed,
+    ship::Ship,
+    animation::{Animator, DespawnOnFinish},
+    game_assets::GameAssets,
+    weapon::ROCKET_TEXTURE,
+    CollidableComponentNames,
+    Collider,
+    ColliderShape,
+    GameCamera,
+    MyGameSet,
+    Velocity
+};
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<EnemyDestroyed>()
+            .add_systems(FixedUpdate, (
+                move_enemy_ships,
+                enemy_fire.after(move_enemy_ships),
+                move_enemy_projectiles,
+                enemy_manager.after(enemy_fire).after(move_enemy_projectiles),
+            ).in_set(MyGameSet));
+    }
+}
+
+/// How an enemy ship flies, set per spawn in the wave script
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum EnemyPattern {
+    /// Straight down the screen
+    Dive,
+    /// Down the screen in a sine wave around where it spawned
+    Weave,
+    /// Drops in near the top and moves from wall to wall for a while
+    Strafe,
+    /// Homes in on the player's ship and rams it
+    Kamikaze,
+}
+
+impl EnemyPattern {
+    pub fn hit_points(&self) -> u32 {
+        match self {
+            EnemyPattern::Dive => 1,
+            EnemyPattern::Weave => 2,
+            EnemyPattern::Strafe => 3,
+            EnemyPattern::Kamikaze => 1,
+        }
+    }
+
+    /// Screen space speed, on top of the scrolling
+    pub fn speed(&self) -> f32 {
+        match self {
+            EnemyPattern::Dive => 160.0,
+            EnemyPattern::Weave => 90.0,
+            EnemyPattern::Strafe => 110.0,
+            EnemyPattern::Kamikaze => 190.0,
+        }
+    }
+
+    /// Seconds between shots, kamikazes don't shoot
+    pub fn fire_interval(&self) -> Option<f32> {
+        match self {
+            EnemyPattern::Dive => Some(1.4),
+            EnemyPattern::Weave => Some(1.1),
+            EnemyPattern::Strafe => Some(0.8),
+            EnemyPattern::Kamikaze => None,
+        }
+    }
+
+    pub fn score_value(&self) -> u64 {
+        match self {
+            EnemyPattern::Dive => 8,
+            EnemyPattern::Weave => 10,
+            EnemyPattern::Strafe => 15,
+            EnemyPattern::Kamikaze => 12,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            EnemyPattern::Dive => Color::rgb(1.0, 0.55, 0.55),
+            EnemyPattern::Weave => Color::rgb(1.0, 0.8, 0.4),
+            EnemyPattern::Strafe => Color::rgb(0.85, 0.5, 1.0),
+            EnemyPattern::Kamikaze => Color::rgb(1.0, 0.3, 0.2),
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct EnemyDestroyed {
+    pub pattern: EnemyPattern
+}
+
+#[derive(Component, Debug)]
+pub struct EnemyShip {
+    pub pattern: EnemyPattern,
+    pub hit_points: u32,
+    pub exploding: bool,
+    // Same as asteroids, a rocket can overlap for a couple of frames
+    pub last_hit_by: Option<Entity>,
+    pub speed: f32,
+    pub time_alive: f32,
+    pub fire_timer: Option<Timer>,
+    // Where a weaving enemy swings around
+    pub anchor_x: f32,
+    pub strafe_direction: f32,
+}
+
+impl EnemyShip {
+    pub fn new(pattern: EnemyPattern, anchor_x: f32, speed_multiplier: f32) -> EnemyShip {
+        EnemyShip {
+            pattern,
+            hit_points: pattern.hit_points(),
+            exploding: false,
+            last_hit_by: None,
+            speed: pattern.speed() * speed_multiplier,
+            time_alive: 0.0,
+            fire_timer: pattern.fire_interval().map(|interval| Timer::from_seconds(interval / speed_multiplier, TimerMode::Repeating)),
+            anchor_x,
+            strafe_direction: if anchor_x > 0.0 { -1.0 } else { 1.0 },
+        }
+    }
+
+    /// Returns true once the enemy has no hit points left
+    pub fn take_damage(&mut self, damage: u32) -> bool {
+        self.hit_points = self.hit_points.saturating_sub(damage);
+
+        if self.hit_points == 0 {
+            self.exploding = true;
+        }
+
+        self.exploding
+    }
+
+    /// Velocity relative to the camera. `offset` and `ship_offset` are both relative to the camera too.
+    pub fn screen_velocity(&mut self, offset: Vec2, ship_offset: Vec2) -> Vec2 {
+        match self.pattern {
+            EnemyPattern::Dive => Vec2::new(0.0, -self.speed),
+            EnemyPattern::Weave => {
+                // Follows anchor_x + sin(t * f) * A, pulled back onto it if knocked off
+                let phase = self.time_alive * ENEMY_WEAVE_FREQUENCY;
+                let target_x = self.anchor_x + phase.sin() * ENEMY_WEAVE_AMPLITUDE;
+                let swing = phase.cos() * ENEMY_WEAVE_AMPLITUDE * ENEMY_WEAVE_FREQUENCY;
+
+                Vec2::new(swing + (target_x - offset.x) * 4.0, -self.speed)
+            },
+            EnemyPattern::Strafe => {
+                if self.time_alive > ENEMY_STRAFE_DURATION {
+                    return Vec2::new(0.0, -self.speed * 1.5);
+                }
+
+                if offset.y > TOP_WALL - ENEMY_STRAFE_DEPTH {
+                    return Vec2::new(0.0, -self.speed);
+                }
+
+                let hits_left = offset.x - ENEMY_SHIP_RADIUS <= LEFT_WALL && self.strafe_direction < 0.0;
+                let hits_right = offset.x + ENEMY_SHIP_RADIUS >= RIGHT_WALL && self.strafe_direction > 0.0;
+
+                if hits_left || hits_right {
+                    self.strafe_direction = -self.strafe_direction;
+                }
+
+                Vec2::new(self.strafe_direction * self.speed, 0.0)
+            },
+            EnemyPattern::Kamikaze => {
+                // Once it is past the ship it just keeps going
+                if offset.y < ship_offset.y {
+                    return Vec2::new(0.0, -self.speed);
+                }
+
+                (ship_offset - offset).try_normalize().unwrap_or(Vec2::NEG_Y) * self.speed
+            }
+        }
+    }
+
+    pub fn is_outside_window(&self, enemy_transform: &Transform, camera_transform: &Transform) -> bool {
+        let offset = enemy_transform.translation - camera_transform.translation;
+
+        offset.y < BOTTOM_WALL - ENEMY_SHIP_RADIUS * 2.0 || offset.x.abs() > RIGHT_WALL + ENEMY_SHIP_RADIUS * 2.0
+    }
+
+    pub fn check_collision(
+        &self,
+        enemy_transform: &Transform,
+        other_transform: &Transform,
+        other_name: &CollidableComponentNames
+    ) -> bool {
+        let enemy_circle = BoundingCircle::new(enemy_transform.translation.truncate(), ENEMY_SHIP_RADIUS);
+
+        match other_name {
+            CollidableComponentNames::Rocket => {
+                let rocket_rectangle = Aabb2d::new(
+                    other_transform.translation.truncate(),
+                    other_transform.scale.truncate() / 2.0
+                );
+
+                enemy_circle.intersects(&rocket_rectangle)
+            },
+            CollidableComponentNames::Ship => {
+                let ship_circle = BoundingCircle::new(
+                    other_transform.translation.truncate(),
+                    SHIP_TRUE_WIDTH * SHIP_APPLIED_SCALE.x / 2.0
+                );
+
+                enemy_circle.intersects(&ship_circle)
+            },
+            _ => false
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct EnemyShipBundle {
+    pub enemy: EnemyShip,
+    pub sprite_bundle: SpriteSheetBundle,
+    pub collider: Collider,
+    pub animator: Animator,
+    pub despawn_on_finish: DespawnOnFinish,
+    pub velocity: Velocity,
+}
+
+impl EnemyShipBundle {
+    pub fn new(
+        game_assets: &GameAssets,
+        spawn_location: Vec3,
+        pattern: EnemyPattern,
+        speed_multiplier: f32
+    ) -> EnemyShipBundle {
+
+        EnemyShipBundle {
+            enemy: EnemyShip::new(pattern, spawn_location.x, speed_multiplier),
+            sprite_bundle: SpriteSheetBundle {
+                transform: Transform {
+                    translation: spawn_location,
+                    rotation: Quat::from_rotation_z(std::f32::consts::PI),
+                    scale: ENEMY_SHIP_APPLIED_SCALE,
+                },
+                sprite: Sprite {
+                    color: pattern.color(),
+                    ..default()
+                },
+                // Same sheet as the player's ship, flipped and tinted
+                atlas: TextureAtlas {
+                    layout: game_assets.ship_layout.clone(),
+                    index: 1
+                },
+                texture: game_assets.ship_texture.clone(),
+                ..default()
+            },
+            collider: Collider {
+                name: CollidableComponentNames::EnemyShip,
+                shape: ColliderShape::Circle
+            },
+            // Switched to the asteroid explosion, see explosion_event_listener
+            animator: Animator::new("ship_full"),
+            despawn_on_finish: DespawnOnFinish,
+            velocity: Velocity(Vec2::ZERO),
+        }
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct EnemyProjectile {
+    pub hit_target: bool
+}
+
+impl EnemyProjectile {
+    pub fn check_collision(
+        &self,
+        projectile_transform: &Transform,
+        other_transform: &Transform,
+        other_name: &CollidableComponentNames
+    ) -> bool {
+
+        if *other_name != CollidableComponentNames::Ship { return false; }
+
+        let projectile_rectangle = Aabb2d::new(
+            projectile_transform.translation.truncate(),
+            ENEMY_PROJECTILE_HALF_SIZE
+        );
+
+        let ship_circle = BoundingCircle::new(
+            other_transform.translation.truncate(),
+            SHIP_TRUE_WIDTH * SHIP_APPLIED_SCALE.x / 2.0
+        );
+
+        ship_circle.intersects(&projectile_rectangle)
+    }
+}
+
+#[derive(Bundle)]
+pub struct EnemyProjectileBundle {
+    projectile: EnemyProjectile,
+    sprite_bundle: SpriteSheetBundle,
+    collider: Collider,
+    velocity: Velocity,
+}
+
+impl EnemyProjectileBundle {
+    pub fn new(
+        game_assets: &GameAssets,
+        spawn_location: Vec3,
+        velocity: Vec2
+    ) -> EnemyProjectileBundle {
+
+        EnemyProjectileBundle {
+            projectile: EnemyProjectile { hit_target: false },
+            sprite_bundle: SpriteSheetBundle {
+                transform: Transform {
+                    translation: spawn_location,
+                    // The rocket sprite points up
+                    rotation: Quat::from_rotation_arc_2d(Vec2::Y, velocity.try_normalize().unwrap_or(Vec2::NEG_Y)),
+                    scale: ROCKET_APPLIED_SCALE,
+                },
+                sprite: Sprite {
+                    color: Color::rgb(1.0, 0.35, 0.35),
+                    ..default()
+                },
+                atlas: TextureAtlas {
+                    layout: game_assets.rocket_layout.clone(),
+                    index: 0
+                },
+                texture: game_assets.projectile_texture(ROCKET_TEXTURE),
+                ..default()
+            },
+            collider: Collider {
+                name: CollidableComponentNames::EnemyProjectile,
+                shape: ColliderShape::Rectangle
+            },
+            velocity: Velocity(velocity),
+        }
+    }
+}
+
+/// Where an enemy aims its shot, relative to the camera. Strafers aim at the ship, everyone else fires straight down.
+pub fn aim_projectile(pattern: EnemyPattern, offset: Vec2, ship_offset: Vec2) -> Vec2 {
+    let direction = match pattern {
+        EnemyPattern::Strafe => (ship_offset - offset).try_normalize().unwrap_or(Vec2::NEG_Y),
+        _ => Vec2::NEG_Y
+    };
+
+    direction * ENEMY_PROJECTILE_SPEED
+}
+
+#[allow(clippy::type_complexity)]
+fn move_enemy_ships(
+    time: Res<Time<Fixed>>,
+    scroll_speed: Res<ScrollSpeed>,
+    mut enemy_query: Query<(&mut Transform, &mut Velocity, &mut EnemyShip)>,
+    ship_query: Query<&Transform, (With<Ship>, Without<EnemyShip>)>,
+    camera_query: Query<&Transform, (With<GameCamera>, Without<EnemyShip>)>,
+) {
+
+    let camera_translation = camera_query.single().translation;
+    let ship_offset = (ship_query.single().translation - camera_translation).truncate();
+
+    for (mut transform, mut velocity, mut enemy) in enemy_query.iter_mut() {
+        if enemy.exploding { continue; }
+
+        enemy.time_alive += time.delta_seconds();
+
+        let offset = (transform.translation - camera_translation).truncate();
+
+        // Patterns are in screen space, on top of the camera's scrolling
+        **velocity = enemy.screen_velocity(offset, ship_offset) + Vec2::new(0.0, scroll_speed.current);
+
+        transform.translation += velocity.extend(0.0) * time.delta_seconds();
+    }
+}
+
+#[allow(clippy::type_complexity)]
+#[allow(clippy::too_many_arguments)]
+fn enemy_fire(
+    mut commands: Commands,
+    time: Res<Time<Fixed>>,
+    scroll_speed: Res<ScrollSpeed>,
+    mut enemy_query: Query<(&Transform, &mut EnemyShip)>,
+    ship_query: Query<&Transform, (With<Ship>, Without<EnemyShip>)>,
+    camera_query: Query<&Transform, (With<GameCamera>, Without<EnemyShip>)>,
+    game_assets: Res<GameAssets>,
+) {
+
+    let camera_translation = camera_query.single().translation;
+    let ship_offset = (ship_query.single().translation - camera_translation).truncate();
+
+    for (transform, mut enemy) in enemy_query.iter_mut() {
+        if enemy.exploding { continue; }
+
+        let offset = (transform.translation - camera_translation).truncate();
+
+        // Only shoot while on screen
+        if offset.y > TOP_WALL || offset.y < BOTTOM_WALL { continue; }
+
+        let pattern = enemy.pattern;
+
+        let Some(fire_timer) = enemy.fire_timer.as_mut() else { continue; };
+
+        if !fire_timer.tick(time.delta()).just_finished() { continue; }
+
+        // Shots keep the scroll speed they were fired at
+        let velocity = aim_projectile(pattern, offset, ship_offset) + Vec2::new(0.0, scroll_speed.current);
+
+        commands.spawn(EnemyProjectileBundle::new(
+            &game_assets,
+            transform.translation - Vec3::new(0.0, ENEMY_SHIP_RADIUS, 0.0),
+            velocity
+        ));
+    }
+}
+
+fn move_enemy_projectiles(
+    time: Res<Time<Fixed>>,
+    mut projectile_query: Query<(&mut Transform, &Velocity), With<EnemyProjectile>>,
+) {
+    for (mut transform, velocity) in projectile_query.iter_mut() {
+        transform.translation += velocity.extend(0.0) * time.delta_seconds();
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn enemy_manager(
+    mut commands: Commands,
+    enemy_query: Query<(Entity, &Transform, &EnemyShip)>,
+    projectile_query: Query<(Entity, &Transform, &EnemyProjectile)>,
+    camera_query: Query<&Transform, (With<GameCamera>, Without<EnemyShip>, Without<EnemyProjectile>)>,
+) {
+
+    let camera_transform = camera_query.single();
+
+    for (entity, transform, enemy) in enemy_query.iter() {
+        if enemy.exploding { continue; }
+
+        if enemy.is_outside_window(transform, camera_transform) {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for (entity, transform, projectile) in projectile_query.iter() {
+        let offset = transform.translation - camera_transform.translation;
+
+        let off_screen = offset.y < BOTTOM_WALL - WALL_THICKNESS || offset.y > TOP_WALL + WALL_THICKNESS || offset.x.abs() > RIGHT_WALL;
+
+        if projectile.hit_target || off_screen {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enemies_survive_until_their_hit_points_run_out() {
+        let mut enemy = EnemyShip::new(EnemyPattern::Strafe, 0.0, 1.0);
+
+        assert!(!enemy.take_damage(1));
+        assert!(!enemy.take_damage(1));
+        assert!(enemy.take_damage(1));
+        assert!(enemy.exploding);
+    }
+
+    #[test]
+    fn weaving_enemies_swing_around_their_anchor() {
+        let mut enemy = EnemyShip::new(EnemyPattern::Weave, 40.0, 1.0);
+
+        let delta = 1.0 / 64.0;
+        let mut x = enemy.anchor_x;
+        let (mut min_x, mut max_x) = (x, x);
+
+        for _ in 0..(64.0 * 2.0 * std::f32::consts::PI / ENEMY_WEAVE_FREQUENCY) as u32 {
+            enemy.time_alive += delta;
+            x += enemy.screen_velocity(Vec2::new(x, 0.0), Vec2::ZERO).x * delta;
+            min_x = min_x.min(x);
+            max_x = max_x.max(x);
+        }
+
+        // One full swing, back where it started
+        assert!((x - enemy.anchor_x).abs() < 5.0);
+        assert!((max_x - enemy.anchor_x - ENEMY_WEAVE_AMPLITUDE).abs() < 5.0);
+        assert!((enemy.anchor_x - min_x - ENEMY_WEAVE_AMPLITUDE).abs() < 5.0);
+    }
+
+    #[test]
+    fn strafing_enemies_turn_at_the_walls() {
+        let mut enemy = EnemyShip::new(EnemyPattern::Strafe, 0.0, 1.0);
+        let strafe_height = TOP_WALL - ENEMY_STRAFE_DEPTH - 10.0;
+
+        // Still dropping in
+        assert_eq!(enemy.screen_velocity(Vec2::new(0.0, TOP_WALL), Vec2::ZERO).x, 0.0);
+
+        let heading_right = enemy.screen_velocity(Vec2::new(0.0, strafe_height), Vec2::ZERO);
+        assert!(heading_right.x > 0.0 && heading_right.y == 0.0);
+
+        let at_wall = enemy.screen_velocity(Vec2::new(RIGHT_WALL, strafe_height), Vec2::ZERO);
+        assert!(at_wall.x < 0.0);
+
+        enemy.time_alive = ENEMY_STRAFE_DURATION + 1.0;
+        assert!(enemy.screen_velocity(Vec2::new(0.0, strafe_height), Vec2::ZERO).y < 0.0);
+    }
+
+    #[test]
+    fn kamikazes_home_in_on_the_ship() {
+        let mut enemy = EnemyShip::new(EnemyPattern::Kamikaze, 0.0, 1.0);
+
+        let velocity = enemy.screen_velocity(Vec2::new(100.0, 200.0), Vec2::new(-50.0, -100.0));
+        let towards_ship = Vec2::new(-150.0, -300.0).normalize();
+
+        assert!(velocity.normalize().dot(towards_ship) > 0.999);
+        assert!((velocity.length() - EnemyPattern::Kamikaze.speed()).abs() < 0.01);
+
+        // Missed, keeps diving
+        assert_eq!(enemy.screen_velocity(Vec2::new(0.0, -200.0), Vec2::ZERO), Vec2::new(0.0, -enemy.speed));
+    }
+
+    #[test]
+    fn projectiles_only_hit_the_ship() {
+        let projectile = EnemyProjectile { hit_target: false };
+        let position = Transform::from_xyz(0.0, 0.0, 0.0);
+
+        assert!(projectile.check_collision(&position, &Transform::from_xyz(10.0, 5.0, 0.0), &CollidableComponentNames::Ship));
+        assert!(!projectile.check_collision(&position, &Transform::from_xyz(100.0, 0.0, 0.0), &CollidableComponentNames::Ship));
+        assert!(!projectile.check_collision(&position, &position, &CollidableComponentNames::Asteroid));
+
+        assert_eq!(aim_projectile(EnemyPattern::Dive, Vec2::ZERO, Vec2::new(100.0, -100.0)), Vec2::new(0.0, -ENEMY_PROJECTILE_SPEED));
+        assert!(aim_projectile(EnemyPattern::Strafe, Vec2::ZERO, Vec2::new(100.0, -100.0)).x > 0.0);
+    }
+}
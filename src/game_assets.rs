@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    shield::SHIELD_BREAK_SOUND,
+    weapon::{WeaponArsenal, ROCKET_TEXTURE}
+};
+
+/// Every sprite sheet and sound the game spawns from, loaded once up front. Bundles take their
+/// handles from `GameAssets` instead of loading textures and adding layouts on every
+/// spawn. The loading screen waits for the textures, see `loading::track_loading`, and
+/// hands over the manifest's images for textures named in data files.
+pub struct GameAssetsPlugin;
+
+impl Plugin for GameAssetsPlugin {
+    fn build(&self, _app: &mut App) {}
+
+    // After every plugin is built, so weapons registered by other plugins are in the arsenal
+    fn finish(&self, app: &mut App) {
+        app.init_resource::<GameAssets>();
+    }
+}
+
+pub const SHIP_TEXTURE: &str = "ship/ship_spritesheet_empty_space.png";
+pub const ASTEROID_TEXTURE: &str = "enemys/asteroid_explosion_sprite.png";
+pub const BOSS_TEXTURE: &str = "ship/ship_full.png";
+
+#[derive(Resource, Debug)]
+pub struct GameAssets {
+    /// Also used by enemy ships
+    pub ship_texture: Handle<Image>,
+    pub ship_layout: Handle<TextureAtlasLayout>,
+    /// Also the explosion enemy ships borrow
+    pub asteroid_texture: Handle<Image>,
+    pub asteroid_layout: Handle<TextureAtlasLayout>,
+    /// Shared by the player's weapons and enemy projectiles
+    pub rocket_layout: Handle<TextureAtlasLayout>,
+    pub boss_texture: Handle<Image>,
+    pub shield_break_sound: Handle<AudioSource>,
+    // By path, for every projectile in the arsenal
+    projectile_textures: HashMap<&'static str, Handle<Image>>,
+    // By path, every image in the manifest, for the parallax layers
+    images: HashMap<String, Handle<Image>>,
+}
+
+impl GameAssets {
+    pub fn projectile_texture(&self, path: &str) -> Handle<Image> {
+        // Weapons registered after loading fall back to the rocket
+        self.projectile_textures.get(path)
+            .unwrap_or(&self.projectile_textures[ROCKET_TEXTURE])
+            .clone()
+    }
+
+    /// An image the manifest lists, `None` for any other path
+    pub fn image(&self, path: &str) -> Option<Handle<Image>> {
+        self.images.get(path).cloned()
+    }
+
+    pub fn add_images(&mut self, asset_server: &AssetServer, paths: &[String]) {
+        for path in paths {
+            self.images.insert(path.clone(), asset_server.load(path));
+        }
+    }
+
+    pub fn textures(&self) -> impl Iterator<Item = &Handle<Image>> {
+        [&self.ship_texture, &self.asteroid_texture, &self.boss_texture].into_iter()
+            .chain(self.projectile_textures.values())
+            .chain(self.images.values())
+    }
+}
+
+impl FromWorld for GameAssets {
+    fn from_world(world: &mut World) -> GameAssets {
+        let asset_server = world.resource::<AssetServer>();
+
+        let mut projectile_paths = vec![ROCKET_TEXTURE];
+
+        if let Some(arsenal) = world.get_resource::<WeaponArsenal>() {
+            projectile_paths.extend(arsenal.iter().map(|weapon| weapon.projectile.texture));
+        }
+
+        let projectile_textures = projectile_paths.into_iter()
+            .map(|path| (path, asset_server.load(path)))
+            .collect();
+
+        let ship_texture = asset_server.load(SHIP_TEXTURE);
+        let asteroid_texture = asset_server.load(ASTEROID_TEXTURE);
+        let boss_texture = asset_server.load(BOSS_TEXTURE);
+        let shield_break_sound = asset_server.load(SHIELD_BREAK_SOUND);
+
+        let mut layouts = world.resource_mut::<Assets<TextureAtlasLayout>>();
+
+        GameAssets {
+            ship_texture,
+            ship_layout: layouts.add(TextureAtlasLayout::from_grid(Vec2::new(48.0, 48.0), 5, 1, None, None)),
+            asteroid_texture,
+            asteroid_layout: layouts.add(TextureAtlasLayout::from_grid(Vec2::new(96.0, 96.0), 8, 1, None, None)),
+            rocket_layout: layouts.add(TextureAtlasLayout::from_grid(Vec2::new(32.0, 32.0), 3, 1, None, None)),
+            boss_texture,
+            shield_break_sound,
+            projectile_textures,
+            images: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        asteroid::{AsteroidBundle, AsteroidType},
+        enemy::{EnemyPattern, EnemyProjectileBundle, EnemyShipBundle},
+        pool::EntityPools,
+        weapon::fire_weapon
+    };
+
+    #[derive(Component)]
+    struct Spawned;
+
+    /// A frame's worth of everything the game spawns from sprite sheets
+    fn spawn_everything(
+        mut commands: Commands,
+        game_assets: Res<GameAssets>,
+        arsenal: Res<WeaponArsenal>,
+        mut pools: ResMut<EntityPools>,
+        spawned_query: Query<Entity, With<Spawned>>,
+    ) {
+
+        for entity in spawned_query.iter() {
+            commands.entity(entity).despawn();
+        }
+
+        for weapon in arsenal.iter() {
+            fire_weapon(&mut commands, &game_assets, &mut pools, weapon, Vec3::ZERO);
+        }
+
+        commands.spawn(AsteroidBundle::new(&game_assets, &Transform::default(), None, AsteroidType::Large, &mut rand::thread_rng()));
+        commands.spawn(EnemyShipBundle::new(&game_assets, Vec3::ZERO, EnemyPattern::Dive, 1.0));
+        commands.spawn(EnemyProjectileBundle::new(&game_assets, Vec3::ZERO, Vec2::NEG_Y));
+    }
+
+    fn tag_spawned(mut commands: Commands, sprite_query: Query<Entity, (With<TextureAtlas>, Without<Spawned>)>) {
+        for entity in sprite_query.iter() {
+            commands.entity(entity).insert(Spawned);
+        }
+    }
+
+    #[test]
+    fn asset_counts_stay_constant_over_a_long_run() {
+        let mut app = App::new();
+
+        app
+            .add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Image>()
+            .init_asset::<TextureAtlasLayout>()
+            .init_asset::<AudioSource>()
+            .init_resource::<WeaponArsenal>()
+            .init_resource::<GameAssets>()
+            .init_resource::<EntityPools>()
+            .add_systems(Update, (spawn_everything, tag_spawned).chain());
+
+        assert_eq!(app.world.resource::<Assets<TextureAtlasLayout>>().len(), 3);
+
+        // Checked every frame, a layout per spawn would only be freed once its sprite is gone
+        for _ in 0..2_000 {
+            app.update();
+
+            assert_eq!(app.world.resource::<Assets<TextureAtlasLayout>>().len(), 3);
+        }
+
+        // Every sprite on screen points at a texture loaded up front
+        let game_assets = app.world.resource::<GameAssets>();
+        let textures: Vec<AssetId<Image>> = game_assets.textures().map(|texture| texture.id()).collect();
+
+        let mut sprites = app.world.query::<&Handle<Image>>();
+
+        assert!(sprites.iter(&app.world).count() > 0);
+        assert!(sprites.iter(&app.world).all(|texture| textures.contains(&texture.id())));
+    }
+}
@@ -0,0 +1,318 @@
+use bevy::prelude::*;
+
+use crate::{
+    boss::BossFight,
+    pickup::ActiveEffects,
+    race::{Fuel, RaceDefinition, RaceRun},
+    weapon::{Weapon, WeaponArsenal},
+    AppState
+};
+
+/// In-game overlay: the boss health bar, the current weapon, pickup countdowns and
+/// the race clock and fuel gauge
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, setup_hud)
+            .add_systems(Update, (
+                update_boss_health_bar,
+                update_weapon_text,
+                update_effects_text,
+                update_race_panel,
+            ).run_if(in_state(AppState::InGame)));
+    }
+}
+
+#[derive(Component)]
+pub struct HudRootNode;
+
+#[derive(Component)]
+struct BossHealthBar;
+
+#[derive(Component)]
+struct BossHealthBarFill;
+
+#[derive(Component)]
+struct BossNameText;
+
+#[derive(Component)]
+struct WeaponText;
+
+#[derive(Component)]
+struct EffectsText;
+
+#[derive(Component)]
+struct RacePanel;
+
+#[derive(Component)]
+struct RaceText;
+
+#[derive(Component)]
+struct FuelGaugeFill;
+
+fn setup_hud(mut commands: Commands) {
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                padding: UiRect::top(Val::Px(8.0)),
+                ..default()
+            },
+            ..default()
+        },
+        HudRootNode
+    )).with_children(|parent| {
+
+        // Boss health bar, hidden until a boss shows up
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(70.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    display: Display::None,
+                    ..default()
+                },
+                ..default()
+            },
+            BossHealthBar
+        )).with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 14.0,
+                        color: Color::WHITE,
+                        ..default()
+                    }
+                ),
+                BossNameText
+            ));
+
+            parent.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Px(8.0),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+                border_color: Color::WHITE.into(),
+                ..default()
+            }).with_children(|parent| {
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Percent(100.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        background_color: Color::rgb(0.85, 0.2, 0.3).into(),
+                        ..default()
+                    },
+                    BossHealthBarFill
+                ));
+            });
+        });
+
+        parent.spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font_size: 14.0,
+                    color: Color::WHITE,
+                    ..default()
+                }
+            ).with_style(Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(8.0),
+                bottom: Val::Px(8.0),
+                ..default()
+            }),
+            WeaponText
+        ));
+
+        parent.spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font_size: 14.0,
+                    color: Color::WHITE,
+                    ..default()
+                }
+            ).with_text_justify(JustifyText::Right)
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(8.0),
+                bottom: Val::Px(8.0),
+                ..default()
+            }),
+            EffectsText
+        ));
+
+        // Race clock and fuel, hidden outside of race mode
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(8.0),
+                    top: Val::Px(8.0),
+                    width: Val::Px(110.0),
+                    flex_direction: FlexDirection::Column,
+                    display: Display::None,
+                    ..default()
+                },
+                ..default()
+            },
+            RacePanel
+        )).with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 14.0,
+                        color: Color::WHITE,
+                        ..default()
+                    }
+                ),
+                RaceText
+            ));
+
+            parent.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Px(6.0),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+                border_color: Color::WHITE.into(),
+                ..default()
+            }).with_children(|parent| {
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Percent(100.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        background_color: Color::rgb(0.9, 0.7, 0.2).into(),
+                        ..default()
+                    },
+                    FuelGaugeFill
+                ));
+            });
+        });
+    });
+}
+
+#[allow(clippy::type_complexity)]
+fn update_boss_health_bar(
+    boss_fight: Res<BossFight>,
+    mut bar_query: Query<&mut Style, (With<BossHealthBar>, Without<BossHealthBarFill>)>,
+    mut fill_query: Query<&mut Style, (With<BossHealthBarFill>, Without<BossHealthBar>)>,
+    mut text_query: Query<&mut Text, With<BossNameText>>,
+) {
+
+    if !boss_fight.is_changed() { return; }
+
+    let display = if boss_fight.boss.is_some() { Display::Flex } else { Display::None };
+
+    for mut style in bar_query.iter_mut() {
+        if style.display != display {
+            style.display = display;
+        }
+    }
+
+    let fraction = if boss_fight.max_health > 0 {
+        boss_fight.health as f32 / boss_fight.max_health as f32
+    } else { 0.0 };
+
+    for mut style in fill_query.iter_mut() {
+        style.width = Val::Percent(fraction * 100.0);
+    }
+
+    for mut text in text_query.iter_mut() {
+        if text.sections[0].value != boss_fight.name {
+            text.sections[0].value.clone_from(&boss_fight.name);
+        }
+    }
+}
+
+fn update_weapon_text(
+    arsenal: Res<WeaponArsenal>,
+    weapon_query: Query<&Weapon, Changed<Weapon>>,
+    mut text_query: Query<&mut Text, With<WeaponText>>,
+) {
+
+    let Ok(weapon) = weapon_query.get_single() else { return; };
+    let Some(definition) = arsenal.get(weapon.current) else { return; };
+
+    for mut text in text_query.iter_mut() {
+        if text.sections[0].value != definition.name {
+            text.sections[0].value.clone_from(&definition.name);
+        }
+    }
+}
+
+/// One line per running pickup effect, counting down in tenths of a second
+fn update_effects_text(
+    effects: Res<ActiveEffects>,
+    mut text_query: Query<&mut Text, With<EffectsText>>,
+) {
+
+    let countdowns = effects.remaining()
+        .map(|(kind, seconds)| format!("{} {:.1}", kind.name(), seconds))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    for mut text in text_query.iter_mut() {
+        if text.sections[0].value != countdowns {
+            text.sections[0].value.clone_from(&countdowns);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_race_panel(
+    race: Option<Res<RaceRun>>,
+    fuel: Option<Res<Fuel>>,
+    definitions: Res<Assets<RaceDefinition>>,
+    mut panel_query: Query<&mut Style, (With<RacePanel>, Without<FuelGaugeFill>)>,
+    mut fill_query: Query<&mut Style, (With<FuelGaugeFill>, Without<RacePanel>)>,
+    mut text_query: Query<&mut Text, With<RaceText>>,
+) {
+
+    let (Some(race), Some(fuel)) = (race, fuel) else { return; };
+    let Some(definition) = definitions.get(&race.definition) else { return; };
+
+    for mut style in panel_query.iter_mut() {
+        if style.display != Display::Flex {
+            style.display = Display::Flex;
+        }
+    }
+
+    for mut style in fill_query.iter_mut() {
+        let width = Val::Percent(fuel.fraction() * 100.0);
+
+        if style.width != width {
+            style.width = width;
+        }
+    }
+
+    let to_go = (definition.finish_distance - race.progress.distance).max(0.0);
+    let status = format!("Time {:.1}\n{:.0} to go", race.progress.time_left, to_go);
+
+    for mut text in text_query.iter_mut() {
+        if text.sections[0].value != status {
+            text.sections[0].value.clone_from(&status);
+        }
+    }
+}
@@ -0,0 +1,1229 @@
+pub mod ship;
+mod asteroid;
+mod constants;
+mod wall;
+mod ui_plugin;
+mod ui_scaffold;
+mod touch_controls;
+mod waves;
+mod enemy;
+mod boss;
+mod hud;
+pub mod weapon;
+mod pickup;
+mod shield;
+mod race;
+mod scroll;
+mod parallax;
+mod particles;
+mod animation;
+pub mod game_assets;
+mod loading;
+pub mod pool;
+mod debug;
+mod console;
+mod telemetry;
+mod stats;
+#[cfg(feature = "bench")]
+pub mod bench;
+
+use ui_scaffold::UiScaffoldPlugin;
+use touch_controls::{TouchControlsPlugin, VirtualControls, steer_towards};
+use waves::{WavePlugin, run_wave_director};
+use enemy::{EnemyPlugin, EnemyShip, EnemyProjectile, EnemyDestroyed};
+use boss::{BossPlugin, BossPart, BossDefeated};
+use hud::HudPlugin;
+use weapon::{WeaponPlugin, WeaponArsenal, Weapon, fire_weapon};
+use pickup::{PickupPlugin, Pickup, ActiveEffects};
+use shield::ShieldPlugin;
+use race::{RacePlugin, Fuel};
+use scroll::{ScrollSpeed, update_scroll_speed};
+use parallax::ParallaxPlugin;
+use particles::ParticlePlugin;
+use game_assets::{GameAssetsPlugin, GameAssets};
+use loading::LoadingPlugin;
+use debug::DebugOverlayPlugin;
+use console::ConsolePlugin;
+use telemetry::{TelemetryPlugin, category};
+use stats::{StatsPlugin, RunStats};
+use pool::{PoolPlugin, EntityPools, PoolKind, recycle};
+use animation::{AnimationPlugin, Animator, ReturnToClip, PlayClip, play_animations, remove_component_on_finish};
+
+use std::collections::HashMap;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use ship::*;
+use wall::*;
+use asteroid::*;
+use constants::*;
+
+use bevy::{
+    math::*, 
+    prelude::*, 
+    window::WindowResolution 
+};
+
+use bevy_screen_diagnostics::{ScreenDiagnosticsPlugin, ScreenFrameDiagnosticsPlugin, ScreenEntityDiagnosticsPlugin};
+
+#[derive(Debug, Resource, Clone, Eq, PartialEq, Hash, Default, States)]
+enum AppState {
+    #[default]
+    StartMenu,
+    // Behind the loading screen, see loading::track_loading
+    Loading,
+    GameOverMenu,
+    InGame,
+    Paused,
+}
+
+#[derive(Component)]
+struct GameCamera;
+
+#[derive(Resource, PartialEq)]
+#[allow(dead_code)]
+enum GameDifficulty {
+    Easy,
+    Medium,
+    Hard
+}
+
+#[derive(Resource, PartialEq, Debug)]
+enum GameMode {
+    Endless,
+    Race
+}
+
+impl GameMode {
+    fn from_args() -> GameMode {
+        if std::env::args().any(|arg| arg == "--race") { GameMode::Race } else { GameMode::Endless }
+    }
+}
+
+/// Gameplay randomness: where asteroids spawn and how they drift, waves and drops.
+/// Seeded with `--seed=<n>` or the console's `seed` command so a run can be replayed.
+#[derive(Resource)]
+struct GameRng(StdRng);
+
+impl GameRng {
+    fn from_args() -> GameRng {
+        let seed = std::env::args()
+            .find_map(|arg| arg.strip_prefix("--seed=").and_then(|seed| seed.parse().ok()));
+
+        match seed {
+            Some(seed) => GameRng::seeded(seed),
+            None => GameRng(StdRng::from_entropy()),
+        }
+    }
+
+    fn seeded(seed: u64) -> GameRng {
+        GameRng(StdRng::seed_from_u64(seed))
+    }
+}
+
+// How far the camera has scrolled
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+struct DistanceTravelled(f32);
+
+#[derive(Component)]
+struct KinematicObject;
+
+
+#[derive(Component, Deref, DerefMut)]
+struct Velocity(Vec2);
+
+// Radians per second, counter clockwise
+#[derive(Component, Deref, DerefMut)]
+struct AngularVelocity(f32);
+
+#[derive(Component)]
+struct MovementMagnitude {
+    x: f32,
+    y: f32,
+}
+
+enum ColliderShape {
+    Circle,
+    Rectangle,
+    OrientedRectangle
+}
+
+// enum SoundVariants {
+//     ShipExplosion,
+//     DamageToShip,
+//     RocketExplosion,
+//     // BackgroundMusic
+// }
+
+// #[derive(Event)]
+enum ExplosionAnimations {
+    ShipExplosion,
+    AsteroidExplosion,
+    DamageToShip,
+    EnemyShipExplosion
+}
+
+#[derive(Event)]
+struct ExplosionEvent {
+    explosion_type: ExplosionAnimations,
+    entity: Entity
+}
+
+#[derive(Component, PartialEq, Clone, Debug)]
+enum CollidableComponentNames {
+    Ship,
+    Rocket,
+    Asteroid,
+    EnemyShip,
+    EnemyProjectile,
+    BossPart,
+    Pickup
+}
+
+#[derive(Component)]
+#[allow(dead_code)]
+struct Collider {
+    name: CollidableComponentNames,
+    shape: ColliderShape
+}
+
+
+
+#[derive(Resource, Debug, Deref, DerefMut)]
+struct Grid {
+    #[deref]
+    cells: Vec<Vec<Vec<(Entity, CollidableComponentNames, Transform)>>>,
+    grid_size: f32,
+}
+
+
+impl Grid {
+    /// One empty cell per `GRID_SIZE` square of the window
+    fn new() -> Grid {
+        Grid {
+            cells: vec![vec![Vec::new(); (WINDOW_HEIGHT / GRID_SIZE) as usize]; (WINDOW_WIDTH / GRID_SIZE) as usize],
+            grid_size: GRID_SIZE,
+        }
+    }
+
+    /// The cell holding `translation`, if it is on screen
+    fn cell_of(&self, translation: Vec3, camera_translation: Vec3) -> Option<(usize, usize)> {
+        let relative_position = translation - camera_translation;
+
+        let formatted_position_x = relative_position.x + (WINDOW_WIDTH / 2.0);
+        let formatted_position_y = relative_position.y + (WINDOW_HEIGHT / 2.0);
+
+        if formatted_position_x < 0.0 || formatted_position_y < 0.0 {
+            return None;
+        }
+
+        let grid_x = ( formatted_position_x / self.grid_size).floor() as usize;
+        let grid_y = ( formatted_position_y / self.grid_size).floor() as usize;
+
+        // x segments of grid array has a length of 9. Thus, grid_x must be between 0 and 8 (max)
+        // y segments of grid array has a length of 15. Thus, grid_y must be between 0 and 14 (max)
+        if grid_x >= self.cells.len() || grid_y >= self.cells[grid_x].len() {
+            return None;
+        }
+
+        Some((grid_x, grid_y))
+    }
+}
+
+#[derive(Resource, Debug, Deref, DerefMut)]
+struct ScoreCounter(u64);
+
+#[derive(Resource, Debug)]
+struct ScoreTracker {
+    score_count: ScoreCounter,
+    timer: Timer
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+struct MyGameSet;
+
+
+#[derive(Event)]
+struct AsteroidDestroyed {
+    asteroid_type: AsteroidType,
+    position: Vec3
+}
+
+/// Builds and runs the game, see `main.rs`
+pub fn run() {
+
+    // Window 
+    let primary_window = Window {
+        title: "Space Shooter".to_string(),
+        resolution: WindowResolution::new(WINDOW_WIDTH, WINDOW_HEIGHT),
+        present_mode: bevy::window::PresentMode::AutoVsync,
+        resizable: false,
+        ..default()
+    };
+
+    let grid = Grid::new();
+    
+    App::new()
+        .add_plugins(
+            DefaultPlugins.set( 
+            WindowPlugin {
+                primary_window: Some(primary_window),
+                ..default()
+            }).set(ImagePlugin::default_nearest())
+        ).add_plugins((
+            ScreenDiagnosticsPlugin::default(),
+            ScreenFrameDiagnosticsPlugin,
+            ScreenEntityDiagnosticsPlugin
+        ))
+        // .add_plugins(bevy_framepace::FramepacePlugin)
+        .add_plugins(UiScaffoldPlugin)
+        .add_plugins(TouchControlsPlugin)
+        .add_plugins(WavePlugin)
+        .add_plugins(EnemyPlugin)
+        .add_plugins(BossPlugin)
+        .add_plugins(HudPlugin)
+        .add_plugins(WeaponPlugin)
+        .add_plugins(PickupPlugin)
+        .add_plugins(ShieldPlugin)
+        .add_plugins(RacePlugin)
+        .add_plugins(ParallaxPlugin)
+        .add_plugins(ParticlePlugin)
+        .add_plugins(AnimationPlugin)
+        .add_plugins(GameAssetsPlugin)
+        .add_plugins(LoadingPlugin)
+        .add_plugins(PoolPlugin)
+        .add_plugins(DebugOverlayPlugin)
+        .add_plugins(ConsolePlugin)
+        .add_plugins(TelemetryPlugin)
+        .add_plugins(StatsPlugin)
+        .insert_state(AppState::Loading)
+        .configure_sets(Update, (
+            MyGameSet.run_if(in_state(AppState::InGame)),
+        ))
+        .configure_sets(FixedUpdate, (
+            MyGameSet.run_if(in_state(AppState::InGame)),
+        ))
+        .configure_sets(PostUpdate, (
+            MyGameSet.run_if(in_state(AppState::InGame)),
+        ))
+        .insert_resource(ScoreTracker {
+            score_count: ScoreCounter(0),
+            timer: Timer::from_seconds(0.5, TimerMode::Repeating),
+        })
+        .insert_resource(grid)
+        .insert_resource(AppState::InGame)
+        .insert_resource(GameDifficulty::Hard)
+        .insert_resource(GameMode::from_args())
+        .insert_resource(GameRng::from_args())
+        .init_resource::<DistanceTravelled>()
+        .init_resource::<ScrollSpeed>()
+        .add_event::<ExplosionEvent>()
+        .add_event::<AsteroidDestroyed>()
+        .add_systems(Startup, setup)
+        .add_systems(PreUpdate, (
+            bevy::window::close_on_esc,
+        ))
+        .add_systems(Update, 
+
+            (
+                check_if_firing,
+                update_active_rockets.after(check_if_firing),
+                update_grid,
+                collision_checks.after(update_grid),
+                asteroid_collisions.after(update_grid),
+            ).in_set(MyGameSet)
+
+        )
+        .add_systems(FixedPreUpdate, 
+            apply_state_transition::<AppState>.before(MyGameSet)
+        )
+        .add_systems(FixedUpdate, (
+            (
+                run_wave_director,
+                move_asteroids,
+                asteroid_manager,
+                update_scroll_speed,
+                ship_movement,
+                update_kinematic_objects
+            ).chain()
+        ).in_set(MyGameSet))
+        .add_systems(PostUpdate, (  
+            explosion_event_listener,          
+            remove_component_on_finish::<Invulnerable>.after(play_animations),
+            end_invulnerability.after(remove_component_on_finish::<Invulnerable>),
+            update_ship_clip.after(end_invulnerability),
+            asteroid_destroyed, 
+            enemy_destroyed,
+            boss_defeated,
+            update_score.after(asteroid_destroyed).after(enemy_destroyed).after(boss_defeated),
+        ).in_set(MyGameSet))
+        .run();
+}
+
+fn setup(
+    mut commands: Commands, 
+    game_assets: Res<GameAssets>,
+    mut pools: ResMut<EntityPools>,
+    mut rng: ResMut<GameRng>,
+) {
+
+    let camera_bundle = Camera2dBundle::default();
+
+    let camera_transform = camera_bundle.transform;
+
+    // Camera setup
+    commands
+        .spawn(camera_bundle)
+        .insert(
+            (
+            GameCamera, 
+            KinematicObject)
+        );
+
+    // Spawn Ship
+    commands.spawn(
+        ShipBundle::new(&game_assets)
+    );
+
+    // Spawn Walls
+    commands.spawn(WallBundle::new(GameWall::Top)).insert(KinematicObject);
+    commands.spawn(WallBundle::new(GameWall::Bottom)).insert(KinematicObject);
+    commands.spawn(WallBundle::new(GameWall::Right)).insert(KinematicObject);
+    commands.spawn(WallBundle::new(GameWall::Left)).insert(KinematicObject);
+
+
+    let asteroid_bundle = AsteroidBundle::new(
+        &game_assets,
+        &camera_transform,
+        None,
+        AsteroidType::Medium,
+        &mut rng.0
+    );
+
+    pools.spawn(&mut commands, PoolKind::Asteroid, asteroid_bundle);
+
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_grid(
+    mut grid: ResMut<Grid>,
+    collidable_query: Query<(Entity, &Transform, &Collider), (With<Collider>, Without<KinematicObject>)>,
+    camera_query: Query<&Transform, With<GameCamera>>,
+) {
+
+    for cell in grid.cells.iter_mut().flatten() {
+        cell.clear();
+    }
+
+    let camera_transform = camera_query.single();
+    let camera_translation = camera_transform.translation;
+
+    for (entity, transform, collider_info) in collidable_query.iter() {
+
+        let Some((grid_x, grid_y)) = grid.cell_of(transform.translation, camera_translation) else {
+            continue;
+        };
+
+        grid.cells[grid_x][grid_y].push((entity, collider_info.name.clone(), *transform));
+    }
+
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn collision_checks(
+    mut res_grid: ResMut<Grid>,
+    mut ship_query: Query<&mut Ship>, 
+    mut collidable_query: Query<(Entity, &Transform, Option<&mut Asteroid>, Option<&mut Rocket>, Option<&mut EnemyShip>, Option<&mut EnemyProjectile>, Option<&mut BossPart>, Option<&mut Pickup>), With<Collider>>,
+    camera_query: Query<&Transform, With<GameCamera>>,
+    mut collision_events: EventWriter<ExplosionEvent>,
+    mut run_stats: ResMut<RunStats>,
+) {
+
+    let mut ship = ship_query.single_mut();
+
+    let camera_transform = camera_query.single();
+    let camera_translation = camera_transform.translation;
+
+    let grid = res_grid.as_mut();
+
+    // Damage of every rocket, the grid only knows what kind of collider a neighbor is
+    let rocket_damage: HashMap<Entity, u32> = collidable_query.iter()
+        .filter_map(|(entity, _, _, rocket, ..)| rocket.map(|rocket| (entity, rocket.damage)))
+        .collect();
+
+    for ( cur_entity, cur_transform, mut asteroid, mut rocket, mut enemy_ship, mut enemy_projectile, mut boss_part, mut pickup ) in &mut collidable_query {
+        let Some((grid_x, grid_y)) = grid.cell_of(cur_transform.translation, camera_translation) else {
+            continue;
+        };
+
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let neighbor_x = (grid_x as i32 + dx) as usize;
+                let neighbor_y  = (grid_y as i32 + dy) as usize;
+
+                if neighbor_x > 8 || neighbor_y > 14  { continue; }
+
+                let cur_cell: &Vec<(Entity, CollidableComponentNames, Transform)> = &grid[neighbor_x][neighbor_y];
+
+                process_collision(
+                    cur_cell, &cur_entity, cur_transform, &mut collision_events, &rocket_damage,
+                    &mut asteroid, &mut rocket, &mut enemy_ship, &mut enemy_projectile, &mut boss_part, &mut pickup, &mut ship,
+                    &mut run_stats
+                );
+                
+            }
+        }  
+    }
+}
+
+
+/// Bounces overlapping asteroids off each other. Pairs come from the grid: every
+/// cell is only compared with the cells after it, so each pair is checked once.
+pub(crate) fn asteroid_collisions(
+    grid: Res<Grid>,
+    mut asteroid_query: Query<(&mut Transform, &mut Velocity, &mut Asteroid)>,
+    mut collision_events: EventWriter<ExplosionEvent>,
+) {
+    // Large asteroids are wider than a cell, so look two cells out
+    const REACH: i32 = 2;
+
+    let mut pairs: Vec<(Entity, Entity)> = Vec::new();
+
+    let asteroids_in = |x: i32, y: i32| -> Vec<Entity> {
+        if x < 0 || y < 0 || x as usize >= grid.len() || y as usize >= grid[x as usize].len() {
+            return Vec::new();
+        }
+
+        grid[x as usize][y as usize].iter()
+            .filter(|(_, name, _)| *name == CollidableComponentNames::Asteroid)
+            .map(|(entity, _, _)| *entity)
+            .collect()
+    };
+
+    for x in 0..grid.len() as i32 {
+        for y in 0..grid[x as usize].len() as i32 {
+            let cell_asteroids = asteroids_in(x, y);
+
+            if cell_asteroids.is_empty() { continue; }
+
+            for (i, asteroid) in cell_asteroids.iter().enumerate() {
+                for other in &cell_asteroids[i + 1..] {
+                    pairs.push((*asteroid, *other));
+                }
+            }
+
+            for dx in 0..=REACH {
+                for dy in -REACH..=REACH {
+                    if dx == 0 && dy <= 0 { continue; }
+
+                    let neighbor_asteroids = asteroids_in(x + dx, y + dy);
+
+                    for asteroid in &cell_asteroids {
+                        for other in &neighbor_asteroids {
+                            pairs.push((*asteroid, *other));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    for (entity_a, entity_b) in pairs {
+        let Ok([
+            (mut transform_a, mut velocity_a, mut asteroid_a),
+            (mut transform_b, mut velocity_b, mut asteroid_b)
+        ]) = asteroid_query.get_many_mut([entity_a, entity_b]) else { continue; };
+
+        if asteroid_a.exploding || asteroid_b.exploding { continue; }
+
+        if !asteroid_a.check_collision(&transform_a, &transform_b, &CollidableComponentNames::Asteroid) {
+            continue;
+        }
+
+        let impact = resolve_asteroid_collision(
+            AsteroidBody {
+                position: transform_a.translation.truncate(),
+                velocity: **velocity_a,
+                radius: asteroid_a.asteroid_type.radius(),
+                mass: asteroid_a.asteroid_type.mass(),
+            },
+            AsteroidBody {
+                position: transform_b.translation.truncate(),
+                velocity: **velocity_b,
+                radius: asteroid_b.asteroid_type.radius(),
+                mass: asteroid_b.asteroid_type.mass(),
+            }
+        );
+
+        let Some(impact) = impact else { continue; };
+
+        **velocity_a = impact.velocities.0;
+        **velocity_b = impact.velocities.1;
+        transform_a.translation += impact.separations.0.extend(0.0);
+        transform_b.translation += impact.separations.1.extend(0.0);
+
+        if impact.impact_speed < ASTEROID_CHIP_DAMAGE_SPEED { continue; }
+
+        for (entity, asteroid) in [(entity_a, &mut asteroid_a), (entity_b, &mut asteroid_b)] {
+            if asteroid.take_damage(1) {
+                collision_events.send(ExplosionEvent {
+                    explosion_type: ExplosionAnimations::AsteroidExplosion,
+                    entity
+                });
+            }
+        }
+    }
+}
+
+/// Damages the ship and sends the matching explosion, shared by everything that can hurt it.
+/// The shield takes what it can first, whatever hit the ship still goes off.
+fn damage_ship(
+    ship: &mut Ship,
+    ship_entity: Entity,
+    collision_events: &mut EventWriter<ExplosionEvent>,
+) {
+    if ship.god_mode { return; }
+
+    let damage = ship.shield.absorb(1);
+
+    if damage == 0 { return; }
+
+    let destroyed = ship.take_damage(damage);
+
+    let explosion_type = if destroyed {
+        ExplosionAnimations::ShipExplosion
+    } else {
+        ExplosionAnimations::DamageToShip
+    };
+
+    collision_events.send(ExplosionEvent {
+        explosion_type,
+        entity: ship_entity
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn process_collision(
+    cell: &Vec<(Entity, CollidableComponentNames, Transform)>,
+    cur_entity: &Entity,
+    cur_transform: &Transform,
+    collision_events: &mut EventWriter<ExplosionEvent>,
+    rocket_damage: &HashMap<Entity, u32>,
+    asteroid: &mut Option<Mut<'_, Asteroid>>,
+    rocket: &mut Option<Mut<'_, Rocket>>,
+    enemy_ship: &mut Option<Mut<'_, EnemyShip>>,
+    enemy_projectile: &mut Option<Mut<'_, EnemyProjectile>>,
+    boss_part: &mut Option<Mut<'_, BossPart>>,
+    pickup: &mut Option<Mut<'_, Pickup>>,
+    ship: &mut Ship,
+    run_stats: &mut RunStats,
+) {
+
+    for (neighbor_entity, neighbor_name, neighbor_transform) in cell {
+
+
+        if let Some(ref mut asteroid) = asteroid {
+
+            // Asteroid pairs are resolved in asteroid_collisions
+            if *neighbor_name == CollidableComponentNames::Asteroid {
+                continue;
+            }
+
+            if asteroid.exploding { continue; }
+
+            if *neighbor_name == CollidableComponentNames::Ship {
+                let gap = cur_transform.translation.truncate().distance(neighbor_transform.translation.truncate())
+                    - asteroid.asteroid_type.radius()
+                    - SHIP_RADIUS;
+
+                run_stats.note_gap(gap);
+            }
+
+            let collided: bool = asteroid.check_collision(
+                cur_transform, neighbor_transform, neighbor_name
+            );
+
+            if !collided { continue; }
+
+            match *neighbor_name {
+                CollidableComponentNames::Rocket => {
+                    if asteroid.last_hit_by == Some(*neighbor_entity) { continue; }
+
+                    asteroid.last_hit_by = Some(*neighbor_entity);
+
+                    let destroyed = asteroid.take_damage(rocket_damage.get(neighbor_entity).copied().unwrap_or(1));
+
+                    debug!(
+                        target: category::COLLISION,
+                        asteroid = ?cur_entity, rocket = ?neighbor_entity, hit_points = asteroid.hit_points,
+                        "asteroid hit by a rocket"
+                    );
+
+                    if !destroyed { continue; }
+
+                    collision_events.send(
+                        ExplosionEvent {
+                            explosion_type: ExplosionAnimations::AsteroidExplosion,
+                            entity: *cur_entity
+                        }
+                    );
+                },
+                CollidableComponentNames::Ship => {
+                    if ship.ignores_hits() { continue; };
+
+                    debug!(target: category::COLLISION, asteroid = ?cur_entity, "asteroid hit the ship");
+
+                    collision_events.send(ExplosionEvent {
+                        explosion_type: ExplosionAnimations::AsteroidExplosion,
+                        entity: *cur_entity
+                    });
+
+                    damage_ship(ship, *neighbor_entity, collision_events);
+                },
+                _ => {}
+            }                           
+        }
+
+        if let Some(ref mut enemy_ship) = enemy_ship {
+
+            if enemy_ship.exploding { continue; }
+
+            if !enemy_ship.check_collision(cur_transform, neighbor_transform, neighbor_name) { continue; }
+
+            match *neighbor_name {
+                CollidableComponentNames::Rocket => {
+                    if enemy_ship.last_hit_by == Some(*neighbor_entity) { continue; }
+
+                    enemy_ship.last_hit_by = Some(*neighbor_entity);
+
+                    if !enemy_ship.take_damage(rocket_damage.get(neighbor_entity).copied().unwrap_or(1)) { continue; }
+
+                    collision_events.send(ExplosionEvent {
+                        explosion_type: ExplosionAnimations::EnemyShipExplosion,
+                        entity: *cur_entity
+                    });
+                },
+                CollidableComponentNames::Ship => {
+                    if ship.ignores_hits() { continue; }
+
+                    // Ramming the player takes the enemy out too
+                    let hit_points = enemy_ship.hit_points;
+                    enemy_ship.take_damage(hit_points);
+
+                    collision_events.send(ExplosionEvent {
+                        explosion_type: ExplosionAnimations::EnemyShipExplosion,
+                        entity: *cur_entity
+                    });
+
+                    damage_ship(ship, *neighbor_entity, collision_events);
+                },
+                _ => {}
+            }
+        }
+
+        if let Some(ref mut enemy_projectile) = enemy_projectile {
+
+            if enemy_projectile.hit_target || ship.ignores_hits() { continue; }
+
+            if enemy_projectile.check_collision(cur_transform, neighbor_transform, neighbor_name) {
+                enemy_projectile.hit_target = true;
+
+                damage_ship(ship, *neighbor_entity, collision_events);
+            }
+        }
+
+        if let Some(ref mut boss_part) = boss_part {
+
+            if boss_part.hit_points == 0 { continue; }
+
+            if !boss_part.check_collision(cur_transform, neighbor_transform, neighbor_name) { continue; }
+
+            match *neighbor_name {
+                CollidableComponentNames::Rocket => {
+                    if boss_part.last_hit_by == Some(*neighbor_entity) { continue; }
+
+                    boss_part.last_hit_by = Some(*neighbor_entity);
+
+                    // Health, phases and the end of the fight are handled in boss::update_boss_health
+                    boss_part.take_damage(rocket_damage.get(neighbor_entity).copied().unwrap_or(1));
+                },
+                CollidableComponentNames::Ship => {
+                    if ship.ignores_hits() { continue; }
+
+                    damage_ship(ship, *neighbor_entity, collision_events);
+                },
+                _ => {}
+            }
+        }
+
+        // Effects are applied in pickup::collect_pickups
+        if let Some(ref mut pickup) = pickup {
+
+            if pickup.collected || ship.is_destroyed() { continue; }
+
+            if pickup.check_collision(cur_transform, neighbor_transform, neighbor_name) {
+                pickup.collected = true;
+            }
+        }
+
+        // Means current entity is a Rocket
+        if let Some(ref mut rocket) = rocket {
+
+            // The rocket will only ever hit an asteroid, an enemy ship or a boss
+            if matches!(
+                *neighbor_name,
+                CollidableComponentNames::Asteroid | CollidableComponentNames::EnemyShip | CollidableComponentNames::BossPart
+            ) {
+
+                let collided = rocket.check_collision(
+                    cur_transform,
+                    neighbor_transform,
+                    neighbor_name
+                );
+
+                if collided && !rocket.scored_hit {
+                    rocket.scored_hit = true;
+                    run_stats.rocket_hits += 1;
+                }
+
+               if collided && !rocket.pierce {
+                    trace!(target: category::COLLISION, rocket = ?cur_entity, hit = ?neighbor_name, "rocket spent");
+                    rocket.hit_target = true;
+               } 
+            };
+        }
+    }
+
+
+}
+
+pub(crate) fn move_asteroids(
+    time: Res<Time<Fixed>>,
+    mut asteroid_query: Query<(&mut Transform, &mut Velocity, &AngularVelocity, &Asteroid)>,
+) {
+    for (mut transform, mut velocity, angular_velocity, asteroid) in asteroid_query.iter_mut() {
+        if asteroid.exploding { continue; }
+
+        if asteroid.wall_behavior == WallBehavior::Bounce {
+            **velocity = bounce_off_side_walls(transform.translation.x, asteroid.asteroid_type.radius(), **velocity);
+        }
+
+        transform.translation += velocity.extend(0.0) * time.delta_seconds();
+        transform.rotate_z(**angular_velocity * time.delta_seconds());
+    }
+}
+
+pub(crate) fn update_kinematic_objects(
+    time: Res<Time<Fixed>>, 
+    mut query: Query<&mut Transform, With<KinematicObject>>, 
+    scroll_speed: Res<ScrollSpeed>,
+    mut distance: ResMut<DistanceTravelled>,
+) {
+    let scroll = scroll_speed.current * time.delta_seconds();
+
+    for mut transform in query.iter_mut() {
+        transform.translation.y += scroll;
+    }
+
+    **distance += scroll;
+}
+
+pub(crate) fn asteroid_manager(
+    mut commands: Commands,
+    mut asteroid_query: Query<(Entity, &Transform, &Asteroid), With<Asteroid>>,
+    camera_query: Query<&Transform, (With<GameCamera>, Without<Asteroid>)>
+) {
+
+    
+
+    
+
+    let camera_transform = camera_query.single();
+
+    for (entity, transform, asteroid) in asteroid_query.iter_mut() {
+
+        if asteroid.exploding {
+            continue;
+        }
+
+        let is_outside_window: bool = asteroid.is_outside_window(transform, camera_transform);
+
+        if is_outside_window { 
+            // let new_translation = asteroid.reset(camera_transform);
+            // transform.translation = new_translation.extend(0.0);
+
+            commands.entity(entity).add(recycle);
+        } 
+    }
+}
+
+pub(crate) fn update_active_rockets(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut rocket_query: Query<(Entity, &mut Transform, &Rocket, &Velocity), With<Rocket>>,
+    camera_query: Query<&Transform, (With<GameCamera>, Without<Rocket>)>,
+) {
+
+    let camera_transform = camera_query.single();
+
+    for (entity, mut rocket_transform, rocket, velocity) in rocket_query.iter_mut() {
+
+        if rocket.hit_target {
+            commands.entity(entity).add(recycle);
+            continue; 
+        }
+
+        if rocket.is_outside_window(&rocket_transform, camera_transform) {
+            commands.entity(entity).add(recycle);
+        } else {
+            rocket_transform.translation += velocity.extend(0.0) * time.delta_seconds();
+        }
+    }
+}
+
+
+fn get_y_bounds(camera_query: &Query<&Transform, (With<GameCamera>, Without<Ship>)> ) -> (f32, f32) {
+    let camera_transform = *camera_query.single();
+
+    let upper_bound: f32 = TOP_WALL - (SHIP_SPEC.y / 2.0) + camera_transform.translation.y;
+    let lower_bound: f32 = BOTTOM_WALL + (SHIP_SPEC.y / 2.0) + camera_transform.translation.y;
+
+    // let upper_bound: f32 = TOP_WALL - (WALL_THICKNESS / 2.0) - (SHIP_SPEC.y / 2.0) + camera_transform.translation.y;
+    // let lower_bound: f32 = BOTTOM_WALL + (WALL_THICKNESS / 2.0) + (SHIP_SPEC.y / 2.0) + camera_transform.translation.y;
+
+    (upper_bound.round(), lower_bound.round())
+}   
+
+#[allow(clippy::too_many_arguments)]
+fn ship_movement(
+    keyboard_input: Res<ButtonInput<KeyCode>>, 
+    virtual_controls: Res<VirtualControls>,
+    scroll_speed: Res<ScrollSpeed>,
+    fuel: Option<ResMut<Fuel>>,
+    timestep: Res<Time<Fixed>>, 
+    mut ship_query: Query<(&mut Transform, &mut Ship), With<Ship>>,
+    camera_query: Query<&Transform, (With<GameCamera>, Without<Ship>)>,
+) {
+
+    let ( upper_bound, lower_bound ) = get_y_bounds(&camera_query);
+
+    let left_bound: f32 = LEFT_WALL + (SHIP_SPEC.x / 2.0);
+    let right_bound: f32 = RIGHT_WALL - (SHIP_SPEC.x / 2.0);
+
+    let (mut transform, mut ship) = ship_query.single_mut();
+
+    if ship.is_destroyed() { return }
+
+    // Player input, from the keyboard and the touch/mouse controls
+    let mut input = Vec2::ZERO;
+
+    if keyboard_input.pressed(KeyCode::KeyW) || keyboard_input.pressed(KeyCode::ArrowUp) {
+        input.y += MAGNITUDE_FORCE;
+    }
+
+    if keyboard_input.pressed(KeyCode::KeyS) || keyboard_input.pressed(KeyCode::ArrowDown) {
+        input.y -= MAGNITUDE_FORCE;
+    }
+
+    if keyboard_input.pressed(KeyCode::KeyA) || keyboard_input.pressed(KeyCode::ArrowLeft) {
+        input.x -= MAGNITUDE_FORCE;
+    }
+
+    if keyboard_input.pressed(KeyCode::KeyD) || keyboard_input.pressed(KeyCode::ArrowRight) {
+        input.x += MAGNITUDE_FORCE;
+    }
+
+    // Touch joystick and drag-to-steer (mouse or touch)
+    input += virtual_controls.direction * MAGNITUDE_FORCE;
+
+    if let Some(steer_target) = virtual_controls.steer_target {
+        let camera_translation = camera_query.single().translation;
+        let ship_offset = (transform.translation - camera_translation).truncate();
+
+        input += steer_towards(ship_offset, steer_target) * MAGNITUDE_FORCE;
+    }
+
+    // Combining sources must never move the ship faster than the keyboard alone
+    let mut input = input.clamp(Vec2::splat(-MAGNITUDE_FORCE), Vec2::splat(MAGNITUDE_FORCE));
+
+    // In a race thrust burns fuel, with an empty tank the ship only drifts along
+    if let Some(mut fuel) = fuel {
+        if fuel.is_empty() {
+            input = Vec2::ZERO;
+        } else {
+            fuel.burn((input.length() / MAGNITUDE_FORCE).min(1.0), timestep.delta_seconds());
+        }
+    }
+
+    // Drives the engine exhaust
+    ship.thrust = (input.length() / MAGNITUDE_FORCE).min(1.0);
+
+    // Keep pace with the camera, which the same input speeds up or slows down
+    let cruise = scroll_speed.current / SHIP_SPEED;
+
+    let magnitude = MovementMagnitude {
+        x: input.x,
+        y: cruise + input.y
+    };
+
+    let new_vel_x: f32 = magnitude.x * SHIP_SPEED * timestep.delta_seconds();
+    let new_ship_position_x = transform.translation.x + new_vel_x;
+
+    let new_vel_y: f32 = magnitude.y * SHIP_SPEED * timestep.delta_seconds();
+    let new_ship_position_y = transform.translation.y + new_vel_y;
+
+    transform.translation.y = new_ship_position_y.clamp(
+        lower_bound, 
+        upper_bound
+    );
+    transform.translation.x = new_ship_position_x.clamp(
+        left_bound, 
+        right_bound
+    );
+
+}
+
+#[allow(clippy::too_many_arguments)]
+fn check_if_firing(
+    keyboard_input: Res<ButtonInput<KeyCode>>, 
+    virtual_controls: Res<VirtualControls>,
+    time: Res<Time>,
+    game_assets: Res<GameAssets>,
+    mut pools: ResMut<EntityPools>,
+    arsenal: Res<WeaponArsenal>,
+    mut commands: Commands,
+    mut ship_query: Query<(&Transform, &Ship, &mut Weapon), With<Ship>>,
+) {
+
+    let (ship_transform, ship_properties, mut weapon) = ship_query.single_mut();
+
+    if ship_properties.is_destroyed() || 
+       ship_properties.invulnerable
+     {
+        return;
+    }
+
+    let Some(definition) = arsenal.get(weapon.current) else { return; };
+
+    let fire_pressed = keyboard_input.pressed(KeyCode::Space) || virtual_controls.fire_held;
+
+    if weapon.trigger(definition, fire_pressed, time.delta_seconds()) {
+        fire_weapon(&mut commands, &game_assets, &mut pools, definition, ship_transform.translation);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn explosion_event_listener(
+    mut commands: Commands,
+    mut collision_events: EventReader<ExplosionEvent>,
+    mut asteroid_explosion: EventWriter<AsteroidDestroyed>,
+    mut ship_query: Query<(Entity, &mut Ship)>,
+    mut asteroid_query: Query<(Entity, &mut Asteroid, &Transform, &Velocity)>,
+    mut enemy_query: Query<(&mut EnemyShip, &mut Handle<Image>, &mut TextureAtlas)>,
+    mut enemy_explosion: EventWriter<EnemyDestroyed>,
+    mut play_clips: EventWriter<PlayClip>,
+    camera_query: Query<&Transform, (With<GameCamera>, Without<Asteroid>)>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    game_assets: Res<GameAssets>,
+    mut pools: ResMut<EntityPools>,
+    mut rng: ResMut<GameRng>,
+)  {
+
+    if !collision_events.is_empty() {
+
+        // Two rockets can hit the same asteroid or enemy within a frame, it should only explode once
+        let mut exploded: Vec<Entity> = Vec::new();
+
+        for ExplosionEvent { explosion_type, entity } in collision_events.read() {
+
+            let (ship_entity, mut ship) = ship_query.single_mut();
+
+            match explosion_type {
+                ExplosionAnimations::ShipExplosion => {
+
+                    next_app_state.set(AppState::GameOverMenu);
+
+                    info!(target: category::GAMEPLAY, "ship destroyed");
+
+                }, 
+                ExplosionAnimations::AsteroidExplosion => {
+                    let exploding_asteroid =  asteroid_query.get_mut(*entity);
+
+                    if let Ok((asteroid_entity, mut asteroid, asteroid_transform, asteroid_velocity)) = exploding_asteroid {
+
+                        if exploded.contains(&asteroid_entity) { continue; }
+
+                        exploded.push(asteroid_entity);
+
+                        debug!(
+                            target: category::GAMEPLAY,
+                            asteroid = ?asteroid_entity, asteroid_type = ?asteroid.asteroid_type,
+                            "asteroid destroyed"
+                        );
+
+                        play_clips.send(PlayClip { entity: asteroid_entity, clip: "asteroid_explode".to_string() });
+
+                        
+                        // Audio now properly works, but only with ogg files (idk why yet)
+                        // Need to better start and stop the audio
+                        // --- 
+                        // let sound: Handle<AudioSource> = asset_server.load("sounds/asteroid_explosion.ogg");
+                        // commands.entity(asteroid_entity).insert((PlayAnimation(AnimatableAsset::Asteroid), AudioBundle {
+                        //     source: sound,
+                        //     settings: PlaybackSettings::ONCE
+                        // }));
+                        // commands.spawn(AudioBundle {
+                        //     source: sound,
+                        //     settings: PlaybackSettings::ONCE
+                        // });
+
+                        asteroid_explosion.send(AsteroidDestroyed {
+                            asteroid_type: asteroid.asteroid_type,
+                            position: asteroid_transform.translation
+                        });
+
+                        asteroid.exploding = true;
+
+                        // Large asteroids break up into smaller ones
+                        let fragments = asteroid.asteroid_type.fragments();
+
+                        if !fragments.is_empty() {
+                            let camera_transform = camera_query.single();
+                            let velocities = fragment_velocities(**asteroid_velocity, fragments);
+
+                            for (fragment, velocity) in fragments.iter().zip(velocities) {
+                                let mut fragment_bundle = AsteroidBundle::new(
+                                    &game_assets,
+                                    camera_transform,
+                                    Some(asteroid_transform.translation + (velocity.normalize_or_zero() * fragment.radius()).extend(0.0)),
+                                    *fragment,
+                                    &mut rng.0
+                                );
+
+                                fragment_bundle.velocity = Velocity(velocity);
+
+                                pools.spawn(&mut commands, PoolKind::Asteroid, fragment_bundle);
+                            }
+                        }
+                    }
+                }
+                
+                ExplosionAnimations::EnemyShipExplosion => {
+                    let Ok((mut enemy_ship, mut texture, mut atlas)) = enemy_query.get_mut(*entity) else { continue; };
+
+                    // Rocket and ram in the same frame
+                    if exploded.contains(entity) { continue; }
+
+                    exploded.push(*entity);
+
+                    // Already set for rocket kills, a bomb takes out enemies that still have hit points
+                    enemy_ship.exploding = true;
+
+                    enemy_explosion.send(EnemyDestroyed {
+                        pattern: enemy_ship.pattern
+                    });
+
+                    // Enemy ships don't have their own explosion frames, borrow the asteroid's
+                    *texture = game_assets.asteroid_texture.clone();
+                    *atlas = TextureAtlas {
+                        layout: game_assets.asteroid_layout.clone(),
+                        index: 0
+                    };
+
+                    play_clips.send(PlayClip { entity: *entity, clip: "asteroid_explode".to_string() });
+                },
+                ExplosionAnimations::DamageToShip => {
+                    
+                    ship.invulnerable = true;
+
+                    // Blinks until the clip finishes, see end_invulnerability
+                    commands.entity(ship_entity).insert(Invulnerable);
+                    play_clips.send(PlayClip { entity: ship_entity, clip: ship.hit_clip().to_string() });
+                }
+            }
+        }
+        
+        collision_events.clear()
+    }
+}
+
+fn end_invulnerability(
+    mut removed: RemovedComponents<Invulnerable>,
+    mut ship_query: Query<&mut Ship>,
+) {
+
+    for entity in removed.read() {
+        if let Ok(mut ship) = ship_query.get_mut(entity) {
+            ship.invulnerable = false;
+        }
+    }
+}
+
+/// Keeps the ship's sprite showing the hull, whether it was hit or repaired
+fn update_ship_clip(
+    mut ship_query: Query<(&Ship, &mut ReturnToClip, &mut Animator), Changed<Ship>>,
+) {
+
+    for (ship, mut return_to_clip, mut animator) in ship_query.iter_mut() {
+        let idle_clip = ship.idle_clip();
+
+        if return_to_clip.0 != idle_clip {
+            return_to_clip.0 = idle_clip.to_string();
+        }
+
+        // Mid blink the ship goes back to it once the blink finishes
+        if !ship.invulnerable && animator.clip() != idle_clip {
+            animator.play(idle_clip);
+        }
+    }
+}
+
+fn update_score(
+    time: Res<Time>,
+    effects: Res<ActiveEffects>,
+    scroll_speed: Res<ScrollSpeed>,
+    mut score_tracker: ResMut<ScoreTracker>,
+) {
+
+    // Points for distance come in faster the faster the run goes
+    let ticks = score_tracker.timer.tick(time.delta().mul_f32(scroll_speed.factor())).times_finished_this_tick();
+
+    if ticks > 0 {
+        *score_tracker.score_count += ticks as u64 * effects.score_multiplier();
+        trace!(target: category::SCORE, score = score_tracker.score_count.0, "distance points");
+    }
+}
+
+fn asteroid_destroyed(
+    mut asteroid_explosion: EventReader<AsteroidDestroyed>,
+    effects: Res<ActiveEffects>,
+    mut score_tracker: ResMut<ScoreTracker>,
+) {
+
+    for AsteroidDestroyed { asteroid_type, .. } in asteroid_explosion.read() {
+        *score_tracker.score_count += asteroid_type.score_value() * effects.score_multiplier();
+
+        debug!(target: category::SCORE, ?asteroid_type, score = score_tracker.score_count.0, "points for an asteroid");
+    }
+
+}
+
+fn enemy_destroyed(
+    mut enemy_explosion: EventReader<EnemyDestroyed>,
+    effects: Res<ActiveEffects>,
+    mut score_tracker: ResMut<ScoreTracker>,
+) {
+    for EnemyDestroyed { pattern } in enemy_explosion.read() {
+        *score_tracker.score_count += pattern.score_value() * effects.score_multiplier();
+    }
+}
+
+fn boss_defeated(
+    mut boss_defeats: EventReader<BossDefeated>,
+    effects: Res<ActiveEffects>,
+    mut score_tracker: ResMut<ScoreTracker>,
+) {
+    for BossDefeated { score_value } in boss_defeats.read() {
+        *score_tracker.score_count += score_value * effects.score_multiplier();
+    }
+}
@@ -0,0 +1,436 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadState, UntypedAssetId},
+    prelude::*,
+    utils::BoxedFuture
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    game_assets::GameAssets,
+    AppState
+};
+
+/// The loading screen. Everything in the manifest (`assets/default.manifest.ron`),
+/// `GameAssets` and the data files plugins register with `Preload::track` is loaded with a
+/// progress bar before the start menu comes up. A file that fails to load keeps the game
+/// on the loading screen with its path shown.
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<AssetManifest>()
+            .register_asset_loader(AssetManifestLoader)
+            .init_resource::<Preload>()
+            .add_systems(Startup, load_manifest)
+            .add_systems(OnEnter(AppState::Loading), spawn_loading_screen)
+            .add_systems(OnExit(AppState::Loading), despawn_screen::<LoadingScreen>)
+            .add_systems(Update, track_loading.in_set(LoadingSet).run_if(in_state(AppState::Loading)))
+            .add_systems(OnEnter(AppState::StartMenu), spawn_start_prompt)
+            .add_systems(OnExit(AppState::StartMenu), despawn_screen::<StartPrompt>)
+            .add_systems(Update, start_game.run_if(in_state(AppState::StartMenu)));
+    }
+}
+
+/// Where the loading screen checks progress, anything adding to `Preload` while loading runs before it
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LoadingSet;
+
+pub const DEFAULT_MANIFEST: &str = "default.manifest.ron";
+
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct AssetManifest {
+    pub images: Vec<String>,
+    pub sounds: Vec<String>,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum AssetManifestError {
+    #[error("the manifest is empty")]
+    Empty,
+    #[error("{0} is listed twice")]
+    Duplicate(String),
+}
+
+impl AssetManifest {
+
+    pub fn validate(&self) -> Result<(), AssetManifestError> {
+        if self.images.is_empty() && self.sounds.is_empty() {
+            return Err(AssetManifestError::Empty);
+        }
+
+        let paths: Vec<&String> = self.images.iter().chain(self.sounds.iter()).collect();
+
+        for (index, path) in paths.iter().enumerate() {
+            if paths[..index].contains(path) {
+                return Err(AssetManifestError::Duplicate(path.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadingProgress {
+    Loading { loaded: usize, total: usize },
+    Failed(String),
+    Done,
+}
+
+impl LoadingProgress {
+    /// Progress over `(path, state)` pairs, the first failure wins
+    pub fn from_states<'a>(states: impl IntoIterator<Item = (&'a str, LoadState)>) -> LoadingProgress {
+        let mut loaded = 0;
+        let mut total = 0;
+
+        for (path, state) in states {
+            total += 1;
+
+            match state {
+                LoadState::Loaded => loaded += 1,
+                LoadState::Failed => return LoadingProgress::Failed(path.to_string()),
+                _ => {}
+            }
+        }
+
+        if loaded == total {
+            LoadingProgress::Done
+        } else {
+            LoadingProgress::Loading { loaded, total }
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Preload {
+    pub manifest: Handle<AssetManifest>,
+    // Filled in once the manifest is in
+    pub handles: Vec<(String, UntypedHandle)>,
+    // Data files, by the plugins that load them
+    tracked: Vec<(String, UntypedHandle)>,
+}
+
+impl Preload {
+    /// Holds the start menu back until `handle` has loaded, for data files (wave scripts,
+    /// animations, ...) a plugin loads itself
+    pub fn track<A: Asset>(&mut self, path: impl Into<String>, handle: &Handle<A>) {
+        self.tracked.push((path.into(), handle.clone().untyped()));
+    }
+}
+
+#[derive(Component)]
+struct LoadingScreen;
+
+#[derive(Component)]
+struct LoadingText;
+
+#[derive(Component)]
+struct LoadingBarFill;
+
+#[derive(Component)]
+struct StartPrompt;
+
+fn load_manifest(mut preload: ResMut<Preload>, asset_server: Res<AssetServer>) {
+    preload.manifest = asset_server.load(DEFAULT_MANIFEST);
+}
+
+fn spawn_loading_screen(mut commands: Commands) {
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            background_color: Color::BLACK.into(),
+            // Over the game and the HUD
+            z_index: ZIndex::Global(10),
+            ..default()
+        },
+        LoadingScreen
+    )).with_children(|parent| {
+        parent.spawn((
+            TextBundle::from_section(
+                "Loading",
+                TextStyle {
+                    font_size: 18.0,
+                    color: Color::WHITE,
+                    ..default()
+                }
+            ).with_text_justify(JustifyText::Center),
+            LoadingText
+        ));
+
+        parent.spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(60.0),
+                height: Val::Px(8.0),
+                border: UiRect::all(Val::Px(1.0)),
+                ..default()
+            },
+            background_color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+            border_color: Color::WHITE.into(),
+            ..default()
+        }).with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(0.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: Color::WHITE.into(),
+                    ..default()
+                },
+                LoadingBarFill
+            ));
+        });
+    });
+}
+
+fn track_loading(
+    asset_server: Res<AssetServer>,
+    manifests: Res<Assets<AssetManifest>>,
+    mut game_assets: ResMut<GameAssets>,
+    mut preload: ResMut<Preload>,
+    mut text_query: Query<&mut Text, With<LoadingText>>,
+    mut fill_query: Query<&mut Style, With<LoadingBarFill>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+) {
+
+    if preload.handles.is_empty() {
+        if let Some(manifest) = manifests.get(&preload.manifest) {
+            game_assets.add_images(&asset_server, &manifest.images);
+
+            preload.handles = manifest.sounds.iter()
+                .map(|path| (path.clone(), asset_server.load::<AudioSource>(path).untyped()))
+                .collect();
+
+            // The manifest's images and the textures weapons brought in that it doesn't know about
+            for texture in game_assets.textures() {
+                let id: UntypedAssetId = texture.id().untyped();
+
+                if preload.handles.iter().all(|(_, handle)| handle.id() != id) {
+                    let path = asset_server.get_path(texture.id()).map(|path| path.to_string()).unwrap_or_default();
+
+                    preload.handles.push((path, texture.clone().untyped()));
+                }
+            }
+        }
+    }
+
+    let progress = if preload.handles.is_empty() {
+        // Still waiting on the manifest itself
+        match asset_server.load_state(&preload.manifest) {
+            LoadState::Failed => LoadingProgress::Failed(DEFAULT_MANIFEST.to_string()),
+            _ => LoadingProgress::Loading { loaded: 0, total: 1 },
+        }
+    } else {
+        LoadingProgress::from_states(
+            preload.handles.iter()
+                .chain(preload.tracked.iter())
+                .map(|(path, handle)| (path.as_str(), asset_server.load_state(handle.id())))
+        )
+    };
+
+    let Ok(mut text) = text_query.get_single_mut() else { return; };
+
+    match progress {
+        LoadingProgress::Loading { loaded, total } => {
+            text.sections[0].value = format!("Loading {loaded}/{total}");
+
+            if let Ok(mut fill) = fill_query.get_single_mut() {
+                fill.width = Val::Percent(100.0 * loaded as f32 / total as f32);
+            }
+        },
+        LoadingProgress::Failed(path) => {
+            text.sections[0].value = format!("Could not load {path}");
+            text.sections[0].style.color = Color::rgb(1.0, 0.3, 0.3);
+        },
+        LoadingProgress::Done => {
+            next_app_state.set(AppState::StartMenu);
+        }
+    }
+}
+
+fn spawn_start_prompt(mut commands: Commands) {
+
+    commands.spawn((
+        TextBundle::from_section(
+            "Press Space or tap to start",
+            TextStyle {
+                font_size: 18.0,
+                color: Color::WHITE,
+                ..default()
+            }
+        ).with_style(Style {
+            position_type: PositionType::Absolute,
+            align_self: AlignSelf::Center,
+            justify_self: JustifySelf::Center,
+            top: Val::Percent(45.0),
+            ..default()
+        }),
+        StartPrompt
+    ));
+}
+
+fn start_game(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+) {
+
+    let pressed = keyboard_input.any_just_pressed([KeyCode::Space, KeyCode::Enter])
+        || mouse_input.just_pressed(MouseButton::Left)
+        || touches.any_just_pressed();
+
+    if pressed {
+        next_app_state.set(AppState::InGame);
+    }
+}
+
+fn despawn_screen<T: Component>(mut commands: Commands, screen_query: Query<Entity, With<T>>) {
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[derive(Default)]
+pub struct AssetManifestLoader;
+
+#[derive(Error, Debug)]
+pub enum AssetManifestLoaderError {
+    #[error("could not read asset manifest: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse asset manifest: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("invalid asset manifest: {0}")]
+    Invalid(#[from] AssetManifestError),
+}
+
+impl AssetLoader for AssetManifestLoader {
+    type Asset = AssetManifest;
+    type Settings = ();
+    type Error = AssetManifestLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<AssetManifest, AssetManifestLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            let manifest: AssetManifest = ron::de::from_bytes(&bytes)?;
+            manifest.validate()?;
+
+            Ok(manifest)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["manifest.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        animation::DEFAULT_ANIMATIONS,
+        game_assets::{ASTEROID_TEXTURE, BOSS_TEXTURE, SHIP_TEXTURE},
+        parallax::{ParallaxDefinition, DEFAULT_PARALLAX},
+        pickup::DEFAULT_DROP_TABLES,
+        race::DEFAULT_RACE,
+        waves::DEFAULT_WAVE_SCRIPT,
+        shield::SHIELD_BREAK_SOUND,
+        weapon::WeaponArsenal
+    };
+
+    fn read_asset(path: &str) -> String {
+        std::fs::read_to_string(format!("{}/assets/{}", env!("CARGO_MANIFEST_DIR"), path)).unwrap()
+    }
+
+    #[test]
+    fn manifest_lists_every_asset_the_game_uses() {
+        let manifest: AssetManifest = ron::from_str(&read_asset(DEFAULT_MANIFEST)).unwrap();
+
+        assert_eq!(manifest.validate(), Ok(()));
+
+        for path in manifest.images.iter().chain(manifest.sounds.iter()) {
+            assert!(
+                std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets").join(path).exists(),
+                "{path} is missing"
+            );
+        }
+
+        let parallax: ParallaxDefinition = ron::from_str(&read_asset("background/default.parallax.ron")).unwrap();
+
+        let arsenal = WeaponArsenal::default();
+        let projectiles = arsenal.iter().map(|weapon| weapon.projectile.texture);
+
+        for texture in [SHIP_TEXTURE, ASTEROID_TEXTURE, BOSS_TEXTURE].into_iter().chain(projectiles).chain(parallax.layers.iter().map(|layer| layer.texture.as_str())) {
+            assert!(manifest.images.iter().any(|path| path == texture), "{texture} is not in the manifest");
+        }
+
+        assert!(manifest.sounds.iter().any(|path| path == SHIELD_BREAK_SOUND));
+    }
+
+    #[test]
+    fn tracked_data_files_exist() {
+        for path in [DEFAULT_WAVE_SCRIPT, DEFAULT_ANIMATIONS, DEFAULT_DROP_TABLES, DEFAULT_PARALLAX, DEFAULT_RACE] {
+            assert!(
+                std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets").join(path).exists(),
+                "{path} is missing"
+            );
+        }
+    }
+
+    #[test]
+    fn progress_reports_the_first_failure() {
+        let progress = LoadingProgress::from_states([
+            ("ship.png", LoadState::Loaded),
+            ("rocket.png", LoadState::Loading),
+            ("boom.ogg", LoadState::NotLoaded),
+        ]);
+
+        assert_eq!(progress, LoadingProgress::Loading { loaded: 1, total: 3 });
+
+        let progress = LoadingProgress::from_states([
+            ("ship.png", LoadState::Loaded),
+            ("missing.png", LoadState::Failed),
+            ("also_missing.png", LoadState::Failed),
+        ]);
+
+        assert_eq!(progress, LoadingProgress::Failed("missing.png".to_string()));
+
+        assert_eq!(LoadingProgress::from_states([("ship.png", LoadState::Loaded)]), LoadingProgress::Done);
+    }
+
+    #[test]
+    fn duplicates_are_rejected() {
+        let manifest = AssetManifest {
+            images: vec!["ship.png".to_string(), "rocket.png".to_string()],
+            sounds: vec!["ship.png".to_string()],
+        };
+
+        assert_eq!(manifest.validate(), Err(AssetManifestError::Duplicate("ship.png".to_string())));
+
+        let manifest = AssetManifest { images: Vec::new(), sounds: Vec::new() };
+
+        assert_eq!(manifest.validate(), Err(AssetManifestError::Empty));
+    }
+}
@@ -0,0 +1,317 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    constants::*,
+    game_assets::GameAssets,
+    loading::Preload,
+    telemetry::category,
+    AppState,
+    GameCamera
+};
+
+/// Scrolling background made of layers that each move at their own fraction of the
+/// camera's speed. Every layer has just enough tiles to cover the screen, a tile that
+/// drops out of view is moved back up above the others. Layers are RON files ending in
+/// `.parallax.ron`, see `assets/background/default.parallax.ron`.
+pub struct ParallaxPlugin;
+
+impl Plugin for ParallaxPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<ParallaxDefinition>()
+            .register_asset_loader(ParallaxDefinitionLoader)
+            .add_systems(Startup, load_parallax)
+            .add_systems(FixedUpdate, (
+                // Textures come from the manifest, handed to GameAssets while loading
+                spawn_parallax_layers.run_if(not(in_state(AppState::Loading))),
+                scroll_parallax_layers.after(crate::update_kinematic_objects),
+            ));
+    }
+}
+
+pub const DEFAULT_PARALLAX: &str = "background/default.parallax.ron";
+
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct ParallaxDefinition {
+    /// Back to front
+    pub layers: Vec<ParallaxLayer>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ParallaxLayer {
+    pub texture: String,
+    /// Size of the texture, known up front so tiles can be laid out before it loads
+    pub tile_size: (f32, f32),
+    /// How much of the camera's scrolling the layer shows. 0 is painted on the screen,
+    /// 1 moves with the world
+    pub speed: f32,
+    pub z: f32,
+    #[serde(default)]
+    pub mirrored: bool,
+}
+
+impl ParallaxLayer {
+    /// Enough tiles to always cover the window while one is being recycled
+    pub fn tile_count(&self) -> usize {
+        (WINDOW_HEIGHT / self.tile_size.1).ceil() as usize + 1
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ParallaxDefinitionError {
+    #[error("there are no layers")]
+    NoLayers,
+    #[error("layer {0} needs a positive tile size")]
+    InvalidTileSize(usize),
+    #[error("layer {0} speed must be between 0 and 1")]
+    InvalidSpeed(usize),
+}
+
+impl ParallaxDefinition {
+
+    pub fn validate(&self) -> Result<(), ParallaxDefinitionError> {
+        if self.layers.is_empty() {
+            return Err(ParallaxDefinitionError::NoLayers);
+        }
+
+        for (index, layer) in self.layers.iter().enumerate() {
+            if layer.tile_size.0 <= 0.0 || layer.tile_size.1 <= 0.0 {
+                return Err(ParallaxDefinitionError::InvalidTileSize(index));
+            }
+
+            if !(0.0..=1.0).contains(&layer.speed) {
+                return Err(ParallaxDefinitionError::InvalidSpeed(index));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct Parallax {
+    pub definition: Handle<ParallaxDefinition>,
+    pub spawned: bool,
+    pub last_camera_y: f32,
+}
+
+#[derive(Component, Debug)]
+pub struct ParallaxTile {
+    pub speed: f32,
+    pub height: f32,
+    pub count: usize,
+}
+
+/// Moves a tile that dropped below the view back above the rest of its layer
+pub fn recycle(tile_y: f32, camera_y: f32, tile_height: f32, count: usize) -> f32 {
+    let view_bottom = camera_y - WINDOW_HEIGHT / 2.0;
+    let mut tile_y = tile_y;
+
+    while tile_y + tile_height / 2.0 < view_bottom {
+        tile_y += tile_height * count as f32;
+    }
+
+    tile_y
+}
+
+fn load_parallax(mut commands: Commands, asset_server: Res<AssetServer>, mut preload: ResMut<Preload>) {
+    let definition = asset_server.load(DEFAULT_PARALLAX);
+
+    preload.track(DEFAULT_PARALLAX, &definition);
+
+    commands.insert_resource(Parallax {
+        definition,
+        spawned: false,
+        last_camera_y: 0.0,
+    });
+}
+
+fn spawn_parallax_layers(
+    mut commands: Commands,
+    mut parallax: ResMut<Parallax>,
+    definitions: Res<Assets<ParallaxDefinition>>,
+    game_assets: Res<GameAssets>,
+    camera_query: Query<&Transform, With<GameCamera>>,
+) {
+
+    if parallax.spawned { return; }
+
+    let Some(definition) = definitions.get(&parallax.definition) else { return; };
+
+    let camera_y = camera_query.single().translation.y;
+
+    for layer in &definition.layers {
+        let Some(texture) = game_assets.image(&layer.texture) else {
+            warn!(target: category::GAMEPLAY, texture = %layer.texture, "parallax layer texture isn't in the manifest, skipping it");
+            continue;
+        };
+
+        let count = layer.tile_count();
+
+        // The first tile's bottom edge sits on the bottom of the view
+        let first_y = camera_y - WINDOW_HEIGHT / 2.0 + layer.tile_size.1 / 2.0;
+
+        for index in 0..count {
+            let rotation = if layer.mirrored { Quat::from_rotation_y(std::f32::consts::PI) } else { Quat::IDENTITY };
+
+            commands.spawn((
+                SpriteBundle {
+                    transform: Transform {
+                        translation: Vec3::new(0.0, first_y + index as f32 * layer.tile_size.1, layer.z),
+                        rotation,
+                        ..default()
+                    },
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::new(layer.tile_size.0, layer.tile_size.1)),
+                        ..default()
+                    },
+                    texture: texture.clone(),
+                    ..default()
+                },
+                ParallaxTile {
+                    speed: layer.speed,
+                    height: layer.tile_size.1,
+                    count,
+                }
+            ));
+        }
+    }
+
+    parallax.spawned = true;
+    parallax.last_camera_y = camera_y;
+}
+
+fn scroll_parallax_layers(
+    mut parallax: ResMut<Parallax>,
+    mut tile_query: Query<(&mut Transform, &ParallaxTile), Without<GameCamera>>,
+    camera_query: Query<&Transform, With<GameCamera>>,
+) {
+
+    if !parallax.spawned { return; }
+
+    let camera_y = camera_query.single().translation.y;
+    let camera_delta = camera_y - parallax.last_camera_y;
+    parallax.last_camera_y = camera_y;
+
+    for (mut transform, tile) in tile_query.iter_mut() {
+        // A layer that shows less of the scroll follows the camera for the rest
+        transform.translation.y += camera_delta * (1.0 - tile.speed);
+        transform.translation.y = recycle(transform.translation.y, camera_y, tile.height, tile.count);
+    }
+}
+
+#[derive(Default)]
+pub struct ParallaxDefinitionLoader;
+
+#[derive(Error, Debug)]
+pub enum ParallaxDefinitionLoaderError {
+    #[error("could not read parallax layers: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse parallax layers: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("invalid parallax layers: {0}")]
+    Invalid(#[from] ParallaxDefinitionError),
+}
+
+impl AssetLoader for ParallaxDefinitionLoader {
+    type Asset = ParallaxDefinition;
+    type Settings = ();
+    type Error = ParallaxDefinitionLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<ParallaxDefinition, ParallaxDefinitionLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            let definition: ParallaxDefinition = ron::de::from_bytes(&bytes)?;
+            definition.validate()?;
+
+            Ok(definition)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["parallax.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_parallax() -> ParallaxDefinition {
+        let source = std::fs::read_to_string(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/assets/", "background/default.parallax.ron")
+        ).unwrap();
+
+        ron::from_str(&source).unwrap()
+    }
+
+    #[test]
+    fn default_layers_are_valid() {
+        let definition = default_parallax();
+
+        assert_eq!(definition.validate(), Ok(()));
+
+        // Farther layers move slower
+        assert!(definition.layers.windows(2).all(|pair| pair[0].speed < pair[1].speed && pair[0].z < pair[1].z));
+    }
+
+    #[test]
+    fn tiles_cover_the_view_forever() {
+        let layer = ParallaxLayer {
+            texture: String::new(),
+            tile_size: (360.0, 250.0),
+            speed: 0.5,
+            z: 0.0,
+            mirrored: false,
+        };
+
+        let count = layer.tile_count();
+        let height = layer.tile_size.1;
+
+        // Same layout and movement as the systems, scrolled well past what the tiles cover
+        let mut tiles: Vec<f32> = (0..count).map(|index| -WINDOW_HEIGHT / 2.0 + height / 2.0 + index as f32 * height).collect();
+        let mut camera_y = 0.0;
+
+        for _ in 0..10_000 {
+            let camera_delta = 7.3;
+            camera_y += camera_delta;
+
+            for tile_y in tiles.iter_mut() {
+                *tile_y = recycle(*tile_y + camera_delta * (1.0 - layer.speed), camera_y, height, count);
+            }
+
+            let bottom = tiles.iter().map(|tile_y| tile_y - height / 2.0).fold(f32::MAX, f32::min);
+            let top = tiles.iter().map(|tile_y| tile_y + height / 2.0).fold(f32::MIN, f32::max);
+
+            assert!(bottom <= camera_y - WINDOW_HEIGHT / 2.0 + 0.1);
+            assert!(top >= camera_y + WINDOW_HEIGHT / 2.0 - 0.1);
+        }
+    }
+
+    #[test]
+    fn invalid_layers_are_rejected() {
+        let mut definition = default_parallax();
+        definition.layers[1].speed = 1.5;
+        assert_eq!(definition.validate(), Err(ParallaxDefinitionError::InvalidSpeed(1)));
+
+        definition.layers[0].tile_size = (0.0, 100.0);
+        assert_eq!(definition.validate(), Err(ParallaxDefinitionError::InvalidTileSize(0)));
+
+        definition.layers.clear();
+        assert_eq!(definition.validate(), Err(ParallaxDefinitionError::NoLayers));
+    }
+}
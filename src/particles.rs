@@ -0,0 +1,502 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    constants::*,
+    ship::{Rocket, Ship},
+    AsteroidDestroyed,
+    ExplosionAnimations,
+    ExplosionEvent,
+    MyGameSet
+};
+
+/// Small CPU particles for engine exhaust, rocket trails, asteroid debris and damage
+/// sparks. Particles are plain sprites simulated on the fixed timestep, never more
+/// than `ParticleBudget::max` at once. Seeding `ParticleRng` (`--particle-seed=<n>`)
+/// makes every emitter and burst repeat exactly, which is what the tests rely on.
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(ParticleRng::from_args())
+            .insert_resource(ParticleBudget { max: PARTICLE_BUDGET })
+            .add_systems(Update, attach_emitters)
+            .add_systems(FixedUpdate, (
+                update_exhaust,
+                emit_particles.after(update_exhaust),
+                update_particles.after(emit_particles),
+            ).in_set(MyGameSet))
+            .add_systems(PostUpdate, burst_particles
+                .after(crate::explosion_event_listener)
+                .in_set(MyGameSet)
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct ParticleRng(pub StdRng);
+
+impl ParticleRng {
+    /// Seeded with `--particle-seed=<n>`, random otherwise
+    pub fn from_args() -> ParticleRng {
+        let seed = std::env::args()
+            .find_map(|arg| arg.strip_prefix("--particle-seed=").and_then(|seed| seed.parse().ok()));
+
+        match seed {
+            Some(seed) => ParticleRng::seeded(seed),
+            None => ParticleRng(StdRng::from_entropy()),
+        }
+    }
+
+    /// Deterministic mode, the same seed gives the same particles
+    pub fn seeded(seed: u64) -> ParticleRng {
+        ParticleRng(StdRng::seed_from_u64(seed))
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct ParticleBudget {
+    pub max: usize,
+}
+
+/// Values to blend between over a particle's life, keyed by the fraction of its life
+/// gone (0.0 to 1.0, in order)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve<T>(pub Vec<(f32, T)>);
+
+pub trait Mix: Copy {
+    fn mix(self, other: Self, amount: f32) -> Self;
+}
+
+impl Mix for f32 {
+    fn mix(self, other: f32, amount: f32) -> f32 {
+        self + (other - self) * amount
+    }
+}
+
+impl Mix for Color {
+    fn mix(self, other: Color, amount: f32) -> Color {
+        let [red, green, blue, alpha] = self.as_rgba_f32();
+        let [other_red, other_green, other_blue, other_alpha] = other.as_rgba_f32();
+
+        Color::rgba(
+            red.mix(other_red, amount),
+            green.mix(other_green, amount),
+            blue.mix(other_blue, amount),
+            alpha.mix(other_alpha, amount)
+        )
+    }
+}
+
+impl<T: Mix> Curve<T> {
+    pub fn constant(value: T) -> Curve<T> {
+        Curve(vec![(0.0, value)])
+    }
+
+    pub fn linear(start: T, end: T) -> Curve<T> {
+        Curve(vec![(0.0, start), (1.0, end)])
+    }
+
+    pub fn sample(&self, at: f32) -> T {
+        let keys = &self.0;
+
+        let Some(index) = keys.iter().position(|(key, _)| *key > at) else {
+            return keys[keys.len() - 1].1;
+        };
+
+        if index == 0 { return keys[0].1; }
+
+        let (start_at, start) = keys[index - 1];
+        let (end_at, end) = keys[index];
+
+        start.mix(end, (at - start_at) / (end_at - start_at))
+    }
+}
+
+/// Everything about how an emitter's particles look and move
+#[derive(Debug, Clone)]
+pub struct EmitterSpec {
+    /// Particles per second, bursts ignore it
+    pub rate: f32,
+    pub lifetime: (f32, f32),
+    pub speed: (f32, f32),
+    /// Center of the velocity cone
+    pub direction: Vec2,
+    /// Full width of the velocity cone in radians
+    pub cone: f32,
+    pub size: Curve<f32>,
+    pub color: Curve<Color>,
+    pub z: f32,
+}
+
+impl EmitterSpec {
+    pub fn exhaust() -> EmitterSpec {
+        EmitterSpec {
+            rate: 60.0,
+            lifetime: (0.15, 0.35),
+            speed: (60.0, 110.0),
+            direction: Vec2::NEG_Y,
+            cone: 0.5,
+            size: Curve::linear(4.0, 1.0),
+            color: Curve(vec![
+                (0.0, Color::rgba(1.0, 0.95, 0.6, 1.0)),
+                (0.4, Color::rgba(1.0, 0.5, 0.1, 0.8)),
+                (1.0, Color::rgba(0.6, 0.1, 0.05, 0.0)),
+            ]),
+            // Under the ship
+            z: -0.01,
+        }
+    }
+
+    pub fn rocket_trail() -> EmitterSpec {
+        EmitterSpec {
+            rate: 40.0,
+            lifetime: (0.1, 0.25),
+            speed: (10.0, 30.0),
+            direction: Vec2::NEG_Y,
+            cone: 0.8,
+            size: Curve::linear(2.5, 0.5),
+            color: Curve::linear(Color::rgba(0.9, 0.9, 1.0, 0.8), Color::rgba(0.4, 0.5, 1.0, 0.0)),
+            z: -0.01,
+        }
+    }
+
+    pub fn debris() -> EmitterSpec {
+        EmitterSpec {
+            rate: 0.0,
+            lifetime: (0.4, 0.9),
+            speed: (40.0, 140.0),
+            direction: Vec2::Y,
+            cone: std::f32::consts::TAU,
+            size: Curve::linear(3.5, 1.5),
+            color: Curve::linear(Color::rgba(0.6, 0.5, 0.45, 1.0), Color::rgba(0.35, 0.3, 0.3, 0.0)),
+            z: 0.2,
+        }
+    }
+
+    pub fn sparks() -> EmitterSpec {
+        EmitterSpec {
+            rate: 0.0,
+            lifetime: (0.15, 0.4),
+            speed: (120.0, 220.0),
+            direction: Vec2::Y,
+            cone: std::f32::consts::TAU,
+            size: Curve::constant(2.0),
+            color: Curve::linear(Color::rgba(1.0, 1.0, 0.7, 1.0), Color::rgba(1.0, 0.4, 0.1, 0.0)),
+            z: 0.2,
+        }
+    }
+
+    /// A single particle leaving `position`, the cone turned by `rotation`
+    pub fn particle(&self, position: Vec2, rotation: f32, rng: &mut impl Rng) -> ParticleSpawn {
+        let angle = rotation + rng.gen_range(-0.5..=0.5) * self.cone;
+        let speed = rng.gen_range(self.speed.0..=self.speed.1);
+
+        ParticleSpawn {
+            position,
+            velocity: Vec2::from_angle(angle).rotate(self.direction) * speed,
+            lifetime: rng.gen_range(self.lifetime.0..=self.lifetime.1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParticleSpawn {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub lifetime: f32,
+}
+
+#[derive(Component, Debug)]
+pub struct ParticleEmitter {
+    pub spec: Arc<EmitterSpec>,
+    /// Scales the spawn rate, 0 turns the emitter off
+    pub rate_scale: f32,
+    /// From the emitting entity, turned with it
+    pub offset: Vec2,
+    accumulator: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(spec: EmitterSpec, offset: Vec2) -> ParticleEmitter {
+        ParticleEmitter {
+            spec: Arc::new(spec),
+            rate_scale: 1.0,
+            offset,
+            accumulator: 0.0,
+        }
+    }
+
+    /// How many particles are due after `delta` seconds, fractions carry over
+    pub fn due(&mut self, delta: f32) -> u32 {
+        self.accumulator += self.spec.rate * self.rate_scale * delta;
+
+        let due = self.accumulator.floor();
+        self.accumulator -= due;
+
+        due as u32
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct Particle {
+    pub spec: Arc<EmitterSpec>,
+    pub velocity: Vec2,
+    pub age: f32,
+    pub lifetime: f32,
+}
+
+impl Particle {
+    /// Fraction of its life gone
+    pub fn progress(&self) -> f32 {
+        (self.age / self.lifetime).min(1.0)
+    }
+}
+
+fn spawn_particle(commands: &mut Commands, spec: &Arc<EmitterSpec>, spawn: ParticleSpawn) {
+    let size = spec.size.sample(0.0);
+
+    commands.spawn((
+        SpriteBundle {
+            transform: Transform::from_translation(spawn.position.extend(spec.z)),
+            sprite: Sprite {
+                color: spec.color.sample(0.0),
+                custom_size: Some(Vec2::splat(size)),
+                ..default()
+            },
+            ..default()
+        },
+        Particle {
+            spec: spec.clone(),
+            velocity: spawn.velocity,
+            age: 0.0,
+            lifetime: spawn.lifetime,
+        }
+    ));
+}
+
+/// Spawns `count` particles at once, as far as the budget allows
+pub fn burst(
+    commands: &mut Commands,
+    spec: &Arc<EmitterSpec>,
+    count: usize,
+    position: Vec2,
+    live: &mut usize,
+    budget: &ParticleBudget,
+    rng: &mut impl Rng,
+) {
+    let count = count.min(budget.max.saturating_sub(*live));
+
+    for _ in 0..count {
+        spawn_particle(commands, spec, spec.particle(position, 0.0, rng));
+    }
+
+    *live += count;
+}
+
+fn attach_emitters(
+    mut commands: Commands,
+    ship_query: Query<Entity, Added<Ship>>,
+    rocket_query: Query<Entity, Added<Rocket>>,
+) {
+    for ship in ship_query.iter() {
+        commands.entity(ship).insert(ParticleEmitter::new(EmitterSpec::exhaust(), Vec2::new(0.0, -16.0)));
+    }
+
+    for rocket in rocket_query.iter() {
+        commands.entity(rocket).insert(ParticleEmitter::new(EmitterSpec::rocket_trail(), Vec2::new(0.0, -8.0)));
+    }
+}
+
+/// The engine burns brighter the harder the ship is steered
+fn update_exhaust(mut ship_query: Query<(&Ship, &mut ParticleEmitter)>) {
+    for (ship, mut emitter) in ship_query.iter_mut() {
+        let rate_scale = if ship.is_destroyed() { 0.0 } else { 0.3 + 0.7 * ship.thrust };
+
+        if emitter.rate_scale != rate_scale {
+            emitter.rate_scale = rate_scale;
+        }
+    }
+}
+
+fn emit_particles(
+    mut commands: Commands,
+    time: Res<Time<Fixed>>,
+    mut rng: ResMut<ParticleRng>,
+    budget: Res<ParticleBudget>,
+    mut emitter_query: Query<(&Transform, &mut ParticleEmitter)>,
+    particle_query: Query<(), With<Particle>>,
+) {
+
+    let mut live = particle_query.iter().count();
+
+    for (transform, mut emitter) in emitter_query.iter_mut() {
+        let due = emitter.due(time.delta_seconds()) as usize;
+        let due = due.min(budget.max.saturating_sub(live));
+
+        if due == 0 { continue; }
+
+        let rotation = transform.rotation.to_euler(EulerRot::XYZ).2;
+        let position = transform.translation.truncate() + Vec2::from_angle(rotation).rotate(emitter.offset);
+
+        for _ in 0..due {
+            let spawn = emitter.spec.particle(position, rotation, &mut rng.0);
+            spawn_particle(&mut commands, &emitter.spec, spawn);
+        }
+
+        live += due;
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    time: Res<Time<Fixed>>,
+    mut particle_query: Query<(Entity, &mut Transform, &mut Sprite, &mut Particle)>,
+) {
+
+    for (entity, mut transform, mut sprite, mut particle) in particle_query.iter_mut() {
+        particle.age += time.delta_seconds();
+
+        if particle.age >= particle.lifetime {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        transform.translation += particle.velocity.extend(0.0) * time.delta_seconds();
+
+        let progress = particle.progress();
+
+        sprite.color = particle.spec.color.sample(progress);
+        sprite.custom_size = Some(Vec2::splat(particle.spec.size.sample(progress)));
+    }
+}
+
+fn burst_particles(
+    mut commands: Commands,
+    mut asteroid_explosion: EventReader<AsteroidDestroyed>,
+    mut collision_events: EventReader<ExplosionEvent>,
+    mut rng: ResMut<ParticleRng>,
+    budget: Res<ParticleBudget>,
+    ship_query: Query<&Transform, With<Ship>>,
+    particle_query: Query<(), With<Particle>>,
+) {
+
+    let mut live = particle_query.iter().count();
+
+    let debris = Arc::new(EmitterSpec::debris());
+    let sparks = Arc::new(EmitterSpec::sparks());
+
+    for AsteroidDestroyed { asteroid_type, position } in asteroid_explosion.read() {
+        let count = (asteroid_type.radius() * 0.6) as usize;
+
+        burst(&mut commands, &debris, count, position.truncate(), &mut live, &budget, &mut rng.0);
+    }
+
+    for ExplosionEvent { explosion_type, .. } in collision_events.read() {
+        if !matches!(explosion_type, ExplosionAnimations::DamageToShip) { continue; }
+
+        let Ok(ship_transform) = ship_query.get_single() else { continue; };
+
+        burst(&mut commands, &sparks, 24, ship_transform.translation.truncate(), &mut live, &budget, &mut rng.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emit_for(emitter: &mut ParticleEmitter, seconds: f32, rng: &mut impl Rng) -> Vec<ParticleSpawn> {
+        let mut spawns = Vec::new();
+
+        for _ in 0..(seconds * 64.0) as u32 {
+            for _ in 0..emitter.due(1.0 / 64.0) {
+                spawns.push(emitter.spec.particle(Vec2::ZERO, 0.0, rng));
+            }
+        }
+
+        spawns
+    }
+
+    #[test]
+    fn emitters_keep_their_rate() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut emitter = ParticleEmitter::new(EmitterSpec::exhaust(), Vec2::ZERO);
+
+        assert_eq!(emit_for(&mut emitter, 2.0, &mut rng).len(), 120);
+
+        emitter.rate_scale = 0.5;
+        assert_eq!(emit_for(&mut emitter, 2.0, &mut rng).len(), 60);
+
+        emitter.rate_scale = 0.0;
+        assert!(emit_for(&mut emitter, 2.0, &mut rng).is_empty());
+    }
+
+    #[test]
+    fn particles_leave_within_the_cone() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let spec = EmitterSpec::exhaust();
+
+        for _ in 0..500 {
+            let spawn = spec.particle(Vec2::ZERO, 0.0, &mut rng);
+
+            assert!(spawn.velocity.angle_between(Vec2::NEG_Y).abs() <= spec.cone / 2.0 + 1e-4);
+            assert!((spec.speed.0 - 1e-3..=spec.speed.1 + 1e-3).contains(&spawn.velocity.length()));
+            assert!((spec.lifetime.0..=spec.lifetime.1).contains(&spawn.lifetime));
+
+            // Turned half way round, the exhaust points up
+            let turned = spec.particle(Vec2::ZERO, std::f32::consts::PI, &mut rng);
+            assert!(turned.velocity.y > 0.0);
+        }
+    }
+
+    #[test]
+    fn same_seed_same_particles() {
+        let run = |seed: u64| {
+            let mut rng = ParticleRng::seeded(seed);
+            let mut emitter = ParticleEmitter::new(EmitterSpec::rocket_trail(), Vec2::ZERO);
+
+            emit_for(&mut emitter, 1.0, &mut rng.0)
+        };
+
+        assert_eq!(run(9), run(9));
+        assert_ne!(run(9), run(10));
+    }
+
+    #[test]
+    fn curves_blend_between_keys() {
+        let size = Curve(vec![(0.0, 4.0), (0.5, 2.0), (1.0, 0.0)]);
+
+        assert_eq!(size.sample(0.0), 4.0);
+        assert_eq!(size.sample(0.25), 3.0);
+        assert_eq!(size.sample(0.75), 1.0);
+        assert_eq!(size.sample(2.0), 0.0);
+        assert_eq!(Curve::constant(2.0).sample(0.7), 2.0);
+
+        let color = Curve::linear(Color::rgba(1.0, 0.0, 0.0, 1.0), Color::rgba(0.0, 0.0, 1.0, 0.0));
+        assert_eq!(color.sample(0.5), Color::rgba(0.5, 0.0, 0.5, 0.5));
+    }
+
+    #[test]
+    fn bursts_respect_the_budget() {
+        let mut app = App::new();
+        let mut rng = StdRng::seed_from_u64(3);
+        let budget = ParticleBudget { max: 50 };
+        let spec = Arc::new(EmitterSpec::debris());
+
+        let mut live = 40;
+
+        app.add_systems(Update, move |mut commands: Commands| {
+            burst(&mut commands, &spec, 30, Vec2::ZERO, &mut live, &budget, &mut rng);
+            assert_eq!(live, 50);
+        });
+
+        app.update();
+
+        let spawned = app.world.query::<&Particle>().iter(&app.world).count();
+        assert_eq!(spawned, 10);
+    }
+}
@@ -0,0 +1,620 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    math::bounding::{BoundingCircle, IntersectsVolume},
+    prelude::*,
+    utils::BoxedFuture
+};
+
+use rand::Rng;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    asteroid::Asteroid,
+    constants::*,
+    enemy::{EnemyProjectile, EnemyShip},
+    loading::Preload,
+    ship::Ship,
+    race::Fuel,
+    weapon::Weapon,
+    AsteroidDestroyed,
+    CollidableComponentNames,
+    Collider,
+    ColliderShape,
+    ExplosionAnimations,
+    ExplosionEvent,
+    GameCamera,
+    GameDifficulty,
+    GameRng,
+    MyGameSet
+};
+
+/// Pickups dropped by destroyed asteroids and the timed effects they leave behind.
+/// How often and what drops is set per difficulty in `assets/pickups/default.drops.ron`.
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<DropTables>()
+            .register_asset_loader(DropTablesLoader)
+            .init_resource::<ActiveEffects>()
+            .add_systems(Startup, load_drop_tables)
+            .add_systems(Update, (
+                collect_pickups.after(crate::collision_checks),
+                apply_effects.after(collect_pickups),
+            ).in_set(MyGameSet))
+            .add_systems(FixedUpdate, pickup_manager.in_set(MyGameSet))
+            .add_systems(PostUpdate, drop_pickups.after(crate::explosion_event_listener).in_set(MyGameSet));
+    }
+}
+
+pub const DEFAULT_DROP_TABLES: &str = "pickups/default.drops.ron";
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PickupKind {
+    /// Restores a point of hull health
+    Repair,
+    /// Brings the shield back to full
+    Shield,
+    /// Weapon cooldowns are shortened for a while
+    RapidFire,
+    /// Score counts double for a while
+    ScoreMultiplier,
+    /// Destroys every asteroid and enemy on screen
+    Bomb,
+    /// Tops up the tank in race mode
+    Fuel,
+}
+
+impl PickupKind {
+    /// How long the effect lasts, instant pickups have none
+    pub fn duration(&self) -> Option<f32> {
+        match self {
+            PickupKind::RapidFire => Some(PICKUP_RAPID_FIRE_DURATION),
+            PickupKind::ScoreMultiplier => Some(PICKUP_SCORE_MULTIPLIER_DURATION),
+            PickupKind::Repair | PickupKind::Shield | PickupKind::Bomb | PickupKind::Fuel => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PickupKind::Repair => "+",
+            PickupKind::Shield => "S",
+            PickupKind::RapidFire => "F",
+            PickupKind::ScoreMultiplier => "x2",
+            PickupKind::Bomb => "B",
+            PickupKind::Fuel => "E",
+        }
+    }
+
+    /// Shown next to the countdown in the HUD
+    pub fn name(&self) -> &'static str {
+        match self {
+            PickupKind::Repair => "Repair",
+            PickupKind::Shield => "Shield",
+            PickupKind::RapidFire => "Rapid fire",
+            PickupKind::ScoreMultiplier => "Score x2",
+            PickupKind::Bomb => "Bomb",
+            PickupKind::Fuel => "Fuel",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            PickupKind::Repair => Color::rgb(0.3, 0.9, 0.4),
+            PickupKind::Shield => Color::rgb(0.3, 0.7, 1.0),
+            PickupKind::RapidFire => Color::rgb(1.0, 0.6, 0.2),
+            PickupKind::ScoreMultiplier => Color::rgb(1.0, 0.9, 0.2),
+            PickupKind::Bomb => Color::rgb(0.9, 0.2, 0.3),
+            PickupKind::Fuel => Color::rgb(0.8, 0.8, 0.8),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct DropTable {
+    /// Chance for a destroyed asteroid to drop anything at all
+    pub chance: f32,
+    /// Relative weights of what drops
+    pub weights: Vec<(PickupKind, u32)>,
+}
+
+impl DropTable {
+    pub fn roll(&self, rng: &mut impl Rng) -> Option<PickupKind> {
+        let total: u32 = self.weights.iter().map(|(_, weight)| weight).sum();
+
+        if total == 0 || !rng.gen_bool(self.chance.clamp(0.0, 1.0) as f64) {
+            return None;
+        }
+
+        let mut pick = rng.gen_range(0..total);
+
+        for (kind, weight) in &self.weights {
+            if pick < *weight {
+                return Some(*kind);
+            }
+
+            pick -= weight;
+        }
+
+        None
+    }
+}
+
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct DropTables {
+    pub easy: DropTable,
+    pub medium: DropTable,
+    pub hard: DropTable,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum DropTablesError {
+    #[error("the {0} drop chance must be between 0 and 1")]
+    InvalidChance(&'static str),
+    #[error("the {0} table can drop but has no weights")]
+    NoWeights(&'static str),
+}
+
+impl DropTables {
+    pub fn for_difficulty(&self, difficulty: &GameDifficulty) -> &DropTable {
+        match difficulty {
+            GameDifficulty::Easy => &self.easy,
+            GameDifficulty::Medium => &self.medium,
+            GameDifficulty::Hard => &self.hard,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), DropTablesError> {
+        for (name, table) in [("easy", &self.easy), ("medium", &self.medium), ("hard", &self.hard)] {
+            if !(0.0..=1.0).contains(&table.chance) {
+                return Err(DropTablesError::InvalidChance(name));
+            }
+
+            if table.chance > 0.0 && table.weights.iter().all(|(_, weight)| *weight == 0) {
+                return Err(DropTablesError::NoWeights(name));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct PickupDrops {
+    pub tables: Handle<DropTables>,
+}
+
+fn load_drop_tables(mut commands: Commands, asset_server: Res<AssetServer>, mut preload: ResMut<Preload>) {
+    let tables = asset_server.load(DEFAULT_DROP_TABLES);
+
+    preload.track(DEFAULT_DROP_TABLES, &tables);
+
+    commands.insert_resource(PickupDrops { tables });
+}
+
+/// Timed effects from pickups. Picking up one that is already running starts it over.
+#[derive(Resource, Debug, Default)]
+pub struct ActiveEffects {
+    timers: Vec<(PickupKind, Timer)>,
+}
+
+impl ActiveEffects {
+    pub fn start(&mut self, kind: PickupKind) {
+        let Some(duration) = kind.duration() else { return; };
+
+        self.timers.retain(|(active, _)| *active != kind);
+        self.timers.push((kind, Timer::from_seconds(duration, TimerMode::Once)));
+    }
+
+    pub fn tick(&mut self, delta: std::time::Duration) {
+        for (_, timer) in self.timers.iter_mut() {
+            timer.tick(delta);
+        }
+
+        self.timers.retain(|(_, timer)| !timer.finished());
+    }
+
+    pub fn is_active(&self, kind: PickupKind) -> bool {
+        self.timers.iter().any(|(active, _)| *active == kind)
+    }
+
+    /// Running effects and their seconds left, in the order they were picked up
+    pub fn remaining(&self) -> impl Iterator<Item = (PickupKind, f32)> + '_ {
+        self.timers.iter().map(|(kind, timer)| (*kind, timer.remaining_secs()))
+    }
+
+    pub fn score_multiplier(&self) -> u64 {
+        if self.is_active(PickupKind::ScoreMultiplier) { SCORE_MULTIPLIER } else { 1 }
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct Pickup {
+    pub kind: PickupKind,
+    pub collected: bool,
+}
+
+impl Pickup {
+    pub fn check_collision(
+        &self,
+        pickup_transform: &Transform,
+        other_transform: &Transform,
+        other_name: &CollidableComponentNames
+    ) -> bool {
+
+        if *other_name != CollidableComponentNames::Ship { return false; }
+
+        let pickup_circle = BoundingCircle::new(pickup_transform.translation.truncate(), PICKUP_RADIUS);
+
+        let ship_circle = BoundingCircle::new(
+            other_transform.translation.truncate(),
+            SHIP_TRUE_WIDTH * SHIP_APPLIED_SCALE.x / 2.0
+        );
+
+        pickup_circle.intersects(&ship_circle)
+    }
+}
+
+#[derive(Bundle)]
+pub struct PickupBundle {
+    pickup: Pickup,
+    sprite_bundle: SpriteBundle,
+    collider: Collider,
+}
+
+impl PickupBundle {
+    pub fn new(kind: PickupKind, position: Vec2) -> PickupBundle {
+        PickupBundle {
+            pickup: Pickup {
+                kind,
+                collected: false,
+            },
+            sprite_bundle: SpriteBundle {
+                // Above the explosion it drops out of
+                transform: Transform::from_translation(position.extend(1.0)),
+                sprite: Sprite {
+                    color: kind.color(),
+                    custom_size: Some(PICKUP_SIZE),
+                    ..default()
+                },
+                ..default()
+            },
+            collider: Collider {
+                name: CollidableComponentNames::Pickup,
+                shape: ColliderShape::Circle
+            },
+        }
+    }
+}
+
+pub fn spawn_pickup(commands: &mut Commands, kind: PickupKind, position: Vec2) {
+    commands.spawn(PickupBundle::new(kind, position)).with_children(|parent| {
+        parent.spawn(Text2dBundle {
+            text: Text::from_section(
+                kind.label(),
+                TextStyle {
+                    font_size: 12.0,
+                    color: Color::BLACK,
+                    ..default()
+                }
+            ),
+            transform: Transform::from_xyz(0.0, 0.0, 0.1),
+            ..default()
+        });
+    });
+}
+
+fn drop_pickups(
+    mut commands: Commands,
+    mut asteroid_explosion: EventReader<AsteroidDestroyed>,
+    drops: Res<PickupDrops>,
+    drop_tables: Res<Assets<DropTables>>,
+    difficulty: Res<GameDifficulty>,
+    mut rng: ResMut<GameRng>,
+) {
+
+    // Nothing drops until the tables are loaded
+    let Some(tables) = drop_tables.get(&drops.tables) else {
+        asteroid_explosion.clear();
+        return;
+    };
+
+    let table = tables.for_difficulty(&difficulty);
+
+    for AsteroidDestroyed { position, .. } in asteroid_explosion.read() {
+        if let Some(kind) = table.roll(&mut rng.0) {
+            spawn_pickup(&mut commands, kind, position.truncate());
+        }
+    }
+}
+
+/// Applies whatever the ship flew into during `collision_checks`
+#[allow(clippy::type_complexity)]
+#[allow(clippy::too_many_arguments)]
+fn collect_pickups(
+    mut commands: Commands,
+    mut effects: ResMut<ActiveEffects>,
+    pickup_query: Query<(Entity, &Pickup)>,
+    mut ship_query: Query<&mut Ship>,
+    asteroid_query: Query<(Entity, &Transform, &Asteroid)>,
+    enemy_query: Query<(Entity, &Transform, &EnemyShip)>,
+    mut projectile_query: Query<&mut EnemyProjectile>,
+    camera_query: Query<&Transform, With<GameCamera>>,
+    mut collision_events: EventWriter<ExplosionEvent>,
+    mut fuel: Option<ResMut<Fuel>>,
+) {
+
+    for (entity, pickup) in pickup_query.iter() {
+        if !pickup.collected { continue; }
+
+        commands.entity(entity).despawn_recursive();
+
+        match pickup.kind {
+            PickupKind::Repair => {
+                let mut ship = ship_query.single_mut();
+
+                // The sprite catches up in update_ship_clip
+                ship.hull.heal(1);
+            },
+            PickupKind::Shield => {
+                let mut ship = ship_query.single_mut();
+
+                ship.shield.refill();
+            },
+            PickupKind::Bomb => {
+                let camera_y = camera_query.single().translation.y;
+                let on_screen = |transform: &Transform| (transform.translation.y - camera_y).abs() <= WINDOW_HEIGHT / 2.0;
+
+                for (asteroid_entity, transform, asteroid) in asteroid_query.iter() {
+                    if asteroid.exploding || !on_screen(transform) { continue; }
+
+                    collision_events.send(ExplosionEvent {
+                        explosion_type: ExplosionAnimations::AsteroidExplosion,
+                        entity: asteroid_entity
+                    });
+                }
+
+                for (enemy_entity, transform, enemy_ship) in enemy_query.iter() {
+                    if enemy_ship.exploding || !on_screen(transform) { continue; }
+
+                    collision_events.send(ExplosionEvent {
+                        explosion_type: ExplosionAnimations::EnemyShipExplosion,
+                        entity: enemy_entity
+                    });
+                }
+
+                for mut projectile in projectile_query.iter_mut() {
+                    projectile.hit_target = true;
+                }
+            },
+            PickupKind::Fuel => {
+                if let Some(fuel) = fuel.as_mut() {
+                    fuel.refill();
+                }
+            },
+            kind => effects.start(kind),
+        }
+    }
+}
+
+fn apply_effects(
+    time: Res<Time>,
+    mut effects: ResMut<ActiveEffects>,
+    mut weapon_query: Query<&mut Weapon, With<Ship>>,
+) {
+
+    effects.tick(time.delta());
+
+    let mut weapon = weapon_query.single_mut();
+
+    let cooldown_multiplier = if effects.is_active(PickupKind::RapidFire) {
+        RAPID_FIRE_COOLDOWN_MULTIPLIER
+    } else { 1.0 };
+
+    if weapon.cooldown_multiplier != cooldown_multiplier {
+        weapon.cooldown_multiplier = cooldown_multiplier;
+    }
+}
+
+/// Pickups stay where they dropped, so they leave through the bottom as the camera moves on
+fn pickup_manager(
+    mut commands: Commands,
+    pickup_query: Query<(Entity, &Transform), With<Pickup>>,
+    camera_query: Query<&Transform, (With<GameCamera>, Without<Pickup>)>,
+) {
+
+    let camera_transform = camera_query.single();
+    let despawn_threshold = BOTTOM_WALL - PICKUP_SIZE.y + camera_transform.translation.y;
+
+    for (entity, transform) in pickup_query.iter() {
+        if transform.translation.y < despawn_threshold {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DropTablesLoader;
+
+#[derive(Error, Debug)]
+pub enum DropTablesLoaderError {
+    #[error("could not read drop tables: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse drop tables: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("invalid drop tables: {0}")]
+    Invalid(#[from] DropTablesError),
+}
+
+impl AssetLoader for DropTablesLoader {
+    type Asset = DropTables;
+    type Settings = ();
+    type Error = DropTablesLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<DropTables, DropTablesLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            let tables: DropTables = ron::de::from_bytes(&bytes)?;
+            tables.validate()?;
+
+            Ok(tables)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["drops.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        animation::PlayClip,
+        enemy::{EnemyDestroyed, EnemyPattern},
+        game_assets::GameAssets,
+        pool::EntityPools,
+        ship::Health,
+        shield::Shield,
+        explosion_event_listener,
+        AppState
+    };
+
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::time::Duration;
+
+    fn default_tables() -> DropTables {
+        let source = std::fs::read_to_string(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/assets/", "pickups/default.drops.ron")
+        ).unwrap();
+
+        ron::from_str(&source).unwrap()
+    }
+
+    #[test]
+    fn default_drop_tables_are_valid() {
+        let tables = default_tables();
+
+        assert_eq!(tables.validate(), Ok(()));
+
+        // Harder games drop less
+        assert!(tables.easy.chance > tables.medium.chance);
+        assert!(tables.medium.chance > tables.hard.chance);
+    }
+
+    #[test]
+    fn rolls_follow_chance_and_weights() {
+        let mut rng = StdRng::seed_from_u64(3);
+
+        let never = DropTable { chance: 0.0, weights: vec![(PickupKind::Repair, 1)] };
+        assert!((0..1000).all(|_| never.roll(&mut rng).is_none()));
+
+        let always_bombs = DropTable { chance: 1.0, weights: vec![(PickupKind::Repair, 0), (PickupKind::Bomb, 5)] };
+        assert!((0..1000).all(|_| always_bombs.roll(&mut rng) == Some(PickupKind::Bomb)));
+
+        let mixed = DropTable { chance: 0.5, weights: vec![(PickupKind::Shield, 3), (PickupKind::RapidFire, 1)] };
+        let rolls: Vec<PickupKind> = (0..4000).filter_map(|_| mixed.roll(&mut rng)).collect();
+        let shields = rolls.iter().filter(|kind| **kind == PickupKind::Shield).count();
+
+        assert!((1800..2200).contains(&rolls.len()));
+        assert!((0.7..0.8).contains(&(shields as f32 / rolls.len() as f32)));
+    }
+
+    #[test]
+    fn invalid_tables_are_rejected() {
+        let mut tables = default_tables();
+        tables.medium.chance = 1.5;
+        assert_eq!(tables.validate(), Err(DropTablesError::InvalidChance("medium")));
+
+        let mut tables = default_tables();
+        tables.hard.weights = vec![(PickupKind::Bomb, 0)];
+        assert_eq!(tables.validate(), Err(DropTablesError::NoWeights("hard")));
+    }
+
+    #[test]
+    fn effects_expire_and_restart() {
+        let mut effects = ActiveEffects::default();
+
+        effects.start(PickupKind::ScoreMultiplier);
+        effects.start(PickupKind::Bomb);
+        assert_eq!(effects.score_multiplier(), SCORE_MULTIPLIER);
+        assert!(!effects.is_active(PickupKind::Bomb));
+
+        effects.tick(Duration::from_secs_f32(PICKUP_SCORE_MULTIPLIER_DURATION - 1.0));
+        effects.start(PickupKind::ScoreMultiplier);
+
+        let remaining: Vec<(PickupKind, f32)> = effects.remaining().collect();
+        assert_eq!(remaining, vec![(PickupKind::ScoreMultiplier, PICKUP_SCORE_MULTIPLIER_DURATION)]);
+
+        effects.tick(Duration::from_secs_f32(PICKUP_SCORE_MULTIPLIER_DURATION));
+        assert_eq!(effects.score_multiplier(), 1);
+        assert_eq!(effects.remaining().count(), 0);
+    }
+
+    #[test]
+    fn only_the_ship_collects() {
+        let pickup = Pickup { kind: PickupKind::Repair, collected: false };
+        let position = Transform::from_xyz(0.0, 0.0, 0.0);
+
+        assert!(pickup.check_collision(&position, &Transform::from_xyz(20.0, 0.0, 0.0), &CollidableComponentNames::Ship));
+        assert!(!pickup.check_collision(&position, &Transform::from_xyz(60.0, 0.0, 0.0), &CollidableComponentNames::Ship));
+        assert!(!pickup.check_collision(&position, &position, &CollidableComponentNames::Rocket));
+    }
+
+    #[test]
+    fn bombed_enemies_are_scored_once() {
+        let mut app = App::new();
+
+        app
+            .add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Image>()
+            .init_asset::<TextureAtlasLayout>()
+            .init_asset::<AudioSource>()
+            .init_resource::<GameAssets>()
+            .init_resource::<EntityPools>()
+            .init_resource::<ActiveEffects>()
+            .init_resource::<NextState<AppState>>()
+            .insert_resource(GameRng::seeded(1))
+            .add_event::<ExplosionEvent>()
+            .add_event::<AsteroidDestroyed>()
+            .add_event::<EnemyDestroyed>()
+            .add_event::<PlayClip>()
+            .add_systems(Update, (collect_pickups, explosion_event_listener).chain());
+
+        app.world.spawn((GameCamera, Transform::default()));
+        app.world.spawn(Ship {
+            hull: Health { current: 5, max: 5 },
+            shield: Shield::new(0),
+            invulnerable: false,
+            god_mode: false,
+            thrust: 0.0,
+        });
+
+        let enemy = app.world.spawn((
+            EnemyShip::new(EnemyPattern::Dive, 0.0, 1.0),
+            Transform::from_xyz(0.0, 100.0, 0.0),
+            Handle::<Image>::default(),
+            TextureAtlas::default(),
+        )).id();
+
+        let mut destroyed = app.world.resource::<Events<EnemyDestroyed>>().get_reader();
+
+        // Two bombs a frame apart, the enemy still has all its hit points for the first
+        for _ in 0..2 {
+            app.world.spawn(Pickup { kind: PickupKind::Bomb, collected: true });
+            app.update();
+        }
+
+        assert!(app.world.get::<EnemyShip>(enemy).unwrap().exploding);
+        assert_eq!(destroyed.read(app.world.resource::<Events<EnemyDestroyed>>()).count(), 1);
+    }
+}
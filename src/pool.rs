@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    hierarchy::despawn_with_children_recursive,
+    prelude::*
+};
+
+use bevy_screen_diagnostics::{Aggregate, ScreenDiagnostics};
+
+use crate::{
+    animation::{Animator, DespawnOnFinish},
+    asteroid::Asteroid,
+    particles::ParticleEmitter,
+    ship::Rocket,
+    weapon::Homing,
+    AngularVelocity,
+    Collider,
+    Velocity
+};
+
+/// Opt-in recycling for rockets and asteroids, the entities spawned and despawned the
+/// most. With pooling on a despawned rocket or asteroid is hidden and has the components
+/// that make it one taken off, the next spawn of the same kind reuses it. On with the
+/// `pooling` feature or `--pool`.
+pub struct PoolPlugin;
+
+impl Plugin for PoolPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(EntityPools::from_args())
+            .register_diagnostic(Diagnostic::new(ROCKET_POOL_SIZE))
+            .register_diagnostic(Diagnostic::new(ROCKET_POOL_FREE))
+            .register_diagnostic(Diagnostic::new(ASTEROID_POOL_SIZE))
+            .register_diagnostic(Diagnostic::new(ASTEROID_POOL_FREE))
+            .add_systems(Startup, setup_pool_diagnostics)
+            .add_systems(Update, measure_pools);
+    }
+}
+
+pub const ROCKET_POOL_SIZE: DiagnosticPath = DiagnosticPath::const_new("pool/rockets");
+pub const ROCKET_POOL_FREE: DiagnosticPath = DiagnosticPath::const_new("pool/rockets_free");
+pub const ASTEROID_POOL_SIZE: DiagnosticPath = DiagnosticPath::const_new("pool/asteroids");
+pub const ASTEROID_POOL_FREE: DiagnosticPath = DiagnosticPath::const_new("pool/asteroids_free");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PoolKind {
+    Rocket,
+    Asteroid,
+}
+
+/// Spawned through `EntityPools::spawn`, goes back to the pool instead of being despawned
+#[derive(Component, Debug)]
+pub struct Pooled(pub PoolKind);
+
+/// Waiting in its pool
+#[derive(Component, Debug)]
+pub struct Inactive;
+
+#[derive(Debug, Default)]
+pub struct Pool {
+    free: Vec<Entity>,
+    /// Every entity the pool owns, in use or not
+    pub size: usize,
+    pub reused: u64,
+}
+
+impl Pool {
+    pub fn free(&self) -> usize {
+        self.free.len()
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct EntityPools {
+    pub enabled: bool,
+    pools: HashMap<PoolKind, Pool>,
+}
+
+impl EntityPools {
+    pub fn new(enabled: bool) -> EntityPools {
+        EntityPools {
+            enabled,
+            pools: HashMap::new(),
+        }
+    }
+
+    pub fn from_args() -> EntityPools {
+        EntityPools::new(cfg!(feature = "pooling") || std::env::args().any(|arg| arg == "--pool"))
+    }
+
+    pub fn pool(&self, kind: PoolKind) -> Option<&Pool> {
+        self.pools.get(&kind)
+    }
+
+    /// Spawns `bundle`, on an entity from the pool when there is one free
+    pub fn spawn<B: Bundle>(&mut self, commands: &mut Commands, kind: PoolKind, bundle: B) -> Entity {
+
+        if !self.enabled {
+            return commands.spawn(bundle).id();
+        }
+
+        let pool = self.pools.entry(kind).or_default();
+
+        while let Some(entity) = pool.free.pop() {
+            // Something despawned it for good while it waited
+            let Some(mut entity_commands) = commands.get_entity(entity) else {
+                pool.size -= 1;
+                continue;
+            };
+
+            // The bundle brings back the visibility and everything recycle took off
+            entity_commands.remove::<Inactive>().insert(bundle);
+            pool.reused += 1;
+
+            return entity;
+        }
+
+        pool.size += 1;
+
+        commands.spawn((bundle, Pooled(kind))).id()
+    }
+
+    fn release(&mut self, kind: PoolKind, entity: Entity) {
+        self.pools.entry(kind).or_default().free.push(entity);
+    }
+}
+
+/// Despawns the entity, or puts it back in its pool if it came from one.
+/// Use it as `commands.entity(entity).add(recycle)`.
+pub fn recycle(entity: Entity, world: &mut World) {
+
+    let Some(mut entity_mut) = world.get_entity_mut(entity) else { return; };
+
+    let Some(&Pooled(kind)) = entity_mut.get::<Pooled>() else {
+        despawn_with_children_recursive(world, entity);
+        return;
+    };
+
+    // Two systems let go of it in the same frame
+    if entity_mut.contains::<Inactive>() { return; }
+
+    // Nothing queries for what's left, so it is out of the grid and every game system
+    match kind {
+        PoolKind::Rocket => {
+            entity_mut.remove::<(Rocket, Collider, Velocity, Animator, Homing, ParticleEmitter)>();
+        },
+        PoolKind::Asteroid => {
+            entity_mut.remove::<(Asteroid, Collider, Velocity, AngularVelocity, Animator, DespawnOnFinish)>();
+        },
+    }
+
+    entity_mut.insert((Inactive, Visibility::Hidden));
+
+    if let Some(mut pools) = world.get_resource_mut::<EntityPools>() {
+        pools.release(kind, entity);
+    }
+}
+
+fn setup_pool_diagnostics(pools: Res<EntityPools>, mut screen_diagnostics: ResMut<ScreenDiagnostics>) {
+
+    if !pools.enabled { return; }
+
+    for (name, path) in [
+        ("pooled rockets", ROCKET_POOL_SIZE),
+        ("free rockets", ROCKET_POOL_FREE),
+        ("pooled asteroids", ASTEROID_POOL_SIZE),
+        ("free asteroids", ASTEROID_POOL_FREE),
+    ] {
+        screen_diagnostics
+            .add(name.to_string(), path)
+            .aggregate(Aggregate::Value)
+            .format(|value| format!("{value:.0}"));
+    }
+}
+
+fn measure_pools(pools: Res<EntityPools>, mut diagnostics: Diagnostics) {
+
+    if !pools.enabled { return; }
+
+    for (kind, size_path, free_path) in [
+        (PoolKind::Rocket, ROCKET_POOL_SIZE, ROCKET_POOL_FREE),
+        (PoolKind::Asteroid, ASTEROID_POOL_SIZE, ASTEROID_POOL_FREE),
+    ] {
+        let (size, free) = pools.pool(kind).map_or((0, 0), |pool| (pool.size, pool.free()));
+
+        diagnostics.add_measurement(&size_path, || size as f64);
+        diagnostics.add_measurement(&free_path, || free as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn_rocket(world: &mut World) -> Entity {
+        let mut pools = world.remove_resource::<EntityPools>().unwrap();
+        let mut queue = bevy::ecs::system::CommandQueue::default();
+
+        let entity = {
+            let mut commands = Commands::new(&mut queue, world);
+            pools.spawn(&mut commands, PoolKind::Rocket, Rocket { hit_target: false, scored_hit: false, damage: 1, pierce: false })
+        };
+
+        queue.apply(world);
+        world.insert_resource(pools);
+
+        entity
+    }
+
+    #[test]
+    fn released_entities_are_reused() {
+        let mut world = World::new();
+        world.insert_resource(EntityPools::new(true));
+
+        let first = spawn_rocket(&mut world);
+        let second = spawn_rocket(&mut world);
+        world.entity_mut(first).insert(Velocity(Vec2::Y));
+
+        recycle(first, &mut world);
+        // Released twice, only pooled once
+        recycle(first, &mut world);
+
+        assert!(world.get::<Rocket>(first).is_none());
+        // Nothing moves it while it waits in the pool
+        assert!(world.get::<Velocity>(first).is_none());
+        assert!(world.get::<Inactive>(first).is_some());
+        assert_eq!(world.get::<Visibility>(first), Some(&Visibility::Hidden));
+
+        let pools = world.resource::<EntityPools>();
+        assert_eq!(pools.pool(PoolKind::Rocket).unwrap().free(), 1);
+
+        let third = spawn_rocket(&mut world);
+
+        assert_eq!(third, first);
+        assert!(world.get::<Rocket>(third).is_some());
+        assert!(world.get::<Inactive>(third).is_none());
+
+        let pool = world.resource::<EntityPools>().pool(PoolKind::Rocket).unwrap();
+        assert_eq!((pool.size, pool.free(), pool.reused), (2, 0, 1));
+
+        assert_ne!(second, third);
+    }
+
+    #[test]
+    fn without_pooling_entities_are_despawned() {
+        let mut world = World::new();
+        world.insert_resource(EntityPools::new(false));
+
+        let rocket = spawn_rocket(&mut world);
+        recycle(rocket, &mut world);
+
+        assert!(world.get_entity(rocket).is_none());
+        assert!(world.resource::<EntityPools>().pool(PoolKind::Rocket).is_none());
+
+        // Entities that were despawned for good while pooled are skipped
+        world.resource_mut::<EntityPools>().enabled = true;
+
+        let rocket = spawn_rocket(&mut world);
+        recycle(rocket, &mut world);
+        world.despawn(rocket);
+
+        let replacement = spawn_rocket(&mut world);
+
+        assert_ne!(replacement, rocket);
+        assert_eq!(world.resource::<EntityPools>().pool(PoolKind::Rocket).unwrap().size, 1);
+    }
+}
@@ -0,0 +1,486 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture
+};
+
+use rand::Rng;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    boss::BossFight,
+    constants::*,
+    loading::Preload,
+    pickup::{spawn_pickup, PickupKind},
+    telemetry::category,
+    AppState,
+    AsteroidDestroyed,
+    DistanceTravelled,
+    GameMode,
+    GameRng,
+    MyGameSet
+};
+
+/// Race mode (`--race`): reach the finish line before the clock runs out, checkpoints
+/// on the way add time. Thrust burns fuel, destroyed asteroids sometimes drop more.
+/// Races are RON files ending in `.race.ron`, see `assets/races/default.race.ron`.
+pub struct RacePlugin;
+
+impl Plugin for RacePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<RaceDefinition>()
+            .register_asset_loader(RaceDefinitionLoader)
+            .add_systems(Startup, load_race)
+            .add_systems(Update, (
+                start_race,
+                run_race.after(start_race),
+            ).run_if(resource_exists::<RaceRun>).in_set(MyGameSet))
+            .add_systems(PostUpdate, drop_fuel
+                .run_if(resource_exists::<Fuel>)
+                .after(crate::explosion_event_listener)
+                .in_set(MyGameSet)
+            );
+    }
+}
+
+pub const DEFAULT_RACE: &str = "races/default.race.ron";
+
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct RaceDefinition {
+    pub name: String,
+    /// Units the camera has to scroll to finish
+    pub finish_distance: f32,
+    /// Seconds on the clock at the start
+    pub time_limit: f32,
+    pub checkpoints: Vec<Checkpoint>,
+    pub fuel: FuelDefinition,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Checkpoint {
+    pub distance: f32,
+    /// Seconds added to the clock when it is passed
+    pub bonus_time: f32,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct FuelDefinition {
+    pub capacity: f32,
+    /// Fuel per second of full thrust
+    pub burn_rate: f32,
+    /// Fuel in one pickup
+    pub pickup_amount: f32,
+    /// Chance for a destroyed asteroid to drop fuel, on top of the usual pickups
+    pub drop_chance: f32,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum RaceDefinitionError {
+    #[error("the finish distance and time limit must be positive")]
+    InvalidGoal,
+    #[error("checkpoint {0} must come after the one before it and before the finish")]
+    UnorderedCheckpoint(usize),
+    #[error("fuel capacity must be positive and the drop chance between 0 and 1")]
+    InvalidFuel,
+}
+
+impl RaceDefinition {
+
+    pub fn validate(&self) -> Result<(), RaceDefinitionError> {
+        if self.finish_distance <= 0.0 || self.time_limit <= 0.0 {
+            return Err(RaceDefinitionError::InvalidGoal);
+        }
+
+        let mut last_distance = 0.0;
+
+        for (index, checkpoint) in self.checkpoints.iter().enumerate() {
+            if checkpoint.distance <= last_distance || checkpoint.distance >= self.finish_distance {
+                return Err(RaceDefinitionError::UnorderedCheckpoint(index));
+            }
+
+            last_distance = checkpoint.distance;
+        }
+
+        if self.fuel.capacity <= 0.0 || !(0.0..=1.0).contains(&self.fuel.drop_chance) {
+            return Err(RaceDefinitionError::InvalidFuel);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RaceEvent {
+    Checkpoint { index: usize, bonus_time: f32 },
+    Finished { time: f32 },
+    OutOfTime { distance: f32 },
+}
+
+/// Where a race stands, kept apart from any ECS state so it can be tested on its own
+#[derive(Debug, Default, Clone)]
+pub struct RaceProgress {
+    pub elapsed: f32,
+    pub time_left: f32,
+    pub distance: f32,
+    pub next_checkpoint: usize,
+    pub over: bool,
+}
+
+impl RaceProgress {
+    pub fn new(definition: &RaceDefinition) -> RaceProgress {
+        RaceProgress {
+            time_left: definition.time_limit,
+            ..default()
+        }
+    }
+
+    /// Moves the race on to `distance` after `delta` seconds
+    pub fn advance(&mut self, definition: &RaceDefinition, delta: f32, distance: f32) -> Vec<RaceEvent> {
+        let mut events = Vec::new();
+
+        if self.over { return events; }
+
+        self.elapsed += delta;
+        self.time_left -= delta;
+        self.distance = distance;
+
+        while let Some(checkpoint) = definition.checkpoints.get(self.next_checkpoint) {
+            if distance < checkpoint.distance { break; }
+
+            self.time_left += checkpoint.bonus_time;
+
+            events.push(RaceEvent::Checkpoint { index: self.next_checkpoint, bonus_time: checkpoint.bonus_time });
+
+            self.next_checkpoint += 1;
+        }
+
+        if distance >= definition.finish_distance {
+            self.over = true;
+            events.push(RaceEvent::Finished { time: self.elapsed });
+        } else if self.time_left <= 0.0 {
+            self.time_left = 0.0;
+            self.over = true;
+            events.push(RaceEvent::OutOfTime { distance });
+        }
+
+        events
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct RaceRun {
+    pub definition: Handle<RaceDefinition>,
+    pub progress: RaceProgress,
+    pub started: bool,
+}
+
+/// How a race ended, kept for the rest of the run
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct RaceResult {
+    pub name: String,
+    pub finish_time: Option<f32>,
+    pub distance: f32,
+    pub checkpoints: usize,
+}
+
+#[derive(Resource, Debug)]
+pub struct Fuel {
+    pub amount: f32,
+    pub capacity: f32,
+    pub burn_rate: f32,
+    pub pickup_amount: f32,
+}
+
+impl Fuel {
+    pub fn new(definition: &FuelDefinition) -> Fuel {
+        Fuel {
+            amount: definition.capacity,
+            capacity: definition.capacity,
+            burn_rate: definition.burn_rate,
+            pickup_amount: definition.pickup_amount,
+        }
+    }
+
+    /// `thrust` goes from 0 (drifting) to 1 (full thrust)
+    pub fn burn(&mut self, thrust: f32, delta: f32) {
+        self.amount = (self.amount - thrust * self.burn_rate * delta).max(0.0);
+    }
+
+    pub fn refill(&mut self) {
+        self.amount = (self.amount + self.pickup_amount).min(self.capacity);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.amount <= 0.0
+    }
+
+    pub fn fraction(&self) -> f32 {
+        self.amount / self.capacity
+    }
+}
+
+#[derive(Component)]
+struct RaceMarker;
+
+fn load_race(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mode: Res<GameMode>,
+    mut preload: ResMut<Preload>,
+) {
+    if *mode != GameMode::Race { return; }
+
+    let definition = asset_server.load(DEFAULT_RACE);
+
+    preload.track(DEFAULT_RACE, &definition);
+
+    commands.insert_resource(RaceRun {
+        definition,
+        progress: RaceProgress::default(),
+        started: false,
+    });
+}
+
+/// Fills the tank and puts the finish line and checkpoints down once the race is loaded
+fn start_race(
+    mut commands: Commands,
+    mut race: ResMut<RaceRun>,
+    definitions: Res<Assets<RaceDefinition>>,
+) {
+
+    if race.started { return; }
+
+    let Some(definition) = definitions.get(&race.definition) else { return; };
+
+    race.progress = RaceProgress::new(definition);
+    race.started = true;
+
+    commands.insert_resource(Fuel::new(&definition.fuel));
+
+    // The camera starts at zero, so a line at a distance is crossed as the camera reaches it
+    let markers = definition.checkpoints.iter()
+        .map(|checkpoint| (checkpoint.distance, Color::rgba(0.3, 0.9, 0.4, 0.6), 2.0))
+        .chain([(definition.finish_distance, Color::rgba(1.0, 1.0, 1.0, 0.8), 6.0)]);
+
+    for (distance, color, thickness) in markers {
+        commands.spawn((
+            SpriteBundle {
+                transform: Transform::from_xyz(0.0, distance, 0.5),
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::new(WINDOW_WIDTH, thickness)),
+                    ..default()
+                },
+                ..default()
+            },
+            RaceMarker
+        ));
+    }
+}
+
+fn run_race(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut race: ResMut<RaceRun>,
+    definitions: Res<Assets<RaceDefinition>>,
+    distance: Res<DistanceTravelled>,
+    boss_fight: Res<BossFight>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+) {
+
+    // The clock stops while a boss holds the scrolling
+    if !race.started || boss_fight.active() { return; }
+
+    let Some(definition) = definitions.get(&race.definition) else { return; };
+
+    let events = race.progress.advance(definition, time.delta_seconds(), **distance);
+
+    for event in events {
+        match event {
+            RaceEvent::Checkpoint { index, bonus_time } => {
+                info!(target: category::RACE, checkpoint = index + 1, bonus_time, "checkpoint reached");
+            },
+            RaceEvent::Finished { time } => {
+                info!(target: category::RACE, race = %definition.name, time, "race finished");
+
+                commands.insert_resource(RaceResult {
+                    name: definition.name.clone(),
+                    finish_time: Some(time),
+                    distance: race.progress.distance,
+                    checkpoints: race.progress.next_checkpoint,
+                });
+
+                next_app_state.set(AppState::GameOverMenu);
+            },
+            RaceEvent::OutOfTime { distance } => {
+                info!(target: category::RACE, distance, "out of time");
+
+                commands.insert_resource(RaceResult {
+                    name: definition.name.clone(),
+                    finish_time: None,
+                    distance,
+                    checkpoints: race.progress.next_checkpoint,
+                });
+
+                next_app_state.set(AppState::GameOverMenu);
+            },
+        }
+    }
+}
+
+fn drop_fuel(
+    mut commands: Commands,
+    mut asteroid_explosion: EventReader<AsteroidDestroyed>,
+    race: Res<RaceRun>,
+    definitions: Res<Assets<RaceDefinition>>,
+    mut rng: ResMut<GameRng>,
+) {
+
+    let Some(definition) = definitions.get(&race.definition) else { return; };
+
+    for AsteroidDestroyed { position, .. } in asteroid_explosion.read() {
+        if rng.0.gen_bool(definition.fuel.drop_chance as f64) {
+            spawn_pickup(&mut commands, PickupKind::Fuel, position.truncate());
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RaceDefinitionLoader;
+
+#[derive(Error, Debug)]
+pub enum RaceDefinitionLoaderError {
+    #[error("could not read race: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse race: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("invalid race: {0}")]
+    Invalid(#[from] RaceDefinitionError),
+}
+
+impl AssetLoader for RaceDefinitionLoader {
+    type Asset = RaceDefinition;
+    type Settings = ();
+    type Error = RaceDefinitionLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<RaceDefinition, RaceDefinitionLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            let definition: RaceDefinition = ron::de::from_bytes(&bytes)?;
+            definition.validate()?;
+
+            Ok(definition)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["race.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_race() -> RaceDefinition {
+        let source = std::fs::read_to_string(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/assets/", "races/default.race.ron")
+        ).unwrap();
+
+        ron::from_str(&source).unwrap()
+    }
+
+    /// Runs the race at 64 ticks a second, scrolling at `speed`
+    fn run(definition: &RaceDefinition, speed: f32, seconds: f32) -> (RaceProgress, Vec<RaceEvent>) {
+        let mut progress = RaceProgress::new(definition);
+        let mut events = Vec::new();
+
+        let delta = 1.0 / 64.0;
+
+        for tick in 1..=(seconds * 64.0) as u32 {
+            events.extend(progress.advance(definition, delta, tick as f32 * delta * speed));
+        }
+
+        (progress, events)
+    }
+
+    #[test]
+    fn default_race_is_valid_and_finishable() {
+        let definition = default_race();
+
+        assert_eq!(definition.validate(), Ok(()));
+
+        // Cruising the whole way has to make it with the checkpoint bonuses
+        let cruise_time = definition.finish_distance / KINEMATIC_OBJECTS_SPEED;
+        let (progress, events) = run(&definition, KINEMATIC_OBJECTS_SPEED, cruise_time + 1.0);
+
+        assert!(progress.over);
+        assert_eq!(progress.next_checkpoint, definition.checkpoints.len());
+        assert!(matches!(events.last(), Some(RaceEvent::Finished { time }) if (time - cruise_time).abs() < 0.05));
+    }
+
+    #[test]
+    fn checkpoints_add_time() {
+        let definition = RaceDefinition {
+            name: "test".to_string(),
+            finish_distance: 1000.0,
+            time_limit: 2.0,
+            checkpoints: vec![
+                Checkpoint { distance: 100.0, bonus_time: 2.0 },
+                Checkpoint { distance: 200.0, bonus_time: 3.0 },
+            ],
+            fuel: FuelDefinition { capacity: 10.0, burn_rate: 1.0, pickup_amount: 5.0, drop_chance: 0.0 },
+        };
+
+        let (progress, events) = run(&definition, 100.0, 10.0);
+
+        assert_eq!(events[0], RaceEvent::Checkpoint { index: 0, bonus_time: 2.0 });
+        assert_eq!(events[1], RaceEvent::Checkpoint { index: 1, bonus_time: 3.0 });
+        assert!(matches!(events[2], RaceEvent::OutOfTime { distance } if (distance - 700.0).abs() < 2.0));
+        assert_eq!(events.len(), 3);
+        assert_eq!(progress.time_left, 0.0);
+    }
+
+    #[test]
+    fn invalid_races_are_rejected() {
+        let mut definition = default_race();
+        definition.checkpoints.reverse();
+        assert_eq!(definition.validate(), Err(RaceDefinitionError::UnorderedCheckpoint(1)));
+
+        let mut definition = default_race();
+        definition.checkpoints.push(Checkpoint { distance: definition.finish_distance, bonus_time: 1.0 });
+        assert!(matches!(definition.validate(), Err(RaceDefinitionError::UnorderedCheckpoint(_))));
+
+        let mut definition = default_race();
+        definition.time_limit = 0.0;
+        assert_eq!(definition.validate(), Err(RaceDefinitionError::InvalidGoal));
+    }
+
+    #[test]
+    fn fuel_burns_with_thrust() {
+        let mut fuel = Fuel::new(&FuelDefinition { capacity: 10.0, burn_rate: 2.0, pickup_amount: 4.0, drop_chance: 0.5 });
+
+        fuel.burn(0.0, 1.0);
+        assert_eq!(fuel.amount, 10.0);
+
+        fuel.burn(0.5, 2.0);
+        assert_eq!(fuel.amount, 8.0);
+
+        fuel.burn(1.0, 10.0);
+        assert!(fuel.is_empty());
+
+        fuel.refill();
+        fuel.refill();
+        fuel.refill();
+        assert_eq!(fuel.fraction(), 1.0);
+    }
+}
@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+
+use crate::{
+    boss::BossFight,
+    constants::*,
+    touch_controls::VirtualControls
+};
+
+/// How fast the camera, the walls and everything that keeps pace with them scroll up.
+/// Holding up speeds the whole run up, holding down brakes, letting go eases back to
+/// cruising speed. Boss fights hold it at a standstill.
+#[derive(Resource, Debug)]
+pub struct ScrollSpeed {
+    pub current: f32,
+}
+
+impl Default for ScrollSpeed {
+    fn default() -> Self {
+        ScrollSpeed { current: KINEMATIC_OBJECTS_SPEED }
+    }
+}
+
+impl ScrollSpeed {
+    /// `throttle` goes from -1 (full brake) to 1 (full boost)
+    pub fn update(&mut self, throttle: f32, delta: f32) {
+        let throttle = throttle.clamp(-1.0, 1.0);
+
+        let (target, rate) = if throttle > 0.0 {
+            (SCROLL_SPEED_MAX, SCROLL_ACCELERATION * throttle)
+        } else if throttle < 0.0 {
+            (SCROLL_SPEED_MIN, SCROLL_BRAKING * -throttle)
+        } else if self.current < KINEMATIC_OBJECTS_SPEED {
+            // Getting going again after a boss fight is quicker than coasting down
+            (KINEMATIC_OBJECTS_SPEED, SCROLL_ACCELERATION)
+        } else {
+            (KINEMATIC_OBJECTS_SPEED, SCROLL_RETURN)
+        };
+
+        let step = rate * delta;
+
+        self.current = if self.current < target {
+            (self.current + step).min(target)
+        } else {
+            (self.current - step).max(target)
+        };
+    }
+
+    /// Speed relative to cruising, 1.0 at the default speed
+    pub fn factor(&self) -> f32 {
+        self.current / KINEMATIC_OBJECTS_SPEED
+    }
+}
+
+pub fn update_scroll_speed(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    virtual_controls: Res<VirtualControls>,
+    boss_fight: Res<BossFight>,
+    time: Res<Time<Fixed>>,
+    mut scroll_speed: ResMut<ScrollSpeed>,
+) {
+
+    if boss_fight.active() {
+        scroll_speed.current = 0.0;
+        return;
+    }
+
+    let mut throttle = virtual_controls.direction.y;
+
+    if keyboard_input.pressed(KeyCode::KeyW) || keyboard_input.pressed(KeyCode::ArrowUp) {
+        throttle += 1.0;
+    }
+
+    if keyboard_input.pressed(KeyCode::KeyS) || keyboard_input.pressed(KeyCode::ArrowDown) {
+        throttle -= 1.0;
+    }
+
+    scroll_speed.update(throttle, time.delta_seconds());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hold(scroll_speed: &mut ScrollSpeed, throttle: f32, seconds: f32) {
+        for _ in 0..(seconds * 64.0) as u32 {
+            scroll_speed.update(throttle, 1.0 / 64.0);
+        }
+    }
+
+    #[test]
+    fn throttle_stays_within_limits() {
+        let mut scroll_speed = ScrollSpeed::default();
+
+        hold(&mut scroll_speed, 1.0, 1.0);
+        assert!((scroll_speed.current - (KINEMATIC_OBJECTS_SPEED + SCROLL_ACCELERATION)).abs() < 0.1);
+
+        hold(&mut scroll_speed, 1.0, 60.0);
+        assert_eq!(scroll_speed.current, SCROLL_SPEED_MAX);
+
+        hold(&mut scroll_speed, -1.0, 60.0);
+        assert_eq!(scroll_speed.current, SCROLL_SPEED_MIN);
+        assert!(scroll_speed.factor() < 1.0);
+    }
+
+    #[test]
+    fn eases_back_to_cruising() {
+        let mut scroll_speed = ScrollSpeed { current: SCROLL_SPEED_MAX };
+
+        hold(&mut scroll_speed, 0.0, 1.0);
+        assert!(scroll_speed.current < SCROLL_SPEED_MAX && scroll_speed.current > KINEMATIC_OBJECTS_SPEED);
+
+        hold(&mut scroll_speed, 0.0, 60.0);
+        assert_eq!(scroll_speed.factor(), 1.0);
+
+        // Picking up again after a boss fight stopped everything
+        let mut scroll_speed = ScrollSpeed { current: 0.0 };
+
+        hold(&mut scroll_speed, 0.0, KINEMATIC_OBJECTS_SPEED / SCROLL_ACCELERATION + 0.1);
+        assert_eq!(scroll_speed.current, KINEMATIC_OBJECTS_SPEED);
+    }
+}
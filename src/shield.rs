@@ -0,0 +1,249 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle}
+};
+
+use crate::{
+    constants::*,
+    game_assets::GameAssets,
+    ship::{Health, Ship},
+    MyGameSet
+};
+
+/// The regenerating shield on top of the ship's hull. Hits go to the shield first
+/// (see `damage_ship`), it comes back a point at a time after a quiet spell.
+pub struct ShieldPlugin;
+
+impl Plugin for ShieldPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<ShieldBroken>()
+            .add_systems(Update, (
+                attach_shield_bubble,
+                (
+                    regenerate_shield,
+                    update_shield_bubble.after(regenerate_shield),
+                    shield_broken.after(regenerate_shield),
+                ).in_set(MyGameSet),
+            ));
+    }
+}
+
+pub const SHIELD_BREAK_SOUND: &str = "sounds/shield_break.wav";
+
+#[derive(Event)]
+pub struct ShieldBroken;
+
+#[derive(Debug)]
+pub struct Shield {
+    pub points: Health,
+    regen_delay: Timer,
+    regen_interval: Timer,
+    grace: Timer,
+    // Set when a hit takes the last point, picked up by shield_broken
+    pub just_broke: bool,
+}
+
+/// A once timer that has already run out
+fn finished_timer(seconds: f32) -> Timer {
+    let mut timer = Timer::from_seconds(seconds, TimerMode::Once);
+    timer.tick(Duration::from_secs_f32(seconds));
+
+    timer
+}
+
+impl Shield {
+    pub fn new(max: u32) -> Shield {
+        Shield {
+            points: Health::full(max),
+            regen_delay: finished_timer(SHIELD_REGEN_DELAY),
+            regen_interval: Timer::from_seconds(SHIELD_REGEN_INTERVAL, TimerMode::Repeating),
+            grace: finished_timer(SHIELD_GRACE_PERIOD),
+            just_broke: false,
+        }
+    }
+
+    /// Takes what it can of a hit and returns the damage left over for the hull
+    pub fn absorb(&mut self, damage: u32) -> u32 {
+
+        // Any hit puts regeneration off, even one the shield couldn't take
+        self.regen_delay.reset();
+        self.regen_interval.reset();
+
+        if self.points.is_empty() { return damage; }
+
+        if !self.grace.finished() { return 0; }
+
+        let absorbed = damage.min(self.points.current);
+
+        self.points.take(absorbed);
+        self.grace.reset();
+
+        if self.points.is_empty() {
+            self.just_broke = true;
+        }
+
+        damage - absorbed
+    }
+
+    pub fn regenerate(&mut self, delta: Duration) {
+        self.grace.tick(delta);
+
+        if self.points.is_full() { return; }
+
+        if !self.regen_delay.tick(delta).finished() { return; }
+
+        let regained = self.regen_interval.tick(delta).times_finished_this_tick();
+
+        self.points.heal(regained);
+    }
+
+    pub fn refill(&mut self) {
+        self.points.current = self.points.max;
+    }
+}
+
+#[derive(Component)]
+struct ShieldBubble;
+
+fn attach_shield_bubble(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    ship_query: Query<Entity, Added<Ship>>,
+) {
+    for ship_entity in ship_query.iter() {
+        let bubble = commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(Circle::new(SHIELD_BUBBLE_RADIUS))),
+                material: materials.add(SHIELD_COLOR),
+                // Over the ship
+                transform: Transform::from_xyz(0.0, 0.0, 0.1),
+                ..default()
+            },
+            ShieldBubble
+        )).id();
+
+        commands.entity(ship_entity).add_child(bubble);
+    }
+}
+
+fn regenerate_shield(
+    time: Res<Time>,
+    mut ship_query: Query<&mut Ship>,
+) {
+    for mut ship in ship_query.iter_mut() {
+        if ship.is_destroyed() { continue; }
+
+        ship.shield.regenerate(time.delta());
+    }
+}
+
+/// The bubble fades with the shield and disappears once it is gone
+fn update_shield_bubble(
+    ship_query: Query<&Ship, Changed<Ship>>,
+    mut bubble_query: Query<(&mut Visibility, &Handle<ColorMaterial>), With<ShieldBubble>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let Ok(ship) = ship_query.get_single() else { return; };
+
+    for (mut visibility, material) in bubble_query.iter_mut() {
+        *visibility = if ship.shield.points.is_empty() { Visibility::Hidden } else { Visibility::Inherited };
+
+        let color = SHIELD_COLOR.with_a(SHIELD_COLOR.a() * ship.shield.points.fraction());
+
+        // Regeneration touches the ship every frame, only touch the material when it shows
+        if materials.get(material).is_some_and(|material| material.color != color) {
+            if let Some(material) = materials.get_mut(material) {
+                material.color = color;
+            }
+        }
+    }
+}
+
+fn shield_broken(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    mut ship_query: Query<&mut Ship>,
+    mut shield_events: EventWriter<ShieldBroken>,
+) {
+    for mut ship in ship_query.iter_mut() {
+        if !ship.shield.just_broke { continue; }
+
+        ship.shield.just_broke = false;
+
+        shield_events.send(ShieldBroken);
+
+        commands.spawn(AudioBundle {
+            source: game_assets.shield_break_sound.clone(),
+            settings: PlaybackSettings::DESPAWN
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(seconds: f32) -> Duration {
+        Duration::from_secs_f32(seconds)
+    }
+
+    #[test]
+    fn absorbs_before_the_hull() {
+        let mut shield = Shield::new(2);
+
+        assert_eq!(shield.absorb(1), 0);
+        assert_eq!(shield.points.current, 1);
+
+        // Still in the grace period
+        assert_eq!(shield.absorb(1), 0);
+        assert_eq!(shield.points.current, 1);
+
+        shield.regenerate(seconds(SHIELD_GRACE_PERIOD));
+
+        assert_eq!(shield.absorb(3), 2);
+        assert!(shield.points.is_empty());
+        assert!(shield.just_broke);
+
+        // Once it is down everything goes through
+        assert_eq!(shield.absorb(1), 1);
+    }
+
+    #[test]
+    fn regenerates_after_a_delay() {
+        let mut shield = Shield::new(2);
+
+        shield.absorb(2);
+
+        shield.regenerate(seconds(SHIELD_REGEN_DELAY - 0.1));
+        assert_eq!(shield.points.current, 0);
+
+        // A hit during the delay starts it over
+        shield.absorb(1);
+        shield.regenerate(seconds(SHIELD_REGEN_DELAY - 0.1));
+        assert_eq!(shield.points.current, 0);
+
+        shield.regenerate(seconds(0.2));
+        shield.regenerate(seconds(SHIELD_REGEN_INTERVAL));
+        assert_eq!(shield.points.current, 1);
+
+        shield.regenerate(seconds(SHIELD_REGEN_INTERVAL * 5.0));
+        assert_eq!(shield.points.current, 2);
+    }
+
+    #[test]
+    fn capacity_can_grow() {
+        let mut shield = Shield::new(2);
+
+        shield.points.max += 2;
+        shield.regenerate(seconds(SHIELD_REGEN_INTERVAL * 2.0));
+        assert_eq!(shield.points.current, 4);
+
+        shield.absorb(3);
+        shield.refill();
+        assert!(shield.points.is_full());
+    }
+}
@@ -8,112 +8,125 @@ use crate::{
     ColliderShape, 
     CollidableComponentNames,
     constants::*,
-    AnimationIndices,
-    AnimationTimer,
-    PlayAnimation,
-    AnimationProperties
+    asteroid::AsteroidHitbox,
+    weapon::{ProjectileSpec, Weapon},
+    shield::Shield,
+    animation::{Animator, RemoveComponentOnFinish, ReturnToClip},
+    game_assets::GameAssets,
+    Velocity
 };
 
-#[derive(PartialEq)]
-pub enum ShipHealth {
-    Full,
-    Damaged,
-    VeryDamaged,
-    Empty
+/// Hit points that upgrades can add capacity to, used for the hull and the shield
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+}
+
+impl Health {
+    pub fn full(max: u32) -> Health {
+        Health { current: max, max }
+    }
+
+    pub fn take(&mut self, amount: u32) {
+        self.current = self.current.saturating_sub(amount);
+    }
+
+    pub fn heal(&mut self, amount: u32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.current == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.current >= self.max
+    }
+
+    pub fn fraction(&self) -> f32 {
+        if self.max == 0 { return 0.0; }
+
+        self.current as f32 / self.max as f32
+    }
 }
 
 #[derive(Component)]
 pub struct Ship {
-    pub health: ShipHealth,
+    pub hull: Health,
+    pub shield: Shield,
+    /// Blinking after a hit, no damage and no firing until the clip ends
     pub invulnerable: bool,
-    pub cooldown_length: f32,
-    pub cooldown_time_left: f32,
+    /// Toggled from the console, no damage but the ship plays as normal otherwise
+    pub god_mode: bool,
+    // How hard the player is steering, 0 to 1, set by ship_movement
+    pub thrust: f32,
 }
 
 impl Ship {
-    pub fn fire_rocket(
-        &mut self, 
-        commands: &mut Commands,
-        ship_transform: &Transform,
-        asset_server: Res<AssetServer>, 
-        mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
-    ) {
-
-        // Means you can now fire the rocket
-        // Just for precaution right now because
-        // when i call fire_rocket I am making that check + incrementing anyway
-        if self.cooldown_time_left == 0.0 {
-            let rocket_bundle = RocketBundle::new(
-                &asset_server,
-                &mut texture_atlas_layouts,
-                &ship_transform
-            );
-
-            self.cooldown_time_left = self.cooldown_length;
-
-            commands.spawn(rocket_bundle).insert(PlayAnimation);
-        }
-        
+    /// True while nothing that hits the ship should hurt it
+    pub fn ignores_hits(&self) -> bool {
+        self.invulnerable || self.god_mode
     }
-    pub fn take_damage(&mut self) -> ShipHealth  {
 
-        match self.health {
-            ShipHealth::Full => {
-                self.health = ShipHealth::Damaged;
-                ShipHealth::Damaged
-            },
-            ShipHealth::Damaged => {
-                self.health = ShipHealth::VeryDamaged;
-                ShipHealth::VeryDamaged
-            },
-            ShipHealth::VeryDamaged => {
-                self.health = ShipHealth::Empty;
-                ShipHealth::Empty
-                
-            },
-            ShipHealth::Empty => {
-                self.health = ShipHealth::Empty;
-                ShipHealth::Empty
-            },
-        }
+    /// Damages the hull, the shield is dealt with before this. Returns true once the ship is destroyed
+    pub fn take_damage(&mut self, damage: u32) -> bool {
+        self.hull.take(damage);
 
+        self.hull.is_empty()
+    }
 
+    pub fn is_destroyed(&self) -> bool {
+        self.hull.is_empty()
+    }
 
+    /// Clip showing how damaged the hull is
+    pub fn idle_clip(&self) -> &'static str {
+        match self.hull.fraction() {
+            fraction if fraction >= 1.0 => "ship_full",
+            fraction if fraction >= 0.5 => "ship_damaged",
+            _ => "ship_very_damaged"
+        }
+    }
+
+    /// Blinking version of `idle_clip`, played while invulnerable after a hit
+    pub fn hit_clip(&self) -> &'static str {
+        match self.hull.fraction() {
+            fraction if fraction >= 1.0 => "ship_hit_full",
+            fraction if fraction >= 0.5 => "ship_hit_damaged",
+            _ => "ship_hit_very_damaged"
+        }
     }
 }
 
+/// On the ship while it blinks after a hit, taken off when the blink finishes
+#[derive(Component)]
+pub struct Invulnerable;
+
 #[derive(Bundle)]
 pub struct ShipBundle {
     ship: Ship,
     sprite_bundle: SpriteSheetBundle,
     collider: Collider,
-    animation: AnimationProperties
+    animator: Animator,
+    return_to_clip: ReturnToClip,
+    end_invulnerability: RemoveComponentOnFinish<Invulnerable>,
+    weapon: Weapon
 }
 
 impl ShipBundle {
 
     pub fn new(
-        ship_texture: Handle<Image>,
-        texture_atlas_layouts: &mut ResMut<Assets<TextureAtlasLayout>>,
-        cooldown_length: f32
+        game_assets: &GameAssets,
     ) -> ShipBundle {
 
-        let ship_layout = TextureAtlasLayout::from_grid(Vec2::new(48.0, 48.0), 5, 1, None, None);
-
-        let texture_atlas_layout = texture_atlas_layouts.add(ship_layout);
-
-        let animation_indices = AnimationIndices {
-            first: 1,
-            last: 4
-        };
-
-
         ShipBundle {
             ship: Ship {
-                health: ShipHealth::Full,
+                hull: Health::full(SHIP_HULL_POINTS),
+                shield: Shield::new(SHIP_SHIELD_POINTS),
                 invulnerable: false,
-                cooldown_length,
-                cooldown_time_left: cooldown_length
+                god_mode: false,
+                thrust: 0.0,
             },
             sprite_bundle: SpriteSheetBundle {
                 transform: Transform {
@@ -125,20 +138,21 @@ impl ShipBundle {
                     ..default()
                 }, 
                 atlas: TextureAtlas {
-                    layout: texture_atlas_layout,
+                    layout: game_assets.ship_layout.clone(),
                     index: 1
                 },
-                texture: ship_texture,
+                texture: game_assets.ship_texture.clone(),
                 ..default()
             },
             collider: Collider {
                 name: CollidableComponentNames::Ship,
                 shape: ColliderShape::Circle
             },
-            animation: AnimationProperties {
-                timer: AnimationTimer(Timer::from_seconds(2.0, TimerMode::Repeating)),
-                indices: animation_indices,
-            }
+            animator: Animator::new("ship_full"),
+            // Kept in step with the hull by update_ship_clip
+            return_to_clip: ReturnToClip("ship_full".to_string()),
+            end_invulnerability: RemoveComponentOnFinish::default(),
+            weapon: Weapon::default()
         }
     }
 }
@@ -148,7 +162,12 @@ impl ShipBundle {
 
 #[derive(Component, Debug)]
 pub struct Rocket {
-    pub hit_target: bool
+    pub hit_target: bool,
+    // Counted once towards accuracy, however many things a laser goes through
+    pub scored_hit: bool,
+    pub damage: u32,
+    // Lasers keep going after a hit
+    pub pierce: bool,
 }
 
 impl Rocket {
@@ -156,10 +175,16 @@ impl Rocket {
 
         let despawn_threshold: f32 = TOP_WALL + WALL_THICKNESS / 2.0 + SHIP_SPEC.y / 2.0 + SHIP_PADDING + camera_transform.translation.y;
 
-        rocket_transform.translation.y > despawn_threshold 
+        // Spread and homing shots can leave through the sides or the bottom too
+        let lower_threshold: f32 = BOTTOM_WALL - SHIP_SPEC.y / 2.0 + camera_transform.translation.y;
+        let side_threshold: f32 = RIGHT_WALL + SHIP_SPEC.x / 2.0;
+
+        rocket_transform.translation.y > despawn_threshold ||
+        rocket_transform.translation.y < lower_threshold ||
+        rocket_transform.translation.x.abs() > side_threshold
     }
 
-    pub fn check_collision(
+    pub(crate) fn check_collision(
         &mut self, 
         rocket_transform: &Transform, 
         other_transform: &Transform, 
@@ -171,14 +196,22 @@ impl Rocket {
             rocket_transform.scale.truncate() / 2.0
         );
 
+        // Boss parts are drawn at their hit box size, same as the rocket
+        if *other_name == CollidableComponentNames::BossPart {
+            return Aabb2d::new(other_transform.translation.truncate(), other_transform.scale.truncate() / 2.0)
+                .intersects(&rocket_rectangle);
+        }
+
+        if *other_name == CollidableComponentNames::EnemyShip {
+            return BoundingCircle::new(other_transform.translation.truncate(), ENEMY_SHIP_RADIUS)
+                .intersects(&rocket_rectangle);
+        }
+
         if *other_name == CollidableComponentNames::Asteroid {
 
-            let asteroid_circle = BoundingCircle::new(
-                other_transform.translation.truncate(),
-                ASTEROID_SCALED_RADIUS
-            );
+            let asteroid_hitbox = AsteroidHitbox::from_transform(other_transform);
 
-            match rocket_rectangle.intersects(&asteroid_circle) {
+            match asteroid_hitbox.intersects_aabb(&rocket_rectangle) {
                 true => {
 
                     
@@ -196,44 +229,37 @@ impl Rocket {
 #[derive(Bundle)]
 pub struct RocketBundle {
     rocket: Rocket,
-    animation: AnimationProperties,
+    animator: Animator,
     sprite_bundle: SpriteSheetBundle,
     collider: Collider,
+    velocity: Velocity,
 }
 
 impl RocketBundle {
 
     pub fn new(
-        asset_server: &Res<AssetServer>, 
-        texture_atlas_layouts: &mut ResMut<Assets<TextureAtlasLayout>>, 
-        spawn_location: &Transform
+        game_assets: &GameAssets,
+        projectile: &ProjectileSpec,
+        spawn_location: Vec3,
+        direction: Vec2
     ) -> RocketBundle {
-        let rocket_texture = asset_server.load("weapons/rocket_sprites_3.png");
-
-        let layout = TextureAtlasLayout::from_grid(Vec2::new(32.0, 32.0), 3, 1, None, None);
-
-        let texture_atlas_layout = texture_atlas_layouts.add(layout);
-
-        let animation_indices = AnimationIndices {
-            first: 0,
-            last: 2
-        };
-
         RocketBundle {
             sprite_bundle: SpriteSheetBundle {
                 transform: Transform {
-                    translation: spawn_location.translation.clone(),
-                    scale: ROCKET_APPLIED_SCALE,
-                    ..default()
+                    translation: spawn_location,
+                    // The sprite points up
+                    rotation: Quat::from_rotation_arc_2d(Vec2::Y, direction),
+                    scale: projectile.scale,
                 },
                 sprite: Sprite {
+                    color: projectile.color,
                     ..default()
                 }, 
                 atlas: TextureAtlas {
-                    layout: texture_atlas_layout,
+                    layout: game_assets.rocket_layout.clone(),
                     index: 0
                 },
-                texture: rocket_texture,
+                texture: game_assets.projectile_texture(projectile.texture),
                 ..default()
             },
             collider: Collider {
@@ -241,12 +267,13 @@ impl RocketBundle {
                 shape: ColliderShape::Rectangle
             },
             rocket: Rocket {
-                hit_target: false
+                hit_target: false,
+                scored_hit: false,
+                damage: projectile.damage,
+                pierce: projectile.pierce,
             },
-            animation: AnimationProperties {
-                timer: AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
-                indices: animation_indices
-            }
+            animator: Animator::new("rocket_flight"),
+            velocity: Velocity(direction * projectile.speed),
         }
     }
 }
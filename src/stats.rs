@@ -0,0 +1,536 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use bevy::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    asteroid::AsteroidType,
+    race::RaceResult,
+    ship::{Rocket, Ship},
+    telemetry::category,
+    AppState,
+    AsteroidDestroyed,
+    DistanceTravelled,
+    MyGameSet,
+    ScoreTracker
+};
+
+/// Keeps score of the run, shows it on the game over screen and adds it to the
+/// lifetime stats in `stats.ron` (or `--stats=<path>`). Lifetime stats aren't kept on the web.
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<RunStats>()
+            .insert_resource(LifetimeStats::from_args())
+            .add_systems(Update, (
+                count_rockets_fired,
+                tick_run_clock,
+            ).in_set(MyGameSet))
+            .add_systems(PostUpdate,
+                count_destroyed_asteroids.after(crate::explosion_event_listener).in_set(MyGameSet)
+            )
+            // Last, the hit that ends the run comes in after the state has stopped MyGameSet
+            .add_systems(Last, track_ship_damage.run_if(in_state(AppState::InGame)))
+            .add_systems(OnEnter(AppState::GameOverMenu), (end_run, spawn_game_over_screen).chain())
+            .add_systems(OnExit(AppState::GameOverMenu), despawn_game_over_screen);
+    }
+}
+
+// How often the score is added to the timeline
+const SCORE_SAMPLE_SECONDS: f32 = 1.0;
+
+// Bars in the score chart on the game over screen
+const SCORE_CHART_BARS: usize = 40;
+
+#[derive(Resource, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RunStats {
+    pub rockets_fired: u32,
+    /// Rockets that hit something at least once, a piercing rocket counts once however much it goes through
+    pub rocket_hits: u32,
+    pub asteroids_destroyed: HashMap<AsteroidType, u32>,
+    /// Shield and hull points lost
+    pub damage_taken: u32,
+    pub time_alive: f32,
+    pub distance: f32,
+    /// Smallest gap between the ship and an asteroid, `None` if none came near
+    pub closest_call: Option<f32>,
+    /// Seconds into the run and the score then
+    pub score_timeline: Vec<(f32, u64)>,
+    #[serde(skip)]
+    last_health: Option<(u32, u32)>,
+    #[serde(skip)]
+    sample_timer: f32,
+}
+
+impl RunStats {
+    pub fn accuracy(&self) -> Option<f32> {
+        if self.rockets_fired == 0 { return None; }
+
+        Some(self.rocket_hits as f32 / self.rockets_fired as f32)
+    }
+
+    /// Called with the gap between the ship and an asteroid it is checked against
+    pub fn note_gap(&mut self, gap: f32) {
+        let gap = gap.max(0.0);
+
+        self.closest_call = Some(self.closest_call.map_or(gap, |closest| closest.min(gap)));
+    }
+
+    /// Anything lost since last time counts as damage, regenerating doesn't take it back
+    pub fn note_ship_health(&mut self, hull: u32, shield: u32) {
+        if let Some((last_hull, last_shield)) = self.last_health {
+            self.damage_taken += last_hull.saturating_sub(hull) + last_shield.saturating_sub(shield);
+        }
+
+        self.last_health = Some((hull, shield));
+    }
+
+    pub fn asteroids_total(&self) -> u32 {
+        self.asteroids_destroyed.values().sum()
+    }
+
+    pub fn summary_lines(&self) -> Vec<String> {
+        let accuracy = self.accuracy()
+            .map_or("-".to_string(), |accuracy| format!("{:.0}%", accuracy * 100.0));
+
+        let closest_call = self.closest_call
+            .map_or("-".to_string(), |gap| format!("{gap:.0}px"));
+
+        let asteroids = [AsteroidType::Small, AsteroidType::Medium, AsteroidType::Large, AsteroidType::Armored]
+            .iter()
+            .map(|asteroid_type| format!(
+                "{} {asteroid_type:?}",
+                self.asteroids_destroyed.get(asteroid_type).copied().unwrap_or_default()
+            ))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        vec![
+            format!("Time alive: {:.1}s", self.time_alive),
+            format!("Distance: {:.0}", self.distance),
+            format!("Rockets fired: {}, accuracy {accuracy}", self.rockets_fired),
+            format!("Asteroids destroyed: {} ({asteroids})", self.asteroids_total()),
+            format!("Damage taken: {}", self.damage_taken),
+            format!("Closest call: {closest_call}"),
+        ]
+    }
+}
+
+/// Bar heights from 0 to 1 for the score timeline, at most `bars` of them. Each bar
+/// is the best score in its stretch of the run.
+pub fn timeline_bars(timeline: &[(f32, u64)], bars: usize) -> Vec<f32> {
+    if timeline.is_empty() || bars == 0 { return Vec::new(); }
+
+    let best = timeline.iter().map(|(_, score)| *score).max().unwrap_or_default().max(1) as f32;
+    let chunk_size = timeline.len().div_ceil(bars);
+
+    timeline
+        .chunks(chunk_size)
+        .map(|chunk| chunk.iter().map(|(_, score)| *score).max().unwrap_or_default() as f32 / best)
+        .collect()
+}
+
+/// Every run added up, saved between sessions
+#[derive(Resource, Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LifetimeStats {
+    pub runs: u32,
+    pub best_score: u64,
+    pub longest_run: f32,
+    pub closest_call: Option<f32>,
+    pub rockets_fired: u32,
+    pub rocket_hits: u32,
+    pub asteroids_destroyed: u32,
+    pub damage_taken: u32,
+    pub time_alive: f32,
+    pub distance: f32,
+    /// Nothing is saved when `None`
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+impl LifetimeStats {
+    pub fn from_args() -> LifetimeStats {
+        if cfg!(target_arch = "wasm32") {
+            return LifetimeStats::default();
+        }
+
+        let path = std::env::args()
+            .find_map(|arg| arg.strip_prefix("--stats=").map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from("stats.ron"));
+
+        LifetimeStats {
+            path: Some(path),
+            ..default()
+        }
+    }
+
+    pub fn add_run(&mut self, run: &RunStats, score: u64) {
+        self.runs += 1;
+        self.best_score = self.best_score.max(score);
+        self.longest_run = self.longest_run.max(run.time_alive);
+        self.closest_call = match (self.closest_call, run.closest_call) {
+            (Some(closest), Some(gap)) => Some(closest.min(gap)),
+            (closest, gap) => closest.or(gap),
+        };
+        self.rockets_fired += run.rockets_fired;
+        self.rocket_hits += run.rocket_hits;
+        self.asteroids_destroyed += run.asteroids_total();
+        self.damage_taken += run.damage_taken;
+        self.time_alive += run.time_alive;
+        self.distance += run.distance;
+    }
+
+    pub fn summary_line(&self) -> String {
+        format!(
+            "{} runs, best score {}, longest run {:.1}s, {} asteroids destroyed",
+            self.runs, self.best_score, self.longest_run, self.asteroids_destroyed
+        )
+    }
+
+    /// Loads what was saved at `path`, keeping `path` for saving it again
+    fn load(&mut self) -> Result<(), StatsError> {
+        let Some(path) = self.path.take() else { return Ok(()); };
+
+        let loaded = match std::fs::read_to_string(&path) {
+            Ok(source) => ron::from_str(&source).map_err(StatsError::from),
+            // First run
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(LifetimeStats::default()),
+            Err(error) => Err(error.into()),
+        };
+
+        let result = loaded.map(|loaded| *self = loaded);
+
+        self.path = Some(path);
+
+        result
+    }
+
+    /// Adds the run to what is saved. Another window may have finished a run since, so the
+    /// file is read again first. A file that won't load is left alone rather than replaced
+    /// by this one run.
+    fn record_run(&mut self, run: &RunStats, score: u64) {
+        let loaded = self.load();
+
+        if let Err(error) = &loaded {
+            warn!(target: category::GAMEPLAY, %error, "couldn't load lifetime stats, not saving this run");
+        }
+
+        self.add_run(run, score);
+
+        if loaded.is_err() { return; }
+
+        if let Err(error) = self.save() {
+            warn!(target: category::GAMEPLAY, %error, "couldn't save lifetime stats");
+        }
+    }
+
+    fn save(&self) -> Result<(), StatsError> {
+        let Some(path) = &self.path else { return Ok(()); };
+
+        let source = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+
+        std::fs::write(path, source)?;
+
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum StatsError {
+    #[error("Could not read or write the stats file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse the stats file: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("Could not write the stats: {0}")]
+    Write(#[from] ron::Error),
+}
+
+#[derive(Component)]
+struct GameOverScreen;
+
+fn count_rockets_fired(
+    mut run_stats: ResMut<RunStats>,
+    rocket_query: Query<(), Added<Rocket>>,
+) {
+    run_stats.rockets_fired += rocket_query.iter().count() as u32;
+}
+
+fn tick_run_clock(
+    mut run_stats: ResMut<RunStats>,
+    time: Res<Time>,
+    score_tracker: Res<ScoreTracker>,
+) {
+    run_stats.time_alive += time.delta_seconds();
+    run_stats.sample_timer += time.delta_seconds();
+
+    if run_stats.sample_timer < SCORE_SAMPLE_SECONDS { return; }
+
+    run_stats.sample_timer = 0.0;
+
+    let sample = (run_stats.time_alive, score_tracker.score_count.0);
+    run_stats.score_timeline.push(sample);
+}
+
+fn count_destroyed_asteroids(
+    mut run_stats: ResMut<RunStats>,
+    mut asteroid_explosion: EventReader<AsteroidDestroyed>,
+) {
+    for AsteroidDestroyed { asteroid_type, .. } in asteroid_explosion.read() {
+        *run_stats.asteroids_destroyed.entry(*asteroid_type).or_default() += 1;
+    }
+}
+
+fn track_ship_damage(
+    mut run_stats: ResMut<RunStats>,
+    ship_query: Query<&Ship>,
+) {
+    let Ok(ship) = ship_query.get_single() else { return; };
+
+    run_stats.note_ship_health(ship.hull.current, ship.shield.points.current);
+}
+
+fn end_run(
+    mut run_stats: ResMut<RunStats>,
+    mut lifetime: ResMut<LifetimeStats>,
+    score_tracker: Res<ScoreTracker>,
+    distance: Res<DistanceTravelled>,
+) {
+    let score = score_tracker.score_count.0;
+
+    run_stats.distance = **distance;
+
+    let sample = (run_stats.time_alive, score);
+    run_stats.score_timeline.push(sample);
+
+    lifetime.record_run(&run_stats, score);
+
+    info!(
+        target: category::GAMEPLAY,
+        score, time_alive = run_stats.time_alive, accuracy = ?run_stats.accuracy(),
+        "run over"
+    );
+}
+
+fn spawn_game_over_screen(
+    mut commands: Commands,
+    run_stats: Res<RunStats>,
+    lifetime: Res<LifetimeStats>,
+    score_tracker: Res<ScoreTracker>,
+    race_result: Option<Res<RaceResult>>,
+) {
+
+    let text_style = TextStyle {
+        font_size: 14.0,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    let mut lines = vec![format!("Score: {}", score_tracker.score_count.0)];
+
+    if let Some(race_result) = race_result {
+        lines.push(match race_result.finish_time {
+            Some(finish_time) => format!("{}: finished in {finish_time:.1}s", race_result.name),
+            None => format!(
+                "{}: {} checkpoints, {:.0} travelled",
+                race_result.name, race_result.checkpoints, race_result.distance
+            ),
+        });
+    }
+
+    lines.extend(run_stats.summary_lines());
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(6.0),
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.75).into(),
+            // Above the HUD, below the console
+            z_index: ZIndex::Global(5),
+            ..default()
+        },
+        GameOverScreen
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section("Game over", TextStyle {
+            font_size: 32.0,
+            ..text_style.clone()
+        }));
+
+        for line in lines {
+            parent.spawn(TextBundle::from_section(line, text_style.clone()));
+        }
+
+        // Score over the run
+        parent.spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(SCORE_CHART_BARS as f32 * 8.0),
+                height: Val::Px(80.0),
+                align_items: AlignItems::FlexEnd,
+                column_gap: Val::Px(2.0),
+                margin: UiRect::vertical(Val::Px(8.0)),
+                border: UiRect::bottom(Val::Px(1.0)),
+                ..default()
+            },
+            border_color: Color::WHITE.into(),
+            ..default()
+        }).with_children(|chart| {
+            for height in timeline_bars(&run_stats.score_timeline, SCORE_CHART_BARS) {
+                chart.spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(6.0),
+                        height: Val::Percent(height * 100.0),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.9, 0.7, 0.2).into(),
+                    ..default()
+                });
+            }
+        });
+
+        parent.spawn(TextBundle::from_section(
+            format!("Lifetime: {}", lifetime.summary_line()),
+            TextStyle {
+                color: Color::rgb(0.7, 0.7, 0.7),
+                ..text_style
+            }
+        ));
+    });
+}
+
+/// Leaving the game over screen starts the next run from nothing
+fn despawn_game_over_screen(
+    mut commands: Commands,
+    mut run_stats: ResMut<RunStats>,
+    screen_query: Query<Entity, With<GameOverScreen>>,
+) {
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    *run_stats = RunStats::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accuracy_needs_a_shot() {
+        let mut stats = RunStats::default();
+
+        assert_eq!(stats.accuracy(), None);
+
+        stats.rockets_fired = 4;
+        stats.rocket_hits = 1;
+        assert_eq!(stats.accuracy(), Some(0.25));
+
+        stats.rocket_hits = 4;
+        assert_eq!(stats.accuracy(), Some(1.0));
+    }
+
+    #[test]
+    fn closest_call_keeps_the_smallest_gap() {
+        let mut stats = RunStats::default();
+
+        stats.note_gap(120.0);
+        stats.note_gap(40.0);
+        stats.note_gap(90.0);
+        assert_eq!(stats.closest_call, Some(40.0));
+
+        // Touching
+        stats.note_gap(-3.0);
+        assert_eq!(stats.closest_call, Some(0.0));
+    }
+
+    #[test]
+    fn damage_ignores_regeneration() {
+        let mut stats = RunStats::default();
+
+        stats.note_ship_health(5, 3);
+        stats.note_ship_health(5, 1);
+        stats.note_ship_health(5, 3);
+        stats.note_ship_health(4, 0);
+
+        assert_eq!(stats.damage_taken, 2 + 1 + 3);
+    }
+
+    #[test]
+    fn timeline_bars_are_downsampled() {
+        assert!(timeline_bars(&[], 10).is_empty());
+
+        let timeline: Vec<(f32, u64)> = (0..100).map(|second| (second as f32, second * 10)).collect();
+
+        let bars = timeline_bars(&timeline, 10);
+
+        assert_eq!(bars.len(), 10);
+        assert_eq!(bars[9], 1.0);
+        assert!(bars.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        // Fewer samples than bars
+        assert_eq!(timeline_bars(&timeline[..3], 10), vec![0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn lifetime_stats_add_up_and_round_trip() {
+        let mut run = RunStats {
+            rockets_fired: 10,
+            rocket_hits: 4,
+            time_alive: 30.0,
+            distance: 500.0,
+            closest_call: Some(12.0),
+            ..default()
+        };
+        run.asteroids_destroyed.insert(AsteroidType::Large, 2);
+        run.asteroids_destroyed.insert(AsteroidType::Small, 3);
+
+        let mut lifetime = LifetimeStats::default();
+
+        lifetime.add_run(&run, 200);
+        lifetime.add_run(&RunStats { time_alive: 10.0, ..default() }, 50);
+
+        assert_eq!(lifetime.runs, 2);
+        assert_eq!(lifetime.best_score, 200);
+        assert_eq!(lifetime.longest_run, 30.0);
+        assert_eq!(lifetime.closest_call, Some(12.0));
+        assert_eq!(lifetime.asteroids_destroyed, 5);
+        assert_eq!(lifetime.time_alive, 40.0);
+
+        let source = ron::ser::to_string_pretty(&lifetime, ron::ser::PrettyConfig::default()).unwrap();
+        let loaded: LifetimeStats = ron::from_str(&source).unwrap();
+
+        assert_eq!(loaded, lifetime);
+    }
+
+    #[test]
+    fn unreadable_lifetime_stats_are_not_saved_over() {
+        let path = std::env::temp_dir().join(format!("space_race_stats_{}.ron", std::process::id()));
+        std::fs::write(&path, "not ron").unwrap();
+
+        let mut lifetime = LifetimeStats { path: Some(path.clone()), ..default() };
+
+        lifetime.record_run(&RunStats { time_alive: 10.0, ..default() }, 50);
+
+        // Still shown on the game over screen
+        assert_eq!(lifetime.runs, 1);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "not ron");
+
+        std::fs::remove_file(&path).unwrap();
+        lifetime.record_run(&RunStats::default(), 0);
+
+        // Nothing there is a first run, that one is saved
+        let saved: LifetimeStats = ron::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved.runs, 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
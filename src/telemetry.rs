@@ -0,0 +1,387 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH}
+};
+
+use bevy::prelude::*;
+
+use serde::Serialize;
+
+use crate::{
+    asteroid::Asteroid,
+    boss::BossDefeated,
+    enemy::{EnemyDestroyed, EnemyShip},
+    ship::Ship,
+    AppState,
+    AsteroidDestroyed,
+    DistanceTravelled,
+    ExplosionAnimations,
+    ExplosionEvent,
+    GameMode,
+    ScoreTracker
+};
+
+/// Log targets, so each part of the game can be filtered on its own with `RUST_LOG`,
+/// e.g. `RUST_LOG=space_race::collision=debug`. Per frame chatter is `trace`.
+pub mod category {
+    pub const COLLISION: &str = "space_race::collision";
+    pub const GAMEPLAY: &str = "space_race::gameplay";
+    pub const SCORE: &str = "space_race::score";
+    pub const RACE: &str = "space_race::race";
+    pub const UI: &str = "space_race::ui";
+    pub const TELEMETRY: &str = "space_race::telemetry";
+}
+
+/// Opt-in recorder for balancing, on with `--telemetry` or `--telemetry=<directory>`.
+/// Every run is written to its own JSON lines file, `telemetry/run-<unix time>.jsonl`
+/// by default: spawns, hits on the ship, deaths, and once a second the score and frame
+/// times. Desktop only, there is no file system on the web.
+pub struct TelemetryPlugin;
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(TelemetryRecorder::from_args())
+            .add_systems(OnEnter(AppState::InGame), start_run)
+            .add_systems(OnEnter(AppState::GameOverMenu), end_run)
+            // Last, so every event sent this frame is in
+            .add_systems(Last, (
+                record_spawns,
+                record_ship_hits,
+                record_deaths,
+                record_samples,
+            ).run_if(recording));
+    }
+}
+
+// How often the score and frame times are written
+const TELEMETRY_SAMPLE_SECONDS: f32 = 1.0;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TelemetryEvent {
+    RunStarted { mode: String },
+    Spawn { kind: String, position: [f32; 2] },
+    ShipHit { hull: u32, shield: u32 },
+    /// Enemies and bosses don't report where they went down
+    Death { kind: String, position: Option<[f32; 2]> },
+    Score { score: u64, distance: f32 },
+    FrameTimes { frames: u32, average_ms: f32, max_ms: f32 },
+    RunEnded { score: u64, distance: f32 },
+}
+
+/// One line of the file, `time` is seconds since the run started
+#[derive(Serialize, Debug)]
+pub struct TelemetryRecord<'a> {
+    pub time: f32,
+    #[serde(flatten)]
+    pub event: &'a TelemetryEvent,
+}
+
+/// Frame times since the last sample
+#[derive(Debug, Default)]
+pub struct FrameSample {
+    frames: u32,
+    total: f32,
+    max: f32,
+}
+
+impl FrameSample {
+    pub fn add(&mut self, delta_seconds: f32) {
+        self.frames += 1;
+        self.total += delta_seconds;
+        self.max = self.max.max(delta_seconds);
+    }
+
+    /// The frame times so far, starting over
+    pub fn take(&mut self) -> Option<TelemetryEvent> {
+        let sample = std::mem::take(self);
+
+        if sample.frames == 0 { return None; }
+
+        Some(TelemetryEvent::FrameTimes {
+            frames: sample.frames,
+            average_ms: sample.total / sample.frames as f32 * 1000.0,
+            max_ms: sample.max * 1000.0,
+        })
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct TelemetryRecorder {
+    /// Off when `None`
+    pub directory: Option<PathBuf>,
+    writer: Option<Box<dyn Write + Send + Sync>>,
+    run_started: f32,
+    frames: FrameSample,
+    sample_timer: f32,
+}
+
+impl TelemetryRecorder {
+    pub fn from_args() -> TelemetryRecorder {
+        let directory = std::env::args().find_map(|arg| match arg.as_str() {
+            "--telemetry" => Some(PathBuf::from("telemetry")),
+            arg => arg.strip_prefix("--telemetry=").map(PathBuf::from),
+        });
+
+        TelemetryRecorder {
+            directory,
+            ..default()
+        }
+    }
+
+    /// Records to `writer` instead of a file
+    #[cfg(test)]
+    pub fn with_writer(writer: Box<dyn Write + Send + Sync>) -> TelemetryRecorder {
+        TelemetryRecorder {
+            writer: Some(writer),
+            ..default()
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    pub fn record(&mut self, elapsed_seconds: f32, event: TelemetryEvent) {
+        let Some(writer) = self.writer.as_mut() else { return; };
+
+        let record = TelemetryRecord { time: elapsed_seconds - self.run_started, event: &event };
+
+        let written = serde_json::to_writer(&mut *writer, &record)
+            .map_err(std::io::Error::from)
+            .and_then(|_| writer.write_all(b"\n"));
+
+        if let Err(error) = written {
+            warn!(target: category::TELEMETRY, %error, "couldn't write telemetry, recording stopped");
+            self.writer = None;
+        }
+    }
+
+    fn flush(&mut self) {
+        let Some(writer) = self.writer.as_mut() else { return; };
+
+        if let Err(error) = writer.flush() {
+            warn!(target: category::TELEMETRY, %error, "couldn't write telemetry, recording stopped");
+            self.writer = None;
+        }
+    }
+
+    fn open_run_file(&mut self) -> std::io::Result<PathBuf> {
+        let Some(directory) = &self.directory else {
+            return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "no telemetry directory"));
+        };
+
+        fs::create_dir_all(directory)?;
+
+        let started = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or_default();
+        let path = directory.join(format!("run-{started}.jsonl"));
+
+        self.writer = Some(Box::new(BufWriter::new(File::create(&path)?)));
+
+        Ok(path)
+    }
+}
+
+fn recording(recorder: Res<TelemetryRecorder>) -> bool {
+    recorder.is_recording()
+}
+
+/// Entering the game again after a pause keeps the same run
+fn start_run(
+    mut recorder: ResMut<TelemetryRecorder>,
+    time: Res<Time>,
+    mode: Res<GameMode>,
+) {
+
+    if recorder.is_recording() || recorder.directory.is_none() { return; }
+
+    match recorder.open_run_file() {
+        Ok(path) => info!(target: category::TELEMETRY, path = %path.display(), "recording telemetry"),
+        Err(error) => {
+            warn!(target: category::TELEMETRY, %error, "couldn't start recording telemetry");
+            return;
+        }
+    }
+
+    recorder.run_started = time.elapsed_seconds();
+    recorder.frames = FrameSample::default();
+    recorder.sample_timer = 0.0;
+
+    recorder.record(time.elapsed_seconds(), TelemetryEvent::RunStarted { mode: format!("{:?}", *mode) });
+}
+
+fn end_run(
+    mut recorder: ResMut<TelemetryRecorder>,
+    time: Res<Time>,
+    score_tracker: Res<ScoreTracker>,
+    distance: Res<DistanceTravelled>,
+) {
+
+    if !recorder.is_recording() { return; }
+
+    recorder.record(time.elapsed_seconds(), TelemetryEvent::RunEnded {
+        score: score_tracker.score_count.0,
+        distance: **distance,
+    });
+
+    recorder.flush();
+    recorder.writer = None;
+}
+
+// Pooled entities count again every time they come back out
+fn record_spawns(
+    mut recorder: ResMut<TelemetryRecorder>,
+    time: Res<Time>,
+    asteroid_query: Query<(&Transform, &Asteroid), Added<Asteroid>>,
+    enemy_query: Query<(&Transform, &EnemyShip), Added<EnemyShip>>,
+) {
+    for (transform, asteroid) in asteroid_query.iter() {
+        recorder.record(time.elapsed_seconds(), TelemetryEvent::Spawn {
+            kind: format!("{:?}", asteroid.asteroid_type),
+            position: transform.translation.truncate().to_array(),
+        });
+    }
+
+    for (transform, enemy) in enemy_query.iter() {
+        recorder.record(time.elapsed_seconds(), TelemetryEvent::Spawn {
+            kind: format!("{:?}", enemy.pattern),
+            position: transform.translation.truncate().to_array(),
+        });
+    }
+}
+
+fn record_ship_hits(
+    mut recorder: ResMut<TelemetryRecorder>,
+    time: Res<Time>,
+    mut collision_events: EventReader<ExplosionEvent>,
+    ship_query: Query<(&Transform, &Ship)>,
+) {
+    for ExplosionEvent { explosion_type, entity } in collision_events.read() {
+        let Ok((transform, ship)) = ship_query.get(*entity) else { continue; };
+
+        let event = match explosion_type {
+            ExplosionAnimations::DamageToShip => TelemetryEvent::ShipHit {
+                hull: ship.hull.current,
+                shield: ship.shield.points.current,
+            },
+            ExplosionAnimations::ShipExplosion => TelemetryEvent::Death {
+                kind: "Ship".to_string(),
+                position: Some(transform.translation.truncate().to_array()),
+            },
+            _ => continue,
+        };
+
+        recorder.record(time.elapsed_seconds(), event);
+    }
+}
+
+fn record_deaths(
+    mut recorder: ResMut<TelemetryRecorder>,
+    time: Res<Time>,
+    mut asteroid_explosion: EventReader<AsteroidDestroyed>,
+    mut enemy_explosion: EventReader<EnemyDestroyed>,
+    mut boss_defeats: EventReader<BossDefeated>,
+) {
+    for AsteroidDestroyed { asteroid_type, position } in asteroid_explosion.read() {
+        recorder.record(time.elapsed_seconds(), TelemetryEvent::Death {
+            kind: format!("{asteroid_type:?}"),
+            position: Some(position.truncate().to_array()),
+        });
+    }
+
+    for EnemyDestroyed { pattern } in enemy_explosion.read() {
+        recorder.record(time.elapsed_seconds(), TelemetryEvent::Death { kind: format!("{pattern:?}"), position: None });
+    }
+
+    for _ in boss_defeats.read() {
+        recorder.record(time.elapsed_seconds(), TelemetryEvent::Death { kind: "Boss".to_string(), position: None });
+    }
+}
+
+fn record_samples(
+    mut recorder: ResMut<TelemetryRecorder>,
+    time: Res<Time>,
+    real_time: Res<Time<Real>>,
+    score_tracker: Res<ScoreTracker>,
+    distance: Res<DistanceTravelled>,
+) {
+    recorder.frames.add(real_time.delta_seconds());
+    recorder.sample_timer += real_time.delta_seconds();
+
+    if recorder.sample_timer < TELEMETRY_SAMPLE_SECONDS { return; }
+
+    recorder.sample_timer = 0.0;
+
+    recorder.record(time.elapsed_seconds(), TelemetryEvent::Score {
+        score: score_tracker.score_count.0,
+        distance: **distance,
+    });
+
+    if let Some(frame_times) = recorder.frames.take() {
+        recorder.record(time.elapsed_seconds(), frame_times);
+    }
+
+    // Written out as it goes, so a crash keeps most of the run
+    recorder.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn records_are_json_lines() {
+        let buffer = SharedBuffer::default();
+        let mut recorder = TelemetryRecorder::with_writer(Box::new(buffer.clone()));
+
+        recorder.run_started = 10.0;
+
+        recorder.record(10.5, TelemetryEvent::Spawn { kind: "Large".to_string(), position: [1.0, -2.0] });
+        recorder.record(12.0, TelemetryEvent::Death { kind: "Dive".to_string(), position: None });
+
+        let written = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+
+        assert_eq!(written, concat!(
+            r#"{"time":0.5,"event":"spawn","kind":"Large","position":[1.0,-2.0]}"#, "\n",
+            r#"{"time":2.0,"event":"death","kind":"Dive","position":null}"#, "\n",
+        ));
+    }
+
+    #[test]
+    fn frame_samples_start_over() {
+        let mut sample = FrameSample::default();
+
+        assert_eq!(sample.take(), None);
+
+        sample.add(0.010);
+        sample.add(0.030);
+
+        let Some(TelemetryEvent::FrameTimes { frames, average_ms, max_ms }) = sample.take() else {
+            panic!("expected frame times");
+        };
+
+        assert_eq!(frames, 2);
+        assert!((average_ms - 20.0).abs() < 1e-3);
+        assert!((max_ms - 30.0).abs() < 1e-3);
+
+        assert_eq!(sample.take(), None);
+    }
+}
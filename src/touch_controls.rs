@@ -0,0 +1,597 @@
+use bevy::{
+    input::touch::TouchInput,
+    prelude::*,
+    window::PrimaryWindow
+};
+
+use crate::{
+    ui_plugin::OnPressedHandler,
+    AppState
+};
+
+/// On-screen controls for the browser build.
+/// The virtual joystick and fire button stay hidden until the first `TouchInput`
+/// event is seen, while holding the left mouse button steers the ship towards the cursor.
+pub struct TouchControlsPlugin;
+
+impl Plugin for TouchControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<VirtualControls>()
+            .init_resource::<PointerControlMode>()
+            .add_systems(Startup, spawn_touch_controls)
+            .add_systems(PreUpdate, (
+                detect_touch_input,
+                (
+                    update_touch_controls.after(detect_touch_input),
+                    update_mouse_steering.after(update_touch_controls),
+                ).run_if(in_state(AppState::InGame)),
+            ).after(bevy::input::InputSystem).after(bevy::ui::UiSystem::Focus))
+            .add_systems(Update, update_touch_controls_visuals.run_if(in_state(AppState::InGame)))
+            .add_systems(OnExit(AppState::InGame), hide_touch_controls);
+    }
+}
+
+/// The input read by `ship_movement` and `check_if_firing` alongside the keyboard
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub struct VirtualControls {
+    /// Joystick deflection, each axis between -1.0 and 1.0 (y points up)
+    pub direction: Vec2,
+    /// Where the ship should head to, relative to the camera's center
+    pub steer_target: Option<Vec2>,
+    pub fire_held: bool,
+    pub touch_detected: bool,
+    joystick_touch: Option<u64>,
+    steer_touch: Option<u64>,
+}
+
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub enum PointerControlMode {
+    #[default]
+    Joystick,
+    DragToSteer
+}
+
+#[derive(Component)]
+struct TouchControlsRoot;
+
+#[derive(Component)]
+struct JoystickBase;
+
+#[derive(Component)]
+struct JoystickKnob;
+
+#[derive(Component)]
+struct FireButton;
+
+#[derive(Component)]
+struct ControlModeButton;
+
+#[derive(Component)]
+struct ControlModeText;
+
+const JOYSTICK_BASE_SIZE: f32 = 110.0;
+const JOYSTICK_KNOB_SIZE: f32 = 50.0;
+const JOYSTICK_MARGIN: f32 = 20.0;
+const JOYSTICK_RADIUS: f32 = (JOYSTICK_BASE_SIZE - JOYSTICK_KNOB_SIZE) / 2.0;
+const FIRE_BUTTON_SIZE: f32 = 80.0;
+const MODE_BUTTON_SIZE: Vec2 = Vec2::new(56.0, 28.0);
+
+// Distance (in pixels) from the ship at which drag-to-steer applies full thrust
+const STEER_FULL_THRUST_DISTANCE: f32 = 40.0;
+
+fn spawn_touch_controls(mut commands: Commands) {
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        TouchControlsRoot
+    )).with_children(|parent| {
+
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(JOYSTICK_MARGIN),
+                    bottom: Val::Px(JOYSTICK_MARGIN),
+                    width: Val::Px(JOYSTICK_BASE_SIZE),
+                    height: Val::Px(JOYSTICK_BASE_SIZE),
+                    ..default()
+                },
+                background_color: Color::rgba(1.0, 1.0, 1.0, 0.15).into(),
+                ..default()
+            },
+            JoystickBase
+        )).with_children(|base| {
+            base.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Px(JOYSTICK_RADIUS),
+                        top: Val::Px(JOYSTICK_RADIUS),
+                        width: Val::Px(JOYSTICK_KNOB_SIZE),
+                        height: Val::Px(JOYSTICK_KNOB_SIZE),
+                        ..default()
+                    },
+                    background_color: Color::rgba(1.0, 1.0, 1.0, 0.4).into(),
+                    ..default()
+                },
+                JoystickKnob
+            ));
+        });
+
+        parent.spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(JOYSTICK_MARGIN),
+                    bottom: Val::Px(JOYSTICK_MARGIN + (JOYSTICK_BASE_SIZE - FIRE_BUTTON_SIZE) / 2.0),
+                    width: Val::Px(FIRE_BUTTON_SIZE),
+                    height: Val::Px(FIRE_BUTTON_SIZE),
+                    ..default()
+                },
+                background_color: Color::rgba(0.9, 0.2, 0.2, 0.35).into(),
+                ..default()
+            },
+            FireButton
+        ));
+
+        parent.spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(JOYSTICK_MARGIN / 2.0),
+                    top: Val::Px(JOYSTICK_MARGIN / 2.0),
+                    width: Val::Px(MODE_BUTTON_SIZE.x),
+                    height: Val::Px(MODE_BUTTON_SIZE.y),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0.35, 0.35, 0.35, 0.6).into(),
+                ..default()
+            },
+            ControlModeButton
+        )).with_children(|button| {
+            button.spawn((
+                TextBundle::from_section(
+                    "Stick",
+                    TextStyle {
+                        font_size: 16.0,
+                        color: Color::WHITE,
+                        ..default()
+                    }
+                ),
+                ControlModeText
+            ));
+        }).add(OnPressedHandler::from(toggle_control_mode));
+    });
+}
+
+fn toggle_control_mode(mut mode: ResMut<PointerControlMode>) {
+    *mode = match *mode {
+        PointerControlMode::Joystick => PointerControlMode::DragToSteer,
+        PointerControlMode::DragToSteer => PointerControlMode::Joystick,
+    };
+}
+
+fn detect_touch_input(
+    mut touch_events: EventReader<TouchInput>,
+    mut controls: ResMut<VirtualControls>,
+) {
+    if touch_events.read().next().is_some() {
+        controls.touch_detected = true;
+    }
+}
+
+/// Maps a touch position onto the joystick, returning the deflection on each axis.
+/// `center` and `touch_position` are in window coordinates, where y grows downwards.
+pub fn joystick_direction(center: Vec2, radius: f32, touch_position: Vec2) -> Vec2 {
+    let offset = touch_position - center;
+
+    Vec2::new(offset.x, -offset.y)
+        .clamp_length_max(radius) / radius
+}
+
+// The controls are absolutely positioned, so their hit boxes (in window coordinates)
+// follow directly from the window size without waiting on the ui layout
+fn joystick_rect(window_size: Vec2) -> Rect {
+    Rect::new(
+        JOYSTICK_MARGIN,
+        window_size.y - JOYSTICK_MARGIN - JOYSTICK_BASE_SIZE,
+        JOYSTICK_MARGIN + JOYSTICK_BASE_SIZE,
+        window_size.y - JOYSTICK_MARGIN
+    )
+}
+
+fn fire_button_rect(window_size: Vec2) -> Rect {
+    let bottom = JOYSTICK_MARGIN + (JOYSTICK_BASE_SIZE - FIRE_BUTTON_SIZE) / 2.0;
+
+    Rect::new(
+        window_size.x - JOYSTICK_MARGIN - FIRE_BUTTON_SIZE,
+        window_size.y - bottom - FIRE_BUTTON_SIZE,
+        window_size.x - JOYSTICK_MARGIN,
+        window_size.y - bottom
+    )
+}
+
+fn mode_button_rect(window_size: Vec2) -> Rect {
+    Rect::new(
+        window_size.x - JOYSTICK_MARGIN / 2.0 - MODE_BUTTON_SIZE.x,
+        JOYSTICK_MARGIN / 2.0,
+        window_size.x - JOYSTICK_MARGIN / 2.0,
+        JOYSTICK_MARGIN / 2.0 + MODE_BUTTON_SIZE.y
+    )
+}
+
+/// Converts a window position into an offset from the center of the camera
+pub fn window_to_camera_offset(window: &Window, window_position: Vec2) -> Vec2 {
+    Vec2::new(
+        window_position.x - window.width() / 2.0,
+        window.height() / 2.0 - window_position.y
+    )
+}
+
+fn update_touch_controls(
+    touches: Res<Touches>,
+    mode: Res<PointerControlMode>,
+    mut controls: ResMut<VirtualControls>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    fire_button_query: Query<&Interaction, With<FireButton>>,
+) {
+
+    if !controls.touch_detected { return; }
+
+    let Ok(window) = window_query.get_single() else { return; };
+
+    let window_size = Vec2::new(window.width(), window.height());
+
+    let joystick_rect = joystick_rect(window_size);
+    let fire_rect = fire_button_rect(window_size);
+    let mode_rect = mode_button_rect(window_size);
+
+    // Only written back when something differs, so the visuals don't update every frame
+    let mut updated = *controls;
+
+    // The button's Interaction covers the mouse and the first touch. Bevy's ui focus
+    // only follows that first touch though, so a second finger on the fire button
+    // (while the other one steers) has to be hit tested here
+    let touch_on_fire_button = touches.iter().any(|touch| fire_rect.contains(touch.position()));
+    let fire_button_pressed = fire_button_query.get_single()
+        .is_ok_and(|interaction| *interaction == Interaction::Pressed);
+
+    updated.fire_held = touch_on_fire_button || fire_button_pressed;
+
+    if updated.joystick_touch.is_some_and(|id| touches.get_pressed(id).is_none()) {
+        updated.joystick_touch = None;
+    }
+
+    if updated.steer_touch.is_some_and(|id| touches.get_pressed(id).is_none()) {
+        updated.steer_touch = None;
+    }
+
+    for touch in touches.iter_just_pressed() {
+        let position = touch.position();
+
+        if fire_rect.contains(position) || mode_rect.contains(position) {
+            continue;
+        }
+
+        match *mode {
+            PointerControlMode::Joystick => {
+                if updated.joystick_touch.is_none() && joystick_rect.contains(position) {
+                    updated.joystick_touch = Some(touch.id());
+                }
+            },
+            PointerControlMode::DragToSteer => {
+                if updated.steer_touch.is_none() {
+                    updated.steer_touch = Some(touch.id());
+                }
+            }
+        }
+    }
+
+    updated.direction = match updated.joystick_touch.and_then(|id| touches.get_pressed(id)) {
+        Some(touch) if *mode == PointerControlMode::Joystick => {
+            joystick_direction(joystick_rect.center(), JOYSTICK_RADIUS, touch.position())
+        },
+        _ => Vec2::ZERO
+    };
+
+    updated.steer_target = match updated.steer_touch.and_then(|id| touches.get_pressed(id)) {
+        Some(touch) if *mode == PointerControlMode::DragToSteer => {
+            Some(window_to_camera_offset(window, touch.position()))
+        },
+        _ => None
+    };
+
+    if updated != *controls {
+        *controls = updated;
+    }
+}
+
+fn update_mouse_steering(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    mut controls: ResMut<VirtualControls>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    interaction_query: Query<&Interaction>,
+) {
+
+    // Touches take priority, browsers also emulate mouse events for them
+    if controls.steer_touch.is_some() || controls.joystick_touch.is_some() { return; }
+
+    let Ok(window) = window_query.get_single() else { return; };
+
+    let over_ui = interaction_query.iter().any(|interaction| *interaction != Interaction::None);
+
+    let steer_target = match window.cursor_position() {
+        Some(cursor_position) if mouse_input.pressed(MouseButton::Left) && !over_ui => {
+            Some(window_to_camera_offset(window, cursor_position))
+        },
+        _ => None
+    };
+
+    if controls.steer_target != steer_target {
+        controls.steer_target = steer_target;
+    }
+}
+
+/// Returns the movement magnitude, on each axis between -1.0 and 1.0,
+/// needed to move the ship from `ship_offset` towards `steer_target`
+pub fn steer_towards(ship_offset: Vec2, steer_target: Vec2) -> Vec2 {
+    ((steer_target - ship_offset) / STEER_FULL_THRUST_DISTANCE)
+        .clamp(Vec2::NEG_ONE, Vec2::ONE)
+}
+
+fn update_touch_controls_visuals(
+    controls: Res<VirtualControls>,
+    mode: Res<PointerControlMode>,
+    mut root_query: Query<&mut Visibility, (With<TouchControlsRoot>, Without<JoystickBase>)>,
+    mut joystick_query: Query<&mut Visibility, (With<JoystickBase>, Without<TouchControlsRoot>)>,
+    mut knob_query: Query<&mut Style, With<JoystickKnob>>,
+    mut mode_text_query: Query<&mut Text, With<ControlModeText>>,
+) {
+
+    if !controls.touch_detected { return; }
+
+    root_query.single_mut().set_if_neq(Visibility::Inherited);
+
+    joystick_query.single_mut().set_if_neq(match *mode {
+        PointerControlMode::Joystick => Visibility::Inherited,
+        PointerControlMode::DragToSteer => Visibility::Hidden,
+    });
+
+    let knob_left = Val::Px(JOYSTICK_RADIUS + controls.direction.x * JOYSTICK_RADIUS);
+    let knob_top = Val::Px(JOYSTICK_RADIUS - controls.direction.y * JOYSTICK_RADIUS);
+
+    let mut knob_style = knob_query.single_mut();
+
+    if knob_style.left != knob_left || knob_style.top != knob_top {
+        knob_style.left = knob_left;
+        knob_style.top = knob_top;
+    }
+
+    if mode.is_changed() {
+        mode_text_query.single_mut().sections[0].value = match *mode {
+            PointerControlMode::Joystick => "Stick".to_string(),
+            PointerControlMode::DragToSteer => "Drag".to_string(),
+        };
+    }
+}
+
+// Releases any held input and hides the overlay while paused or in a menu
+fn hide_touch_controls(
+    mut controls: ResMut<VirtualControls>,
+    mut root_query: Query<&mut Visibility, With<TouchControlsRoot>>,
+) {
+    *controls = VirtualControls {
+        touch_detected: controls.touch_detected,
+        ..default()
+    };
+
+    for mut visibility in root_query.iter_mut() {
+        visibility.set_if_neq(Visibility::Hidden);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bevy::{
+        input::{touch::TouchPhase, InputPlugin},
+        window::WindowResolution
+    };
+
+    use crate::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
+
+    const WINDOW_SIZE: Vec2 = Vec2::new(WINDOW_WIDTH, WINDOW_HEIGHT);
+
+    fn window() -> Window {
+        Window {
+            resolution: WindowResolution::new(WINDOW_WIDTH, WINDOW_HEIGHT),
+            ..default()
+        }
+    }
+
+    fn test_app() -> (App, Entity) {
+        let mut app = App::new();
+
+        app
+            .add_plugins((MinimalPlugins, InputPlugin, TouchControlsPlugin))
+            .insert_state(AppState::InGame);
+
+        let window_entity = app.world.spawn((window(), PrimaryWindow)).id();
+
+        app.update();
+
+        (app, window_entity)
+    }
+
+    fn touch(app: &mut App, window: Entity, id: u64, phase: TouchPhase, position: Vec2) {
+        app.world.send_event(TouchInput {
+            phase,
+            position,
+            window,
+            force: None,
+            id,
+        });
+        app.update();
+    }
+
+    fn controls(app: &App) -> VirtualControls {
+        *app.world.resource::<VirtualControls>()
+    }
+
+    #[test]
+    fn joystick_direction_is_clamped_to_the_radius() {
+        let center = Vec2::new(100.0, 100.0);
+
+        assert_eq!(joystick_direction(center, 30.0, center), Vec2::ZERO);
+        assert_eq!(joystick_direction(center, 30.0, center + Vec2::new(15.0, 0.0)), Vec2::new(0.5, 0.0));
+        // Window y grows downwards, joystick y points up
+        assert_eq!(joystick_direction(center, 30.0, center + Vec2::new(0.0, -30.0)), Vec2::new(0.0, 1.0));
+
+        let far = joystick_direction(center, 30.0, center + Vec2::new(300.0, 300.0));
+        assert!((far.length() - 1.0).abs() < 1e-5);
+        assert!(far.x > 0.0 && far.y < 0.0);
+    }
+
+    #[test]
+    fn window_positions_map_to_camera_offsets() {
+        let window = window();
+
+        assert_eq!(window_to_camera_offset(&window, WINDOW_SIZE / 2.0), Vec2::ZERO);
+        assert_eq!(window_to_camera_offset(&window, Vec2::ZERO), Vec2::new(-WINDOW_WIDTH / 2.0, WINDOW_HEIGHT / 2.0));
+        assert_eq!(window_to_camera_offset(&window, WINDOW_SIZE), Vec2::new(WINDOW_WIDTH / 2.0, -WINDOW_HEIGHT / 2.0));
+    }
+
+    #[test]
+    fn steering_saturates_at_full_thrust_distance() {
+        let ship = Vec2::new(10.0, -20.0);
+
+        assert_eq!(steer_towards(ship, ship), Vec2::ZERO);
+        assert_eq!(
+            steer_towards(ship, ship + Vec2::new(STEER_FULL_THRUST_DISTANCE / 2.0, -STEER_FULL_THRUST_DISTANCE / 4.0)),
+            Vec2::new(0.5, -0.25)
+        );
+        assert_eq!(
+            steer_towards(ship, ship + Vec2::new(-STEER_FULL_THRUST_DISTANCE * 5.0, STEER_FULL_THRUST_DISTANCE)),
+            Vec2::new(-1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn controls_stay_hidden_until_touch_is_seen() {
+        let (mut app, window) = test_app();
+
+        assert!(!controls(&app).touch_detected);
+
+        let root = app.world.query_filtered::<&Visibility, With<TouchControlsRoot>>().single(&app.world);
+        assert_eq!(*root, Visibility::Hidden);
+
+        touch(&mut app, window, 0, TouchPhase::Started, WINDOW_SIZE / 2.0);
+
+        assert!(controls(&app).touch_detected);
+
+        let root = app.world.query_filtered::<&Visibility, With<TouchControlsRoot>>().single(&app.world);
+        assert_eq!(*root, Visibility::Inherited);
+    }
+
+    #[test]
+    fn joystick_follows_the_touch_that_started_on_it() {
+        let (mut app, window) = test_app();
+
+        let center = joystick_rect(WINDOW_SIZE).center();
+
+        touch(&mut app, window, 0, TouchPhase::Started, center + Vec2::new(JOYSTICK_RADIUS, 0.0));
+
+        assert_eq!(controls(&app).joystick_touch, Some(0));
+        assert_eq!(controls(&app).direction, Vec2::new(1.0, 0.0));
+
+        touch(&mut app, window, 0, TouchPhase::Moved, center + Vec2::new(0.0, -JOYSTICK_RADIUS / 2.0));
+
+        assert_eq!(controls(&app).direction, Vec2::new(0.0, 0.5));
+
+        touch(&mut app, window, 0, TouchPhase::Ended, center + Vec2::new(0.0, -JOYSTICK_RADIUS / 2.0));
+
+        assert_eq!(controls(&app).joystick_touch, None);
+        assert_eq!(controls(&app).direction, Vec2::ZERO);
+    }
+
+    #[test]
+    fn touches_outside_the_joystick_do_not_steer() {
+        let (mut app, window) = test_app();
+
+        touch(&mut app, window, 0, TouchPhase::Started, WINDOW_SIZE / 2.0);
+
+        assert_eq!(controls(&app).joystick_touch, None);
+        assert_eq!(controls(&app).direction, Vec2::ZERO);
+        assert_eq!(controls(&app).steer_target, None);
+    }
+
+    #[test]
+    fn second_finger_on_fire_button_fires_while_steering() {
+        let (mut app, window) = test_app();
+
+        let joystick_center = joystick_rect(WINDOW_SIZE).center();
+        let fire_center = fire_button_rect(WINDOW_SIZE).center();
+
+        touch(&mut app, window, 0, TouchPhase::Started, joystick_center + Vec2::new(-JOYSTICK_RADIUS, 0.0));
+        touch(&mut app, window, 1, TouchPhase::Started, fire_center);
+
+        assert!(controls(&app).fire_held);
+        assert_eq!(controls(&app).direction, Vec2::new(-1.0, 0.0));
+
+        touch(&mut app, window, 1, TouchPhase::Ended, fire_center);
+
+        assert!(!controls(&app).fire_held);
+        assert_eq!(controls(&app).direction, Vec2::new(-1.0, 0.0));
+    }
+
+    #[test]
+    fn drag_to_steer_targets_the_touch() {
+        let (mut app, window) = test_app();
+
+        *app.world.resource_mut::<PointerControlMode>() = PointerControlMode::DragToSteer;
+
+        touch(&mut app, window, 0, TouchPhase::Started, WINDOW_SIZE / 2.0 + Vec2::new(40.0, -20.0));
+
+        assert_eq!(controls(&app).steer_target, Some(Vec2::new(40.0, 20.0)));
+        assert_eq!(controls(&app).joystick_touch, None);
+
+        touch(&mut app, window, 0, TouchPhase::Moved, WINDOW_SIZE / 2.0 + Vec2::new(-10.0, 50.0));
+
+        assert_eq!(controls(&app).steer_target, Some(Vec2::new(-10.0, -50.0)));
+
+        touch(&mut app, window, 0, TouchPhase::Ended, WINDOW_SIZE / 2.0 + Vec2::new(-10.0, 50.0));
+
+        assert_eq!(controls(&app).steer_target, None);
+    }
+
+    #[test]
+    fn leaving_the_game_releases_and_hides_the_controls() {
+        let (mut app, window) = test_app();
+
+        touch(&mut app, window, 0, TouchPhase::Started, fire_button_rect(WINDOW_SIZE).center());
+
+        assert!(controls(&app).fire_held);
+
+        app.world.resource_mut::<NextState<AppState>>().set(AppState::Paused);
+        app.update();
+
+        assert!(!controls(&app).fire_held);
+        assert!(controls(&app).touch_detected);
+
+        let root = app.world.query_filtered::<&Visibility, With<TouchControlsRoot>>().single(&app.world);
+        assert_eq!(*root, Visibility::Hidden);
+    }
+}
@@ -4,7 +4,7 @@ use event_handler_macro::EventHandler;
 
 use std::marker::PhantomData;
 
-use crate::AppState;
+use crate::{telemetry::category, AppState};
 
 #[derive(Component, Debug, Clone, EventHandler)]
 pub struct OnPressed {
@@ -15,6 +15,7 @@ pub struct OnPressed {
 pub struct UiInteractionPlugin;
 
 #[derive(Component)]
+#[allow(dead_code)]
 pub struct MainMenuRootNode;
 
 /// This is the plugin that will handle all of the UI interactions
@@ -27,13 +28,14 @@ impl Plugin for UiInteractionPlugin {
 }
 
 // Call this system when you need to do something
+#[allow(clippy::type_complexity)]
 pub fn handle_pressed(
         mut interaction_query: Query<(Entity, &Interaction), (Changed<Interaction>, With<OnPressed>)>,
         mut handler_query: Query<&mut OnPressed>,
         mut commands: Commands,
 ) {
         for (entity, interaction) in &mut interaction_query {
-                println!("inside of handle_pressed");
+                trace!(target: category::UI, ?entity, ?interaction, "button interaction");
                 let handler = handler_query.get_mut(entity).unwrap().into_inner();
                 if *interaction == Interaction::Pressed {
                         let mut active_handler = handler.clone();
@@ -52,18 +54,16 @@ pub fn react_to_button_pressed(
 ) {
     for (entity, on_event) in &mut pressed_query {
         if on_event.active {
-                println!("Button with entity {:?} was pressed", entity);
-
                 let game_state = state.get();
 
-                println!("Current Game State: {:?}", game_state);
+                debug!(target: category::UI, ?entity, ?game_state, "button pressed");
                 match *game_state {
                     AppState::InGame => {
-                        println!("Setting next state to Paused");
+                        info!(target: category::UI, "pausing");
                         next_state.set(AppState::Paused);
                     },
                     AppState::Paused => {
-                        println!("Setting next state to InGame");
+                        info!(target: category::UI, "resuming");
                         next_state.set(AppState::InGame);
                     },
                     _ => {}
@@ -8,6 +8,7 @@ use crate::{
 };
 
 #[derive(Component)]
+#[allow(dead_code)]
 struct Wall;
 
 pub enum GameWall {
@@ -0,0 +1,797 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture
+};
+
+use rand::Rng;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    asteroid::{Asteroid, AsteroidBundle, AsteroidType},
+    enemy::{EnemyPattern, EnemyShipBundle},
+    boss::{BossDefinition, BossFight},
+    constants::*,
+    game_assets::GameAssets,
+    loading::{LoadingSet, Preload},
+    pickup::{spawn_pickup, PickupKind},
+    pool::{EntityPools, PoolKind},
+    scroll::ScrollSpeed,
+    AppState,
+    GameCamera,
+    GameRng,
+    Velocity
+};
+
+/// Loads the wave script and hands it to the `WaveDirector`, which replaces the old
+/// single spawn timer. Scripts are RON files ending in `.waves.ron`, see
+/// `assets/waves/default.waves.ron` for the format.
+pub struct WavePlugin;
+
+impl Plugin for WavePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<WaveScript>()
+            .register_asset_loader(WaveScriptLoader)
+            .add_systems(Startup, load_wave_script)
+            .add_systems(Update, track_bosses.before(LoadingSet).run_if(in_state(AppState::Loading)));
+    }
+}
+
+pub const DEFAULT_WAVE_SCRIPT: &str = "waves/default.waves.ron";
+
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct WaveScript {
+    /// Start over from the first wave once the last one is done
+    #[serde(default)]
+    pub looping: bool,
+    /// How much harder every loop gets, 0.25 means 25% faster spawns and asteroids per loop
+    #[serde(default)]
+    pub difficulty_per_loop: f32,
+    /// No spawns happen while this many asteroids are on screen
+    #[serde(default = "default_max_asteroids")]
+    pub max_asteroids: usize,
+    pub waves: Vec<Wave>,
+    /// Every boss the script starts, loaded along with it so they are ready before the fight
+    #[serde(skip)]
+    #[dependency]
+    pub bosses: Vec<Handle<BossDefinition>>,
+}
+
+fn default_max_asteroids() -> usize { 25 }
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Wave {
+    pub name: String,
+    pub events: Vec<WaveEvent>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct WaveEvent {
+    pub trigger: WaveTrigger,
+    pub action: WaveAction,
+}
+
+/// When an event fires, measured from the previous event (or the start of the script)
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum WaveTrigger {
+    /// Seconds of game time
+    After(f32),
+    /// Units scrolled by the camera
+    Distance(f32),
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum WaveAction {
+    Asteroids {
+        formation: Formation,
+        /// A random type per asteroid when left out
+        #[serde(default)]
+        asteroid_type: Option<AsteroidType>,
+    },
+    Enemies {
+        formation: Formation,
+        pattern: EnemyPattern,
+    },
+    Pickups {
+        formation: Formation,
+        kind: PickupKind,
+    },
+    /// Holds the script, nothing spawns for this many seconds
+    Pause(f32),
+    /// Starts a boss fight from a `.boss.ron` file, the script waits until the boss is beaten
+    Boss(String),
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum Formation {
+    Single { x: f32 },
+    /// A horizontal row, centered on the screen
+    Line { count: u32, spacing: f32 },
+    /// Asteroids stacked above each other at `x`
+    Column { count: u32, spacing: f32, x: f32 },
+    /// A V pointing down, centered on the screen
+    V { count: u32, spacing: f32 },
+    /// Anywhere across the screen, the count grows with difficulty
+    Random { count: u32 },
+}
+
+impl Formation {
+    fn count(&self) -> u32 {
+        match self {
+            Formation::Single { .. } => 1,
+            Formation::Line { count, .. } |
+            Formation::Column { count, .. } |
+            Formation::V { count, .. } |
+            Formation::Random { count } => *count,
+        }
+    }
+
+    /// Spawn offsets; x from the center of the screen, y above the top of it
+    pub fn offsets(&self, difficulty: f32, rng: &mut impl Rng) -> Vec<Vec2> {
+        match self {
+            Formation::Single { x } => vec![Vec2::new(*x, 0.0)],
+            Formation::Line { count, spacing } => {
+                let width = (*count as f32 - 1.0) * spacing;
+
+                (0..*count).map(|i| Vec2::new(i as f32 * spacing - width / 2.0, 0.0)).collect()
+            },
+            Formation::Column { count, spacing, x } => {
+                (0..*count).map(|i| Vec2::new(*x, i as f32 * spacing)).collect()
+            },
+            Formation::V { count, spacing } => {
+                (0..*count).map(|i| {
+                    // Alternate sides, the tip comes first
+                    let arm = (i as f32 / 2.0).ceil();
+                    let side = if i % 2 == 0 { 1.0 } else { -1.0 };
+
+                    Vec2::new(arm * spacing * side, arm * spacing)
+                }).collect()
+            },
+            Formation::Random { count } => {
+                let count = (*count as f32 * difficulty).round() as u32;
+
+                (0..count).map(|i| Vec2::new(
+                    rng.gen_range(-WINDOW_WIDTH / 2.0..WINDOW_WIDTH / 2.0),
+                    i as f32 * ASTEROID_SCALED_RADIUS * 2.0
+                )).collect()
+            }
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum WaveScriptError {
+    #[error("the script has no waves")]
+    NoWaves,
+    #[error("wave \"{0}\" has no events")]
+    EmptyWave(String),
+    #[error("wave \"{wave}\", event {event}: {reason}")]
+    InvalidEvent { wave: String, event: usize, reason: String },
+    #[error("difficulty_per_loop must not be negative")]
+    NegativeDifficulty,
+    #[error("a looping script needs at least one delay, distance or pause, it would spawn forever")]
+    InstantLoop,
+}
+
+impl WaveScript {
+
+    pub fn validate(&self) -> Result<(), WaveScriptError> {
+        if self.waves.is_empty() {
+            return Err(WaveScriptError::NoWaves);
+        }
+
+        if self.difficulty_per_loop < 0.0 {
+            return Err(WaveScriptError::NegativeDifficulty);
+        }
+
+        let mut takes_time = false;
+
+        for wave in &self.waves {
+            if wave.events.is_empty() {
+                return Err(WaveScriptError::EmptyWave(wave.name.clone()));
+            }
+
+            for (index, event) in wave.events.iter().enumerate() {
+                let invalid = |reason: &str| WaveScriptError::InvalidEvent {
+                    wave: wave.name.clone(),
+                    event: index,
+                    reason: reason.to_string()
+                };
+
+                match event.trigger {
+                    WaveTrigger::After(seconds) | WaveTrigger::Distance(seconds) if seconds < 0.0 || !seconds.is_finite() => {
+                        return Err(invalid("triggers must be finite and not negative"));
+                    },
+                    WaveTrigger::After(amount) | WaveTrigger::Distance(amount) => {
+                        takes_time |= amount > 0.0;
+                    }
+                }
+
+                match &event.action {
+                    WaveAction::Boss(path) => {
+                        if !path.ends_with(".boss.ron") {
+                            return Err(invalid("bosses are loaded from .boss.ron files"));
+                        }
+
+                        takes_time = true;
+                    },
+                    WaveAction::Pause(seconds) => {
+                        if *seconds <= 0.0 || !seconds.is_finite() {
+                            return Err(invalid("pauses must be longer than zero seconds"));
+                        }
+
+                        takes_time = true;
+                    },
+                    WaveAction::Asteroids { formation, .. } |
+                    WaveAction::Enemies { formation, .. } |
+                    WaveAction::Pickups { formation, .. } => {
+                        if formation.count() == 0 {
+                            return Err(invalid("formations need at least one member"));
+                        }
+
+                        match formation {
+                            Formation::Line { spacing, .. } |
+                            Formation::Column { spacing, .. } |
+                            Formation::V { spacing, .. } if *spacing < 0.0 => {
+                                return Err(invalid("spacing must not be negative"));
+                            },
+                            Formation::Single { x } | Formation::Column { x, .. } if x.abs() > WINDOW_WIDTH / 2.0 => {
+                                return Err(invalid("x is outside of the screen"));
+                            },
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+
+        if self.looping && !takes_time {
+            return Err(WaveScriptError::InstantLoop);
+        }
+
+        Ok(())
+    }
+
+    pub fn boss_paths(&self) -> impl Iterator<Item = &String> {
+        self.waves.iter()
+            .flat_map(|wave| wave.events.iter())
+            .filter_map(|event| match &event.action {
+                WaveAction::Boss(path) => Some(path),
+                _ => None
+            })
+    }
+
+    /// The handle the loader took for the boss at `path`
+    pub fn boss(&self, path: &str) -> Option<Handle<BossDefinition>> {
+        self.boss_paths().position(|boss_path| boss_path == path)
+            .and_then(|index| self.bosses.get(index).cloned())
+    }
+
+    fn event(&self, wave: usize, event: usize) -> Option<&WaveEvent> {
+        self.waves.get(wave).and_then(|wave| wave.events.get(event))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpawnKind {
+    /// A random type when `None`
+    Asteroid(Option<AsteroidType>),
+    Enemy(EnemyPattern),
+    Pickup(PickupKind),
+    Boss(String),
+}
+
+/// Something the director wants spawned this tick
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpawnRequest {
+    pub kind: SpawnKind,
+    /// x from the center of the screen, y above the top of it
+    pub offset: Vec2,
+    pub speed_multiplier: f32,
+    /// Formation members fly straight down so the shape holds together
+    pub keep_formation: bool,
+}
+
+/// Where the director is in its script. Kept apart from any ECS state so it can be
+/// stepped on its own.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WaveProgress {
+    pub wave: usize,
+    pub event: usize,
+    pub time_since_event: f32,
+    pub distance_since_event: f32,
+    pub pause_left: f32,
+    pub loops: u32,
+    pub finished: bool,
+}
+
+impl WaveProgress {
+
+    pub fn difficulty(&self, script: &WaveScript) -> f32 {
+        1.0 + self.loops as f32 * script.difficulty_per_loop
+    }
+
+    /// Steps the script by `delta_seconds` of game time and `distance` scrolled,
+    /// returning everything that has to spawn
+    pub fn advance(
+        &mut self,
+        script: &WaveScript,
+        delta_seconds: f32,
+        distance: f32,
+        rng: &mut impl Rng
+    ) -> Vec<SpawnRequest> {
+
+        let mut spawns = Vec::new();
+
+        if self.finished { return spawns; }
+
+        if self.pause_left > 0.0 {
+            self.pause_left -= delta_seconds;
+
+            if self.pause_left > 0.0 { return spawns; }
+
+            // Whatever is left of this tick counts towards the next event
+            self.time_since_event = -self.pause_left;
+            self.distance_since_event = 0.0;
+            self.pause_left = 0.0;
+        } else {
+            self.time_since_event += delta_seconds;
+            self.distance_since_event += distance;
+        }
+
+        loop {
+            let Some(event) = script.event(self.wave, self.event) else {
+                if script.looping && self.wave > 0 {
+                    self.wave = 0;
+                    self.event = 0;
+                    self.loops += 1;
+                    continue;
+                }
+
+                self.finished = true;
+                break;
+            };
+
+            let difficulty = self.difficulty(script);
+
+            let ready = match event.trigger {
+                WaveTrigger::After(seconds) => self.time_since_event >= seconds / difficulty,
+                WaveTrigger::Distance(distance) => self.distance_since_event >= distance,
+            };
+
+            if !ready { break; }
+
+            if let WaveTrigger::After(seconds) = event.trigger {
+                self.time_since_event -= seconds / difficulty;
+            }
+            self.distance_since_event = 0.0;
+
+            self.event += 1;
+
+            if self.event >= script.waves[self.wave].events.len() {
+                self.wave += 1;
+                self.event = 0;
+            }
+
+            let (formation, kind) = match &event.action {
+                WaveAction::Pause(seconds) => {
+                    self.pause_left = seconds / difficulty;
+                    self.time_since_event = 0.0;
+                    break;
+                },
+                WaveAction::Asteroids { formation, asteroid_type } => (formation, SpawnKind::Asteroid(*asteroid_type)),
+                WaveAction::Enemies { formation, pattern } => (formation, SpawnKind::Enemy(*pattern)),
+                WaveAction::Pickups { formation, kind } => (formation, SpawnKind::Pickup(*kind)),
+                WaveAction::Boss(path) => {
+                    spawns.push(SpawnRequest {
+                        kind: SpawnKind::Boss(path.clone()),
+                        offset: Vec2::ZERO,
+                        speed_multiplier: difficulty,
+                        keep_formation: false
+                    });
+
+                    // Everything after the boss waits for the fight to end
+                    break;
+                }
+            };
+
+            let keep_formation = !matches!(formation, Formation::Random { .. });
+
+            spawns.extend(formation.offsets(difficulty, rng).into_iter().map(|offset| SpawnRequest {
+                kind: kind.clone(),
+                offset,
+                speed_multiplier: difficulty,
+                keep_formation
+            }));
+        }
+
+        spawns
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct WaveDirector {
+    pub script: Handle<WaveScript>,
+    pub progress: WaveProgress,
+    pub last_camera_y: f32,
+    bosses_tracked: bool,
+}
+
+fn load_wave_script(mut commands: Commands, asset_server: Res<AssetServer>, mut preload: ResMut<Preload>) {
+    let script = asset_server.load(DEFAULT_WAVE_SCRIPT);
+
+    preload.track(DEFAULT_WAVE_SCRIPT, &script);
+
+    commands.insert_resource(WaveDirector {
+        script,
+        progress: WaveProgress::default(),
+        last_camera_y: 0.0,
+        bosses_tracked: false,
+    });
+}
+
+/// The bosses are only known once the script is in, the loading screen waits for them too
+fn track_bosses(
+    mut director: ResMut<WaveDirector>,
+    scripts: Res<Assets<WaveScript>>,
+    mut preload: ResMut<Preload>,
+) {
+    if director.bosses_tracked { return; }
+
+    let Some(script) = scripts.get(&director.script) else { return; };
+
+    for (path, boss) in script.boss_paths().zip(script.bosses.iter()) {
+        preload.track(path.clone(), boss);
+    }
+
+    director.bosses_tracked = true;
+}
+
+/// Steps the wave script and spawns whatever it asks for above the top of the screen
+#[allow(clippy::too_many_arguments)]
+pub fn run_wave_director(
+    mut commands: Commands,
+    mut director: ResMut<WaveDirector>,
+    scripts: Res<Assets<WaveScript>>,
+    mut boss_fight: ResMut<BossFight>,
+    asteroid_query: Query<(), With<Asteroid>>,
+    camera_query: Query<&Transform, With<GameCamera>>,
+    time: Res<Time<Fixed>>,
+    scroll_speed: Res<ScrollSpeed>,
+    game_assets: Res<GameAssets>,
+    mut pools: ResMut<EntityPools>,
+    mut rng: ResMut<GameRng>,
+    asset_server: Res<AssetServer>,
+) {
+
+    let camera_transform = camera_query.single();
+
+    let distance = camera_transform.translation.y - director.last_camera_y;
+    director.last_camera_y = camera_transform.translation.y;
+
+    // The script waits for the boss to be beaten
+    if boss_fight.active() {
+        return;
+    }
+
+    // Nothing to do until the script is loaded
+    let Some(script) = scripts.get(&director.script) else {
+        return;
+    };
+
+    // Asteroids leave the screen quicker at speed, so more of them fit before it is full.
+    // Timed events speed up with the scroll too, keeping the density per distance the same
+    let speed_factor = scroll_speed.factor();
+    let max_asteroids = (script.max_asteroids as f32 * speed_factor.max(1.0)).round() as usize;
+
+    // Hold the script while the screen is full, it picks up where it left off
+    if asteroid_query.iter().count() >= max_asteroids {
+        return;
+    }
+
+    let spawns = director.progress.advance(script, time.delta_seconds() * speed_factor, distance, &mut rng.0);
+
+    for spawn in spawns {
+        let asteroid_type = match spawn.kind {
+            SpawnKind::Asteroid(asteroid_type) => asteroid_type.unwrap_or_else(|| AsteroidType::random(&mut rng.0)),
+            SpawnKind::Boss(path) => {
+                boss_fight.pending = Some(script.boss(&path).unwrap_or_else(|| asset_server.load(path)));
+                continue;
+            },
+            SpawnKind::Enemy(pattern) => {
+                let spawn_position = Vec3::new(
+                    spawn.offset.x.clamp(LEFT_WALL + ENEMY_SHIP_RADIUS, RIGHT_WALL - ENEMY_SHIP_RADIUS),
+                    TOP_WALL + ENEMY_SHIP_RADIUS + camera_transform.translation.y + spawn.offset.y,
+                    0.0
+                );
+
+                commands.spawn(EnemyShipBundle::new(
+                    &game_assets,
+                    spawn_position,
+                    pattern,
+                    spawn.speed_multiplier
+                ));
+
+                continue;
+            },
+            SpawnKind::Pickup(kind) => {
+                // Pickups stay where they are put, the ship scrolls up to them
+                let spawn_position = Vec2::new(
+                    spawn.offset.x.clamp(LEFT_WALL + PICKUP_RADIUS, RIGHT_WALL - PICKUP_RADIUS),
+                    TOP_WALL + PICKUP_RADIUS + camera_transform.translation.y + spawn.offset.y
+                );
+
+                spawn_pickup(&mut commands, kind, spawn_position);
+
+                continue;
+            }
+        };
+
+        let radius = asteroid_type.radius();
+
+        let spawn_position = Vec3::new(
+            spawn.offset.x.clamp(LEFT_WALL + radius, RIGHT_WALL - radius),
+            TOP_WALL + 100.0 + camera_transform.translation.y + spawn.offset.y,
+            0.0
+        );
+
+        let mut asteroid_bundle = AsteroidBundle::new(
+            &game_assets,
+            camera_transform,
+            Some(spawn_position),
+            asteroid_type,
+            &mut rng.0
+        );
+
+        if spawn.keep_formation {
+            asteroid_bundle.velocity = Velocity(Vec2::NEG_Y * asteroid_type.speed());
+        }
+
+        asteroid_bundle.velocity.0 *= spawn.speed_multiplier;
+
+        pools.spawn(&mut commands, PoolKind::Asteroid, asteroid_bundle);
+    }
+}
+
+#[derive(Default)]
+pub struct WaveScriptLoader;
+
+#[derive(Error, Debug)]
+pub enum WaveScriptLoaderError {
+    #[error("could not read wave script: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse wave script: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("invalid wave script: {0}")]
+    Invalid(#[from] WaveScriptError),
+}
+
+impl AssetLoader for WaveScriptLoader {
+    type Asset = WaveScript;
+    type Settings = ();
+    type Error = WaveScriptLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<WaveScript, WaveScriptLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            let mut script: WaveScript = ron::de::from_bytes(&bytes)?;
+            script.validate()?;
+
+            let boss_paths: Vec<String> = script.boss_paths().cloned().collect();
+            script.bosses = boss_paths.into_iter().map(|path| load_context.load(path)).collect();
+
+            Ok(script)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["waves.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn parse(source: &str) -> WaveScript {
+        ron::from_str(source).unwrap()
+    }
+
+    /// Runs the script at 64 ticks a second, the fixed timestep, scrolling at the default speed
+    fn run(script: &WaveScript, seconds: f32) -> (WaveProgress, Vec<(f32, SpawnRequest)>) {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut progress = WaveProgress::default();
+        let mut spawned = Vec::new();
+
+        let delta = 1.0 / 64.0;
+
+        for tick in 0..(seconds / delta) as u32 {
+            let now = (tick + 1) as f32 * delta;
+
+            for request in progress.advance(script, delta, KINEMATIC_OBJECTS_SPEED * delta, &mut rng) {
+                spawned.push((now, request));
+            }
+        }
+
+        (progress, spawned)
+    }
+
+    #[test]
+    fn default_script_is_valid() {
+        let source = std::fs::read_to_string(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/assets/", "waves/default.waves.ron")
+        ).unwrap();
+
+        let script: WaveScript = ron::from_str(&source).unwrap();
+
+        assert_eq!(script.validate(), Ok(()));
+
+        for path in script.boss_paths() {
+            assert!(std::path::Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/assets")).join(path).exists(), "{path} is missing");
+        }
+
+        let (progress, spawned) = run(&script, 120.0);
+        assert!(!spawned.is_empty());
+        assert!(script.looping && progress.loops > 0);
+    }
+
+    #[test]
+    fn timed_and_distance_events_fire_in_order() {
+        let script = parse(r#"(
+            waves: [
+                (name: "one", events: [
+                    (trigger: After(1.0), action: Asteroids(formation: Single(x: 0.0), asteroid_type: Some(Small))),
+                    (trigger: Distance(300.0), action: Asteroids(formation: Line(count: 3, spacing: 60.0), asteroid_type: Some(Large))),
+                ]),
+            ],
+        )"#);
+
+        let (progress, spawned) = run(&script, 5.0);
+
+        assert!(progress.finished);
+        assert_eq!(spawned.len(), 4);
+
+        assert_eq!(spawned[0].1.kind, SpawnKind::Asteroid(Some(AsteroidType::Small)));
+        assert!((spawned[0].0 - 1.0).abs() < 0.02);
+
+        // 300 units at the default scroll speed takes two seconds
+        assert!(spawned[1..].iter().all(|(time, request)| (time - 3.0).abs() < 0.02 && request.kind == SpawnKind::Asteroid(Some(AsteroidType::Large))));
+
+        let xs: Vec<f32> = spawned[1..].iter().map(|(_, request)| request.offset.x).collect();
+        assert_eq!(xs, vec![-60.0, 0.0, 60.0]);
+    }
+
+    #[test]
+    fn enemy_waves_spawn_their_pattern() {
+        let script = parse(r#"(
+            waves: [
+                (name: "enemies", events: [
+                    (trigger: After(0.5), action: Enemies(formation: V(count: 3, spacing: 40.0), pattern: Weave)),
+                ]),
+            ],
+        )"#);
+
+        let (_, spawned) = run(&script, 1.0);
+
+        assert_eq!(spawned.len(), 3);
+        assert!(spawned.iter().all(|(_, request)| request.kind == SpawnKind::Enemy(EnemyPattern::Weave) && request.keep_formation));
+
+        let offsets: Vec<Vec2> = spawned.iter().map(|(_, request)| request.offset).collect();
+        assert_eq!(offsets, vec![Vec2::ZERO, Vec2::new(-40.0, 40.0), Vec2::new(40.0, 40.0)]);
+    }
+
+    #[test]
+    fn pickup_waves_spawn_their_kind() {
+        let script = parse(r#"(
+            waves: [
+                (name: "supplies", events: [
+                    (trigger: Distance(150.0), action: Pickups(formation: Line(count: 2, spacing: 100.0), kind: Shield)),
+                ]),
+            ],
+        )"#);
+
+        let (_, spawned) = run(&script, 2.0);
+
+        assert_eq!(spawned.len(), 2);
+        assert!(spawned.iter().all(|(time, request)| (time - 1.0).abs() < 0.02 && request.kind == SpawnKind::Pickup(PickupKind::Shield)));
+
+        let xs: Vec<f32> = spawned.iter().map(|(_, request)| request.offset.x).collect();
+        assert_eq!(xs, vec![-50.0, 50.0]);
+    }
+
+    #[test]
+    fn boss_events_end_the_tick() {
+        let script = parse(r#"(
+            waves: [
+                (name: "boss", events: [
+                    (trigger: After(0.0), action: Boss("bosses/mothership.boss.ron")),
+                    (trigger: After(0.0), action: Asteroids(formation: Single(x: 0.0))),
+                ]),
+            ],
+        )"#);
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut progress = WaveProgress::default();
+
+        let first_tick = progress.advance(&script, 0.1, 0.0, &mut rng);
+        assert_eq!(first_tick.len(), 1);
+        assert_eq!(first_tick[0].kind, SpawnKind::Boss("bosses/mothership.boss.ron".to_string()));
+
+        // The director only steps the script again once the fight is over
+        let after_the_fight = progress.advance(&script, 0.1, 0.0, &mut rng);
+        assert_eq!(after_the_fight[0].kind, SpawnKind::Asteroid(None));
+    }
+
+    #[test]
+    fn pauses_hold_the_script() {
+        let script = parse(r#"(
+            waves: [
+                (name: "pause", events: [
+                    (trigger: After(0.0), action: Pause(2.0)),
+                    (trigger: After(0.5), action: Asteroids(formation: Single(x: 10.0))),
+                ]),
+            ],
+        )"#);
+
+        let (_, spawned) = run(&script, 4.0);
+
+        assert_eq!(spawned.len(), 1);
+        assert!((spawned[0].0 - 2.5).abs() < 0.02);
+        assert_eq!(spawned[0].1.kind, SpawnKind::Asteroid(None));
+    }
+
+    #[test]
+    fn looping_escalates_difficulty() {
+        let script = parse(r#"(
+            looping: true,
+            difficulty_per_loop: 1.0,
+            waves: [
+                (name: "loop", events: [
+                    (trigger: After(1.0), action: Asteroids(formation: Random(count: 2))),
+                ]),
+            ],
+        )"#);
+
+        let (progress, spawned) = run(&script, 1.6);
+
+        // 1s for the first loop, 0.5s for the second one at double difficulty
+        assert_eq!(progress.loops, 2);
+        assert_eq!(spawned.len(), 2 + 4);
+        assert_eq!(spawned[0].1.speed_multiplier, 1.0);
+        assert_eq!(spawned[5].1.speed_multiplier, 2.0);
+        assert!(spawned.iter().all(|(_, request)| !request.keep_formation));
+    }
+
+    #[test]
+    fn invalid_scripts_are_rejected() {
+        let no_waves = parse("(waves: [])");
+        assert_eq!(no_waves.validate(), Err(WaveScriptError::NoWaves));
+
+        let instant_loop = parse(r#"(
+            looping: true,
+            waves: [(name: "spin", events: [(trigger: After(0.0), action: Asteroids(formation: Single(x: 0.0)))])],
+        )"#);
+        assert_eq!(instant_loop.validate(), Err(WaveScriptError::InstantLoop));
+
+        let empty_formation = parse(r#"(
+            waves: [(name: "empty", events: [(trigger: After(1.0), action: Asteroids(formation: Line(count: 0, spacing: 10.0)))])],
+        )"#);
+        assert!(matches!(empty_formation.validate(), Err(WaveScriptError::InvalidEvent { event: 0, .. })));
+
+        let off_screen = parse(r#"(
+            waves: [(name: "far", events: [(trigger: Distance(-5.0), action: Asteroids(formation: Single(x: 0.0)))])],
+        )"#);
+        assert!(matches!(off_screen.validate(), Err(WaveScriptError::InvalidEvent { .. })));
+    }
+}
@@ -0,0 +1,586 @@
+use bevy::prelude::*;
+
+use crate::{
+    asteroid::Asteroid,
+    constants::*,
+    game_assets::GameAssets,
+    pool::{EntityPools, PoolKind},
+    ship::{RocketBundle, Rocket, Ship},
+    CollidableComponentNames,
+    GameCamera,
+    Grid,
+    MyGameSet,
+    Velocity
+};
+
+/// The ship's weapons. Every weapon is a `WeaponDefinition` in the `WeaponArsenal`
+/// resource, other plugins can add their own with `WeaponArsenal::register`.
+pub struct WeaponPlugin;
+
+impl Plugin for WeaponPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<WeaponArsenal>()
+            .add_systems(Update, (
+                switch_weapon,
+                steer_homing_rockets.after(crate::update_grid).before(crate::update_active_rockets),
+            ).in_set(MyGameSet));
+    }
+}
+
+/// One projectile of a volley, relative to the ship
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shot {
+    pub offset: Vec2,
+    pub direction: Vec2,
+}
+
+/// Where the projectiles of a single trigger pull go
+pub trait FiringPattern: Send + Sync + 'static {
+    fn shots(&self) -> Vec<Shot>;
+}
+
+pub struct SingleShot;
+
+impl FiringPattern for SingleShot {
+    fn shots(&self) -> Vec<Shot> {
+        vec![Shot { offset: Vec2::ZERO, direction: Vec2::Y }]
+    }
+}
+
+/// Two parallel shots, `spacing` apart
+pub struct TwinShot {
+    pub spacing: f32,
+}
+
+impl FiringPattern for TwinShot {
+    fn shots(&self) -> Vec<Shot> {
+        vec![
+            Shot { offset: Vec2::new(-self.spacing / 2.0, 0.0), direction: Vec2::Y },
+            Shot { offset: Vec2::new(self.spacing / 2.0, 0.0), direction: Vec2::Y },
+        ]
+    }
+}
+
+/// A fan of `count` shots, `angle` degrees wide
+pub struct SpreadShot {
+    pub count: u32,
+    pub angle: f32,
+}
+
+impl FiringPattern for SpreadShot {
+    fn shots(&self) -> Vec<Shot> {
+        if self.count <= 1 {
+            return SingleShot.shots();
+        }
+
+        let step = self.angle.to_radians() / (self.count as f32 - 1.0);
+        let start = -self.angle.to_radians() / 2.0;
+
+        (0..self.count).map(|i| Shot {
+            offset: Vec2::ZERO,
+            direction: Vec2::from_angle(start + step * i as f32).rotate(Vec2::Y),
+        }).collect()
+    }
+}
+
+pub const ROCKET_TEXTURE: &str = "weapons/rocket_sprites_3.png";
+pub const MISSILE_TEXTURE: &str = "weapons/missile_sprites.png";
+pub const LASER_TEXTURE: &str = "weapons/laser_sprites.png";
+
+#[derive(Debug, Clone)]
+pub struct ProjectileSpec {
+    pub texture: &'static str,
+    pub color: Color,
+    /// Also the hit box, see `Rocket::check_collision`
+    pub scale: Vec3,
+    pub damage: u32,
+    pub speed: f32,
+    /// Keeps going after a hit
+    pub pierce: bool,
+    /// Flies straight when `None`
+    pub homing: Option<HomingSpec>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HomingSpec {
+    /// Radians per second
+    pub turn_rate: f32,
+    /// Targets are only picked up this many degrees either side of the heading
+    pub cone: f32,
+    /// Seconds before the missile self-destructs
+    pub lifetime: f32,
+}
+
+impl Default for ProjectileSpec {
+    fn default() -> ProjectileSpec {
+        ProjectileSpec {
+            texture: ROCKET_TEXTURE,
+            color: Color::WHITE,
+            scale: ROCKET_APPLIED_SCALE,
+            damage: 1,
+            speed: 300.0,
+            pierce: false,
+            homing: None,
+        }
+    }
+}
+
+pub struct WeaponDefinition {
+    pub name: String,
+    /// Seconds between trigger pulls
+    pub cooldown: f32,
+    pub projectile: ProjectileSpec,
+    pub pattern: Box<dyn FiringPattern>,
+}
+
+#[derive(Resource)]
+pub struct WeaponArsenal {
+    weapons: Vec<WeaponDefinition>,
+}
+
+impl WeaponArsenal {
+    pub fn register(&mut self, weapon: WeaponDefinition) -> usize {
+        self.weapons.push(weapon);
+        self.weapons.len() - 1
+    }
+
+    pub fn get(&self, index: usize) -> Option<&WeaponDefinition> {
+        self.weapons.get(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &WeaponDefinition> {
+        self.weapons.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.weapons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.weapons.is_empty()
+    }
+}
+
+impl Default for WeaponArsenal {
+    fn default() -> WeaponArsenal {
+        let mut arsenal = WeaponArsenal { weapons: Vec::new() };
+
+        arsenal.register(WeaponDefinition {
+            name: "Rocket".to_string(),
+            cooldown: 0.5,
+            projectile: ProjectileSpec::default(),
+            pattern: Box::new(SingleShot),
+        });
+
+        arsenal.register(WeaponDefinition {
+            name: "Twin".to_string(),
+            cooldown: 0.45,
+            projectile: ProjectileSpec::default(),
+            pattern: Box::new(TwinShot { spacing: 18.0 }),
+        });
+
+        arsenal.register(WeaponDefinition {
+            name: "Spread".to_string(),
+            cooldown: 0.7,
+            projectile: ProjectileSpec {
+                color: Color::rgb(1.0, 0.85, 0.5),
+                speed: 260.0,
+                ..default()
+            },
+            pattern: Box::new(SpreadShot { count: 5, angle: 50.0 }),
+        });
+
+        arsenal.register(WeaponDefinition {
+            name: "Homing".to_string(),
+            cooldown: 0.8,
+            projectile: ProjectileSpec {
+                texture: MISSILE_TEXTURE,
+                color: Color::rgb(0.6, 1.0, 0.6),
+                damage: 2,
+                speed: 220.0,
+                homing: Some(HomingSpec {
+                    turn_rate: 3.5,
+                    cone: 60.0,
+                    lifetime: 4.0,
+                }),
+                ..default()
+            },
+            pattern: Box::new(SingleShot),
+        });
+
+        arsenal.register(WeaponDefinition {
+            name: "Laser".to_string(),
+            cooldown: 0.3,
+            projectile: ProjectileSpec {
+                texture: LASER_TEXTURE,
+                color: Color::rgb(0.4, 0.9, 1.0),
+                scale: Vec3::new(1.0, 4.0, 1.0),
+                speed: 600.0,
+                pierce: true,
+                ..default()
+            },
+            pattern: Box::new(SingleShot),
+        });
+
+        arsenal
+    }
+}
+
+/// The ship's current weapon and its cooldown
+#[derive(Component, Debug)]
+pub struct Weapon {
+    /// Index into the `WeaponArsenal`
+    pub current: usize,
+    pub cooldown_left: f32,
+    /// Scales every weapon's cooldown, lower fires faster
+    pub cooldown_multiplier: f32,
+}
+
+impl Default for Weapon {
+    fn default() -> Weapon {
+        Weapon {
+            current: 0,
+            cooldown_left: 0.0,
+            cooldown_multiplier: 1.0,
+        }
+    }
+}
+
+impl Weapon {
+    /// Counts the cooldown down and returns true if a volley should go out this frame
+    pub fn trigger(&mut self, definition: &WeaponDefinition, pressed: bool, delta_seconds: f32) -> bool {
+        if self.cooldown_left > 0.0 {
+            self.cooldown_left -= delta_seconds;
+        }
+
+        if !pressed || self.cooldown_left > 0.0 {
+            return false;
+        }
+
+        // Carry over the overshoot so the fire rate doesn't depend on the frame rate
+        self.cooldown_left += definition.cooldown * self.cooldown_multiplier;
+
+        true
+    }
+
+    pub fn select(&mut self, index: usize, arsenal: &WeaponArsenal) {
+        if index < arsenal.len() {
+            self.current = index;
+        }
+    }
+
+    pub fn cycle(&mut self, step: i32, arsenal: &WeaponArsenal) {
+        if arsenal.is_empty() { return; }
+
+        let count = arsenal.len() as i32;
+
+        self.select((self.current as i32 + step).rem_euclid(count) as usize, arsenal);
+    }
+}
+
+/// Spawns one volley of `definition` from `origin`
+pub fn fire_weapon(
+    commands: &mut Commands,
+    game_assets: &GameAssets,
+    pools: &mut EntityPools,
+    definition: &WeaponDefinition,
+    origin: Vec3,
+) {
+    for shot in definition.pattern.shots() {
+        let rocket_bundle = RocketBundle::new(
+            game_assets,
+            &definition.projectile,
+            origin + shot.offset.extend(0.0),
+            shot.direction
+        );
+
+        let rocket = pools.spawn(commands, PoolKind::Rocket, rocket_bundle);
+
+        if let Some(homing) = definition.projectile.homing {
+            commands.entity(rocket).insert(Homing {
+                turn_rate: homing.turn_rate,
+                cone: homing.cone.to_radians(),
+                target: None,
+                lifetime: Timer::from_seconds(homing.lifetime, TimerMode::Once),
+            });
+        }
+    }
+}
+
+/// Steers a projectile towards the closest asteroid in front of it
+#[derive(Component, Debug)]
+pub struct Homing {
+    /// Radians per second
+    pub turn_rate: f32,
+    /// Half angle in radians
+    pub cone: f32,
+    pub target: Option<Entity>,
+    pub lifetime: Timer,
+}
+
+/// The closest asteroid within `cone` radians of `heading`, searched ring by ring
+/// outwards from the missile's cell so the whole grid is only walked when nothing is close
+pub(crate) fn acquire_target(
+    grid: &Grid,
+    camera_translation: Vec3,
+    position: Vec3,
+    heading: Vec2,
+    cone: f32,
+    is_targetable: impl Fn(Entity) -> bool,
+) -> Option<Entity> {
+
+    let (cell_x, cell_y) = grid.cell_of(position, camera_translation)?;
+
+    let max_ring = grid.cells.len().max(grid.cells[0].len()) as i32;
+
+    let mut best: Option<(Entity, f32)> = None;
+
+    for ring in 0..=max_ring {
+        // Everything in this ring is at least this far away
+        let ring_distance = (ring - 1).max(0) as f32 * grid.grid_size;
+
+        if let Some((_, best_distance)) = best {
+            if best_distance < ring_distance { break; }
+        }
+
+        for dx in -ring..=ring {
+            for dy in -ring..=ring {
+                // Only the outline, the inside was done in earlier rings
+                if dx.abs() != ring && dy.abs() != ring { continue; }
+
+                let x = cell_x as i32 + dx;
+                let y = cell_y as i32 + dy;
+
+                if x < 0 || y < 0 || x as usize >= grid.cells.len() || y as usize >= grid.cells[x as usize].len() { continue; }
+
+                for (entity, name, transform) in &grid.cells[x as usize][y as usize] {
+                    if *name != CollidableComponentNames::Asteroid { continue; }
+
+                    let offset = (transform.translation - position).truncate();
+                    let distance = offset.length();
+
+                    if heading.angle_between(offset).abs() > cone { continue; }
+
+                    if best.is_some_and(|(_, best_distance)| best_distance <= distance) { continue; }
+
+                    if !is_targetable(*entity) { continue; }
+
+                    best = Some((*entity, distance));
+                }
+            }
+        }
+    }
+
+    best.map(|(entity, _)| entity)
+}
+
+/// Turns `velocity` towards `target_direction` by at most `max_angle` radians
+pub fn turn_towards(velocity: Vec2, target_direction: Vec2, max_angle: f32) -> Vec2 {
+    let angle = velocity.angle_between(target_direction);
+
+    if angle.is_nan() { return velocity; }
+
+    Vec2::from_angle(angle.clamp(-max_angle, max_angle)).rotate(velocity)
+}
+
+#[allow(clippy::type_complexity)]
+fn steer_homing_rockets(
+    time: Res<Time>,
+    grid: Res<Grid>,
+    mut rocket_query: Query<(&mut Transform, &mut Velocity, &mut Homing, &mut Rocket)>,
+    asteroid_query: Query<(&Transform, &Asteroid), Without<Rocket>>,
+    camera_query: Query<&Transform, (With<GameCamera>, Without<Rocket>)>,
+) {
+
+    let camera_translation = camera_query.single().translation;
+
+    let is_targetable = |entity: Entity| {
+        asteroid_query.get(entity).is_ok_and(|(_, asteroid)| !asteroid.exploding)
+    };
+
+    for (mut transform, mut velocity, mut homing, mut rocket) in rocket_query.iter_mut() {
+
+        if homing.lifetime.tick(time.delta()).just_finished() {
+            // Self-destruct, update_active_rockets cleans it up
+            rocket.hit_target = true;
+            continue;
+        }
+
+        // Pick a new target when the old one exploded or is gone
+        if !homing.target.is_some_and(is_targetable) {
+            homing.target = acquire_target(
+                &grid,
+                camera_translation,
+                transform.translation,
+                **velocity,
+                homing.cone,
+                is_targetable
+            );
+        }
+
+        let Some((target_transform, _)) = homing.target.and_then(|target| asteroid_query.get(target).ok()) else {
+            continue;
+        };
+
+        let target_direction = (target_transform.translation - transform.translation).truncate();
+
+        **velocity = turn_towards(**velocity, target_direction, homing.turn_rate * time.delta_seconds());
+
+        // The sprite points up
+        transform.rotation = Quat::from_rotation_arc_2d(Vec2::Y, velocity.normalize_or_zero());
+    }
+}
+
+fn switch_weapon(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    arsenal: Res<WeaponArsenal>,
+    mut weapon_query: Query<&mut Weapon, With<Ship>>,
+) {
+
+    let Ok(mut weapon) = weapon_query.get_single_mut() else { return; };
+
+    if keyboard_input.just_pressed(KeyCode::KeyE) {
+        weapon.cycle(1, &arsenal);
+    }
+
+    if keyboard_input.just_pressed(KeyCode::KeyQ) {
+        weapon.cycle(-1, &arsenal);
+    }
+
+    let number_keys = [KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4, KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9];
+
+    for (index, key) in number_keys.iter().enumerate() {
+        if keyboard_input.just_pressed(*key) {
+            weapon.select(index, &arsenal);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_spread_their_shots() {
+        assert_eq!(SingleShot.shots(), vec![Shot { offset: Vec2::ZERO, direction: Vec2::Y }]);
+
+        let twin = TwinShot { spacing: 20.0 }.shots();
+        assert_eq!(twin[0].offset.x, -10.0);
+        assert_eq!(twin[1].offset.x, 10.0);
+
+        let spread = SpreadShot { count: 3, angle: 90.0 }.shots();
+        assert_eq!(spread.len(), 3);
+        assert!((spread[0].direction - Vec2::new(1.0, 1.0).normalize()).length() < 0.001);
+        assert!((spread[1].direction - Vec2::Y).length() < 0.001);
+        assert!((spread[2].direction - Vec2::new(-1.0, 1.0).normalize()).length() < 0.001);
+    }
+
+    #[test]
+    fn cooldown_limits_the_fire_rate() {
+        let arsenal = WeaponArsenal::default();
+        let rocket = arsenal.get(0).unwrap();
+        let mut weapon = Weapon::default();
+
+        // Exact in binary, so the count doesn't hinge on rounding
+        let delta = 1.0 / 64.0;
+        let volleys = (0..640).filter(|_| weapon.trigger(rocket, true, delta)).count();
+
+        // Ten seconds of holding the trigger
+        assert_eq!(volleys, (10.0 / rocket.cooldown) as usize);
+
+        let mut weapon = Weapon { cooldown_multiplier: 0.5, ..default() };
+        let volleys = (0..640).filter(|_| weapon.trigger(rocket, true, delta)).count();
+        assert_eq!(volleys, (10.0 / (rocket.cooldown * 0.5)) as usize);
+
+        assert!(!(0..640).any(|_| weapon.trigger(rocket, false, delta)));
+    }
+
+    #[test]
+    fn weapons_switch_and_wrap_around() {
+        let mut arsenal = WeaponArsenal::default();
+        let mut weapon = Weapon::default();
+
+        weapon.cycle(-1, &arsenal);
+        assert_eq!(weapon.current, arsenal.len() - 1);
+
+        weapon.cycle(1, &arsenal);
+        assert_eq!(weapon.current, 0);
+
+        weapon.select(100, &arsenal);
+        assert_eq!(weapon.current, 0);
+
+        let index = arsenal.register(WeaponDefinition {
+            name: "Test".to_string(),
+            cooldown: 1.0,
+            projectile: ProjectileSpec::default(),
+            pattern: Box::new(SingleShot),
+        });
+
+        weapon.select(index, &arsenal);
+        assert_eq!(arsenal.get(weapon.current).unwrap().name, "Test");
+    }
+
+    fn grid_with(asteroids: &[(Entity, Vec2)]) -> Grid {
+        let mut grid = Grid::new();
+
+        for (entity, position) in asteroids {
+            let (x, y) = grid.cell_of(position.extend(0.0), Vec3::ZERO).unwrap();
+            grid[x][y].push((*entity, CollidableComponentNames::Asteroid, Transform::from_translation(position.extend(0.0))));
+        }
+
+        grid
+    }
+
+    #[test]
+    fn missiles_pick_the_closest_asteroid_in_their_cone() {
+        let near_behind = Entity::from_raw(1);
+        let near_side = Entity::from_raw(2);
+        let far_ahead = Entity::from_raw(3);
+        let close_ahead = Entity::from_raw(4);
+
+        let grid = grid_with(&[
+            (near_behind, Vec2::new(0.0, -30.0)),
+            (near_side, Vec2::new(40.0, 5.0)),
+            (far_ahead, Vec2::new(-20.0, 250.0)),
+            (close_ahead, Vec2::new(30.0, 120.0)),
+        ]);
+
+        let cone = 45.0_f32.to_radians();
+        let all = |_: Entity| true;
+
+        assert_eq!(acquire_target(&grid, Vec3::ZERO, Vec3::ZERO, Vec2::Y, cone, all), Some(close_ahead));
+
+        // Exploding asteroids are skipped, the next one in the cone is picked
+        let not_close = |entity: Entity| entity != close_ahead;
+        assert_eq!(acquire_target(&grid, Vec3::ZERO, Vec3::ZERO, Vec2::Y, cone, not_close), Some(far_ahead));
+
+        // Facing down only the one behind is in the cone
+        assert_eq!(acquire_target(&grid, Vec3::ZERO, Vec3::ZERO, Vec2::NEG_Y, cone, all), Some(near_behind));
+
+        assert_eq!(acquire_target(&grid, Vec3::ZERO, Vec3::ZERO, Vec2::NEG_X, cone, all), None);
+    }
+
+    #[test]
+    fn homing_turns_are_limited() {
+        let velocity = Vec2::new(0.0, 100.0);
+
+        let turned = turn_towards(velocity, Vec2::X, 0.1);
+        assert!((turned.length() - 100.0).abs() < 0.01);
+        assert!((turned.angle_between(velocity).abs() - 0.1).abs() < 0.001);
+        assert!(turned.x > 0.0);
+
+        let on_target = turn_towards(velocity, Vec2::new(0.01, 1.0), 0.1);
+        assert!((on_target.normalize() - Vec2::new(0.01, 1.0).normalize()).length() < 0.001);
+    }
+
+    #[test]
+    fn homing_and_laser_have_their_own_sprites() {
+        let arsenal = WeaponArsenal::default();
+        let texture = |name: &str| arsenal.iter().find(|weapon| weapon.name == name).unwrap().projectile.texture;
+
+        assert_eq!(texture("Homing"), MISSILE_TEXTURE);
+        assert_eq!(texture("Laser"), LASER_TEXTURE);
+        assert_eq!(texture("Rocket"), ROCKET_TEXTURE);
+    }
+}